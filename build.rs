@@ -0,0 +1,77 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Record what this build was made from, for `quicktool version` and bug reports
+fn main() {
+    println!("cargo:rustc-env=QUICKTOOL_GIT_COMMIT={}", git_commit());
+    println!("cargo:rustc-env=QUICKTOOL_BUILD_DATE={}", build_date());
+    println!(
+        "cargo:rustc-env=QUICKTOOL_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    // A new commit changes the branch's ref, which HEAD names
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Ok(head) = fs::read_to_string(".git/HEAD") {
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed=.git/{}", reference);
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// The short commit hash, marked `-dirty` with uncommitted changes, or `unknown` when
+/// built from a source tarball
+fn git_commit() -> String {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(commit) if !commit.is_empty() => {
+            let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+                .is_some_and(|status| !status.is_empty());
+            if dirty {
+                format!("{}-dirty", commit)
+            } else {
+                commit
+            }
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Today's UTC date, or SOURCE_DATE_EPOCH's for reproducible builds, as `YYYY-MM-DD`
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+    // Days since 1970 to a civil date, after Howard Hinnant's days_from_civil inverse
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
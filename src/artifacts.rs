@@ -0,0 +1,94 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Kinds of generated files that pile up in students' course directories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    CoreDump,
+    ValgrindLog,
+    DefaultBinary,
+    ObjectFile,
+    Binary,
+    SwapFile,
+    PythonCache,
+}
+
+impl Artifact {
+    /// Short description shown next to the file
+    pub fn description(self) -> &'static str {
+        match self {
+            Artifact::CoreDump => "core dump",
+            Artifact::ValgrindLog => "valgrind log",
+            Artifact::DefaultBinary => "compiled program (a.out)",
+            Artifact::ObjectFile => "object file",
+            Artifact::Binary => "compiled program",
+            Artifact::SwapFile => "editor swap or backup file",
+            Artifact::PythonCache => "Python bytecode cache",
+        }
+    }
+
+    /// Whether the file can always be regenerated, so it is safe to delete unasked
+    pub fn safe_to_delete(self) -> bool {
+        matches!(
+            self,
+            Artifact::CoreDump
+                | Artifact::DefaultBinary
+                | Artifact::ObjectFile
+                | Artifact::PythonCache
+        )
+    }
+}
+
+/// Recognise a generated file from its name and contents
+pub fn classify(path: &Path) -> Option<Artifact> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    let name = path.file_name()?.to_string_lossy();
+    if metadata.is_dir() && name == "__pycache__" {
+        return Some(Artifact::PythonCache);
+    }
+    if !metadata.is_file() {
+        return None;
+    }
+
+    let is_core_name = name == "core"
+        || name.starts_with("vgcore.")
+        || name
+            .strip_prefix("core.")
+            .is_some_and(|pid| pid.chars().all(|c| c.is_ascii_digit()));
+    if is_core_name && is_elf(path) {
+        return Some(Artifact::CoreDump);
+    }
+    if (name.starts_with("valgrind") && name.ends_with(".log")) || name.ends_with(".valgrind") {
+        return Some(Artifact::ValgrindLog);
+    }
+    if name.ends_with(".pyc") {
+        return Some(Artifact::PythonCache);
+    }
+    if (name.starts_with('.') && (name.ends_with(".swp") || name.ends_with(".swo")))
+        || name.ends_with('~')
+        || (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+    {
+        return Some(Artifact::SwapFile);
+    }
+    if name.ends_with(".o") && is_elf(path) {
+        return Some(Artifact::ObjectFile);
+    }
+    if is_elf(path) {
+        return Some(if name == "a.out" {
+            Artifact::DefaultBinary
+        } else {
+            Artifact::Binary
+        });
+    }
+    None
+}
+
+/// Whether a file starts with the ELF magic number
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && magic == *b"\x7fELF"
+}
@@ -1,23 +1,384 @@
-use crate::config::ClassConfig;
+use clap::error::ErrorKind;
+use clap::{ArgAction, CommandFactory, Parser};
+
+use crate::config::{self, ClassConfig};
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::features;
+use crate::legacy;
+use crate::notices;
+use crate::output::{self, ColorChoice};
+use crate::plugins;
 use crate::shell;
-use crate::tools::{autotest, fetch_activity};
+use crate::temp;
+use crate::tools::{
+    autotest, bench, bug_report, clean, command_help, compare, completions, config_show,
+    deploy_docs, doctor, du, edit, fetch_activity, fingerprint, healthcheck, help_request, history,
+    hook, install_symlinks, list_classes, multi_class, new_file, progress, run, session_env, stats,
+    student, test_all, version, which,
+};
+use crate::trace;
+use crate::ui;
+use crate::user_config::UserConfig;
+use log::LevelFilter;
+use serde_json::json;
 use std::env;
+use std::path::PathBuf;
 use std::process::exit;
 
-/// Parse command line arguments and determine class code and remaining arguments
-fn parse_args() -> (String, Vec<String>) {
+/// Built-in commands with their help text; a class may disable any of them
+pub const BUILT_IN_COMMANDS: &[(&str, &str)] = &[
+    (
+        "autotest",
+        "Run autotest for the current directory, guessing the exercise\n\
+         from its name or fetched activities if none is given, then any\n\
+         unit tests the exercise provides\n\
+         (--report forum also prints a post for the course forum,\n\
+         --ccache auto|on|off controls compiler caching,\n\
+         --fuzz <exercise|program> [--time s] hunts for crashing inputs)",
+    ),
+    (
+        "autotest-stage",
+        "Run autotest for a specific stage (--list shows its tests)",
+    ),
+    (
+        "fetch-activity",
+        "Fetch activity starter code (--update to refresh it)\n\
+         (--source dir fetches from a local activities tree)",
+    ),
+    (
+        "edit",
+        "Open an exercise's files in $VISUAL/$EDITOR (--fetch to fetch it first)",
+    ),
+    (
+        "new",
+        "Create source files from the course template, e.g. new hello.c (--force)",
+    ),
+    (
+        "run",
+        "Run a program, optionally with --stdin file and --expect file\n\
+         (--normalize-eol accepts whitespace-only differences,\n\
+         --show-invisible shows them, --show-codepoints shows differing\n\
+         Unicode characters, --tolerance n and --relative-tolerance n\n\
+         let numbers differ, --transcript records what you type and see,\n\
+         for help requests)",
+    ),
+    (
+        "bench",
+        "Time repeated runs of a program (--input file, --repeat n)",
+    ),
+    (
+        "du",
+        "Show disk usage and quota (--clean removes core dumps and a.out files)",
+    ),
+    (
+        "clean",
+        "Remove compiled programs and other build artifacts (--dry-run to list them)",
+    ),
+    ("dismiss-notice", "Stop showing a class notice"),
+    (
+        "stats",
+        "Show class usage counters to staff (opt-in/opt-out to contribute)",
+    ),
+    (
+        "help-request",
+        "Bundle an exercise's code and autotest output for the course forum",
+    ),
+    (
+        "progress",
+        "Export your exercise progress (--format text|json|csv)",
+    ),
+    (
+        "test-all",
+        "Autotest every exercise fetched below this directory (optionally one week)",
+    ),
+    (
+        "compare",
+        "Compare a program with the reference solution on generated inputs\n\
+         (--count n, --seed n, --normalize-eol, --show-invisible,\n\
+         --show-codepoints)",
+    ),
+    (
+        "fingerprint",
+        "Show the versions and settings your results are produced with (--json)",
+    ),
+    (
+        "config",
+        "Show the configuration quicktool uses for the class (config show [--json])",
+    ),
+    ("student", "Look up students in the class roster"),
+    (
+        "which",
+        "Show which program a command runs in the class environment,\n\
+         following symlinks, and the programs it shadows",
+    ),
+    (
+        "setenv",
+        "Set environment variables for the class's commands and shells for a while\n\
+         (KEY=VALUE... [--for 2h]; without arguments, list them)",
+    ),
+    (
+        "unsetenv",
+        "Remove variables set with setenv (KEY... or --all)",
+    ),
+    (
+        "deploy-docs",
+        "Install man pages for autotest and fetch-activity in the class man directory\n\
+         (--dir DIR writes them elsewhere)",
+    ),
+];
+
+/// Built-in commands only course staff may use; they are hidden from everyone else
+pub const STAFF_COMMANDS: &[&str] = &["student", "deploy-docs"];
+
+/// Commands of quicktool itself, given in place of a class code
+const QUICKTOOL_COMMANDS: &[&str] = &[
+    "--bug-report",
+    "healthcheck",
+    "doctor",
+    "list-classes",
+    "install-symlinks",
+    "history",
+    "--classes",
+    "hook",
+    "version",
+    "--version",
+    "completions",
+    "__complete",
+    "__env",
+];
+
+/// Options given between the class code and the command, e.g. `1511 --strict autotest`
+#[derive(Parser)]
+#[command(
+    disable_help_flag = true,
+    override_usage = "<class> [options] [command]..."
+)]
+struct ClassArgs {
+    /// Turn configuration warnings into errors
+    #[arg(long)]
+    strict: bool,
+    /// Stop with an error unless you are recognised as course staff
+    #[arg(long)]
+    staff: bool,
+    /// Print the commands and file changes a command would make without making them
+    #[arg(long)]
+    dry_run: bool,
+    /// Report fetch-activity, autotest-stage and errors as JSON on stdout
+    #[arg(long)]
+    json: bool,
+    /// Keep temporary files in DIR instead of TMPDIR or the class's choice
+    #[arg(long, value_name = "DIR")]
+    tmpdir: Option<PathBuf>,
+    /// Log what quicktool is doing; -vv logs more
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Log nothing, not even errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// When to colour output; NO_COLOR turns colour off in auto mode
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// The same as --color=never
+    #[arg(long, conflicts_with = "color")]
+    no_color: bool,
+    /// Run heavy work such as autotest at a lower priority, niceness N from 0 to 19
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(i32).range(0..=19)
+    )]
+    nice: Option<i32>,
+    /// Change to DIR before running the command
+    #[arg(long, value_name = "DIR")]
+    chdir: Option<PathBuf>,
+    /// Show the class's commands
+    #[arg(short, long)]
+    help: bool,
+    /// A built-in command, or a command to run in the class environment, as given
+    #[arg(skip)]
+    command: Vec<String>,
+    /// Whether the command came after `--`, so it is run as it is in the class environment
+    #[arg(skip)]
+    passthrough: bool,
+}
+
+/// Parse command line arguments and determine class code, the arguments after it and
+/// what they mean. The class comes from the program's name when run as e.g. `1511`,
+/// then from `quicktool <class>`, then from the user's default_class setting.
+fn parse_args() -> (String, Vec<String>, ClassArgs) {
     let args: Vec<String> = env::args().collect();
-    let program_name = args[0].split('/').last().unwrap_or("quicktool");
+    let program_name = args[0].split('/').next_back().unwrap_or("quicktool");
 
-    if program_name == "quicktool" {
-        if args.len() < 2 {
+    let (class_code, rest) = if program_name == "quicktool" {
+        let named = args
+            .get(1)
+            .filter(|code| config::parse_class_code(code).is_some());
+        if let Some(class_code) = named {
+            (class_code.clone(), &args[2..])
+        } else if let Some(class_code) = UserConfig::load().default_class() {
+            (class_code, &args[1..])
+        } else if args.len() > 1 {
+            (args[1].clone(), &args[2..])
+        } else {
+            // Someone at a terminal can choose a class instead
+            if let Some(class_code) = pick_class() {
+                return (
+                    class_code,
+                    Vec::new(),
+                    ClassArgs::parse_from([program_name]),
+                );
+            }
             eprintln!("Usage: quicktool classname [command]");
-            exit(2);
+            eprintln!("       quicktool command, with default_class = \"classname\" under [user]");
+            eprintln!("       in {}", UserConfig::load().path.display());
+            eprintln!(
+                "       quicktool healthcheck --class classname [--format text|json|prometheus]"
+            );
+            eprintln!("                             [--strict] [--textfile path]");
+            eprintln!("       quicktool --bug-report classname [command]");
+            eprintln!("       quicktool doctor classname");
+            eprintln!("       quicktool list-classes");
+            eprintln!("       quicktool install-symlinks dir [--force] [classname...]");
+            eprintln!("       quicktool history [--class classname] [-n count]");
+            eprintln!("       quicktool --classes classname,classname... [command]");
+            eprintln!("       quicktool hook bash|zsh|fish");
+            eprintln!("       quicktool version [--json]");
+            eprintln!("       quicktool completions bash|zsh|fish");
+            exit(exit_codes::USAGE);
         }
-        (args[1].clone(), args[2..].to_vec())
     } else {
-        (program_name.to_string(), args[1..].to_vec())
+        (program_name.to_string(), &args[1..])
+    };
+    let (options, command, passthrough) = split_class_args(rest);
+    let mut class_args =
+        ClassArgs::parse_from(std::iter::once(&class_code).chain(options).cloned());
+    class_args.command = command.to_vec();
+    class_args.passthrough = passthrough;
+    (class_code, rest.to_vec(), class_args)
+}
+
+/// Split the arguments after the class into quicktool's options and the command. The
+/// options end at the first argument that is not an option, or the value of one, so
+/// everything from the command on is left as it was given, e.g. the `-r` in
+/// `1511 grep -r foo .`. Everything after a `--` is the command too, even if it looks like
+/// an option or names a built-in, e.g. `1511 -- ./a.out --help`; the flag says whether
+/// there was one.
+fn split_class_args(args: &[String]) -> (&[String], &[String], bool) {
+    let command = ClassArgs::command();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            return (&args[..i], &args[i + 1..], true);
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+        // An option such as --chdir DIR takes the next argument as its value
+        let takes_next = !arg.contains('=')
+            && command.get_arguments().any(|option| {
+                option
+                    .get_long()
+                    .is_some_and(|long| *arg == format!("--{}", long))
+                    && option.get_action().takes_values()
+                    && !option.is_require_equals_set()
+            });
+        i += if takes_next { 2 } else { 1 };
+    }
+    let i = i.min(args.len());
+    (&args[..i], &args[i..], false)
+}
+
+/// Ask which installed class to use, when quicktool is run at a terminal without one
+fn pick_class() -> Option<String> {
+    if !ui::interactive() {
+        return None;
+    }
+    let classes = config::installed_classes();
+    if classes.is_empty() {
+        return None;
+    }
+    ui::pick("Choose a class", &classes)
+}
+
+/// Start logging. RUST_LOG applies unless -v, -vv or -q choose a level; otherwise only
+/// errors are logged.
+fn init_logger(verbose: u8, quiet: bool) {
+    let mut builder = env_logger::Builder::from_default_env();
+    let level = match verbose {
+        _ if quiet => Some(LevelFilter::Off),
+        0 => None,
+        1 => Some(LevelFilter::Info),
+        2 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    };
+    if let Some(level) = level {
+        builder.filter_level(level);
+    }
+    builder.init();
+}
+
+/// Parse the options a built-in tool adds to a script it passes arguments on to, wherever
+/// they appear; every other argument goes, in order, to the tool's positional arguments
+pub fn parse_passthrough_args<T: Parser>(
+    class: &str,
+    tool: &str,
+    args: &[String],
+) -> Result<T, QuicktoolError> {
+    let command = T::command();
+    let mut own = Vec::new();
+    let mut passed_on = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            passed_on.extend(iter.cloned());
+            break;
+        }
+        if arg == "-h" || arg == "--help" {
+            own.push(arg.clone());
+            continue;
+        }
+        let name = arg.split('=').next().unwrap_or(arg);
+        let option = command.get_arguments().find(|option| {
+            option
+                .get_long()
+                .is_some_and(|long| name == format!("--{}", long))
+        });
+        match option {
+            Some(option) => {
+                own.push(arg.clone());
+                if option.get_action().takes_values() && !arg.contains('=') {
+                    own.extend(iter.next().cloned());
+                }
+            }
+            None => passed_on.push(arg.clone()),
+        }
     }
+    own.push("--".to_string());
+    own.extend(passed_on);
+    parse_tool_args(class, tool, &own)
+}
+
+/// Parse a built-in tool's arguments, printing its --help and exiting if that was asked for.
+/// Other errors are usage errors.
+pub fn parse_tool_args<T: Parser>(
+    class: &str,
+    tool: &str,
+    args: &[String],
+) -> Result<T, QuicktoolError> {
+    let name = format!("{} {}", class, tool);
+    T::try_parse_from(std::iter::once(&name).chain(args.iter())).map_err(|e| {
+        if e.kind() == ErrorKind::DisplayHelp {
+            let _ = e.print();
+            exit(0);
+        }
+        let message = e.render().to_string();
+        QuicktoolError::Usage(message.trim_start_matches("error: ").trim_end().to_string())
+    })
 }
 
 /// Get class configuration or exit with error if not valid
@@ -35,13 +396,20 @@ fn get_class_config(class_code: &str, program_name: &str) -> ClassConfig {
                 class_code,
                 class_code
             );
-            exit(2);
+            if program_name == "quicktool" {
+                eprintln!(
+                    "To run commands without naming a class, add e.g. default_class = \"1511\" \
+                     under [user] in {}",
+                    UserConfig::load().path.display()
+                );
+            }
+            exit(exit_codes::USAGE);
         }
     }
 }
 
 /// Set up environment variables for the class
-fn setup_environment(class_config: &ClassConfig) {
+pub fn setup_environment(class_config: &ClassConfig) {
     // Save original environment variables
     let noclass_path =
         env::var("noclass_PATH").unwrap_or_else(|_| env::var("PATH").unwrap_or_default());
@@ -58,6 +426,13 @@ fn setup_environment(class_config: &ClassConfig) {
     let manpath = class_config.get_manpath(&noclass_manpath);
 
     if path == noclass_path && manpath == noclass_manpath {
+        if class_config.strict {
+            eprintln!(
+                "Error: no path information for class {} (--strict)",
+                class_config.class
+            );
+            exit(exit_codes::FAILURE);
+        }
         eprintln!(
             "Warning: no path information for class {}",
             class_config.class
@@ -75,66 +450,440 @@ fn setup_environment(class_config: &ClassConfig) {
     if let Some(account) = &class_config.account_name {
         env::set_var("GIVECLASS", account);
     }
+
+    // Variables the user set for the class with setenv
+    session_env::apply(&class_config.class);
+}
+
+/// Make sure this build satisfies the class's declared requirements before running a
+/// built-in tool, falling back to the class's own script when the class allows it
+fn ensure_compatible(class_config: &mut ClassConfig, args: &[String]) {
+    // A class without a readable config.sh declares no requirements
+    let _ = class_config.load_class_config();
+
+    if let Err(problem) = features::check_compatibility(class_config) {
+        eprintln!("quicktool: {}", problem);
+        eprintln!("{}", features::UPGRADE_INSTRUCTIONS);
+        if features::legacy_fallback_allowed(class_config) {
+            eprintln!("quicktool: running the class's own {} instead", args[0]);
+            run_legacy_tool(class_config, args);
+        }
+        exit(exit_codes::FAILURE);
+    }
+}
+
+/// Run the class's own script for a built-in tool, then exit
+fn run_legacy_tool(class_config: &ClassConfig, args: &[String]) -> ! {
+    match legacy::run_legacy(class_config, args) {
+        Ok(_) => exit(exit_codes::SUCCESS),
+        Err(e) => fail(class_config, e),
+    }
 }
 
 /// Display help information
 fn show_help(class_config: &ClassConfig) {
-    println!("Usage: {} [command]", class_config.class);
+    let is_staff = class_config.is_staff();
+    let print_commands = |staff_only: bool| {
+        for (command, description) in BUILT_IN_COMMANDS {
+            if !class_config.tool_enabled(command) || STAFF_COMMANDS.contains(command) != staff_only
+            {
+                continue;
+            }
+            for (index, line) in description.lines().enumerate() {
+                let name = if index == 0 { command } else { "" };
+                println!("  {:<15} {}", name, line);
+            }
+        }
+    };
+
+    println!("Usage: {} [options] [command]", class_config.class);
     println!("Commands:");
-    println!("  help            Display this help message");
-    println!("  autotest        Run autotest for the current directory");
-    println!("  autotest-stage  Run autotest for a specific stage");
-    println!("  fetch-activity  Fetch activity starter code");
+    println!("  help [command]  Display this help message, or a command's in detail");
+    print_commands(false);
+    for plugin in plugins::list(class_config) {
+        println!("  {:<15} Provided by quicktool-{}", plugin, plugin);
+    }
     println!("  ...             Run a command in the class environment");
-    println!("");
+    let class_commands = plugins::class_commands(class_config);
+    if !class_commands.is_empty() {
+        println!();
+        println!("Class-provided commands:");
+        let mut line = String::new();
+        for command in class_commands {
+            if !line.is_empty() && line.len() + command.len() > 78 {
+                println!("{}", line);
+                line.clear();
+            }
+            line = format!("{}  {}", line, command);
+        }
+        println!("{}", line);
+    }
+    if is_staff {
+        println!();
+        println!("Staff commands:");
+        print_commands(true);
+    }
+    println!();
+    println!("autotest, autotest-stage and fetch-activity also accept:");
+    println!("  --legacy        Run the class's own script instead of quicktool's");
+    println!("  --help          Show the options quicktool understands for the command");
+    println!("autotest and autotest-stage warn when the compiler is not the version the class");
+    println!("is marked with; --ignore-toolchain compiles anyway where the class refuses to.");
+    println!();
     println!("If no command is specified, a shell with the class environment will be started.");
+    println!("--strict (or QUICKTOOL_STRICT=1) turns configuration warnings into errors.");
+    println!("--staff stops with an error unless you are recognised as course staff.");
+    println!("--color=auto|always|never (or --no-color, or NO_COLOR=1) controls colour.");
+    println!("-v shows what quicktool is doing (-vv shows more); -q hides its error log.");
+    println!("--dry-run prints what autotest, fetch-activity or a class command would run,");
+    println!("copy or link, without doing it.");
+    println!("--json reports fetch-activity, autotest-stage and errors as JSON on stdout.");
+    println!("--chdir DIR changes to DIR before running the command.");
+    println!("--nice[=N] runs autotest and test-all at niceness N (10 if not given), so they");
+    println!("do not slow down a shared server; a class can set quicktool_nice and");
+    println!("quicktool_ionice (idle, or 0-7) in config.sh to do this by default.");
+    println!("Exit codes:");
+    for (code, meaning) in exit_codes::DOCUMENTED {
+        println!("  {}  {}", code, meaning);
+    }
+    println!("and a program's own status when a program it runs fails.");
+    println!("Options go before the command; the command's own options are passed to it");
+    println!("as they are, e.g. {} grep -r main .", class_config.class);
+    println!("-- runs everything after it in the class environment as it is,");
+    println!("e.g. {} -- ./a.out --help", class_config.class);
+}
+
+/// Report a failed command and exit with its kind's exit code: an `Error:` line on stderr,
+/// or with `--json` an object with the error, its kind and the exit code on stdout
+fn fail(class_config: &ClassConfig, error: impl Into<QuicktoolError>) -> ! {
+    let error = error.into();
+    let code = error.exit_code();
+    if class_config.json {
+        println!(
+            "{}",
+            json!({ "error": error.to_string(), "kind": error.kind(), "exit_code": code })
+        );
+    } else {
+        eprintln!("Error: {}", error);
+    }
+    exit(code);
+}
+
+/// Report why a command that is not tied to a class failed, returning its exit code
+fn report_error(error: QuicktoolError) -> i32 {
+    eprintln!("Error: {}", error);
+    error.exit_code()
+}
+
+/// Handle commands that are not tied to a class: `quicktool <command> ...`
+fn run_quicktool_command(command: &str, args: &[String]) -> Option<i32> {
+    if !QUICKTOOL_COMMANDS.contains(&command) {
+        return None;
+    }
+    init_logger(0, false);
+    match command {
+        "--bug-report" => Some(bug_report::run_bug_report(args).unwrap_or_else(report_error)),
+        "healthcheck" => Some(match healthcheck::run_healthcheck(args) {
+            Ok(code) => code,
+            Err(e) => {
+                // Monitoring reads healthcheck's codes as OK/WARNING/CRITICAL
+                eprintln!("Error: {}", e);
+                healthcheck::Status::Fail.exit_code()
+            }
+        }),
+        "doctor" => Some(doctor::run_doctor(args).unwrap_or_else(report_error)),
+        "list-classes" => Some(list_classes::run_list_classes(args).unwrap_or_else(report_error)),
+        "install-symlinks" => {
+            Some(install_symlinks::run_install_symlinks(args).unwrap_or_else(report_error))
+        }
+        "history" => Some(history::run_history(args).unwrap_or_else(report_error)),
+        "--classes" => Some(multi_class::run_multi_class(args).unwrap_or_else(report_error)),
+        "version" | "--version" => Some(version::run_version(args).unwrap_or_else(report_error)),
+        "hook" | "__env" => {
+            let result = if command == "hook" {
+                hook::run_hook(args)
+            } else {
+                hook::run_env(args)
+            };
+            Some(result.unwrap_or_else(report_error))
+        }
+        "completions" | "__complete" => {
+            let result = if command == "completions" {
+                completions::run_completions(args)
+            } else {
+                completions::run_complete(args)
+            };
+            Some(result.unwrap_or_else(report_error))
+        }
+        _ => None,
+    }
 }
 
 pub fn run() {
+    // Commands of quicktool itself come before any class code
+    let args: Vec<String> = env::args().collect();
+    if args[0].split('/').next_back() == Some("quicktool") && args.len() > 1 {
+        if let Some(code) = run_quicktool_command(&args[1], &args[2..]) {
+            exit(code);
+        }
+    }
+
     // Parse command line arguments
-    let (class_code, remaining_args) = parse_args();
+    let (class_code, rest, class_args) = parse_args();
+    output::set_color_choice(if class_args.no_color {
+        ColorChoice::Never
+    } else {
+        class_args.color
+    });
+    init_logger(class_args.verbose, class_args.quiet);
+    let remaining_args = class_args.command;
     let program_name = env::args()
-        .nth(0)
+        .next()
         .unwrap_or_default()
         .split('/')
-        .last()
+        .next_back()
         .unwrap_or("quicktool")
         .to_string();
 
     // Get class configuration
     let mut class_config = get_class_config(&class_code, &program_name);
 
+    // Run the command in a child and record it, if the user keeps a history
+    if let Some(code) =
+        history::record_invocation(&class_config.class, &program_name, &class_code, &rest)
+    {
+        exit(code);
+    }
+
+    // Global options given before the command
+    class_config.strict = class_args.strict || env::var_os("QUICKTOOL_STRICT").is_some();
+    class_config.dry_run = class_args.dry_run;
+    class_config.json = class_args.json;
+    class_config.nice = class_args.nice;
+    if class_args.help {
+        let _ = class_config.load_class_config();
+        show_help(&class_config);
+        return;
+    }
+    if class_args.staff {
+        let _ = class_config.load_class_config();
+        if !class_config.is_staff() {
+            fail(
+                &class_config,
+                class_config.not_staff_message("running with --staff"),
+            );
+        }
+    }
+
+    // Where quicktool and the commands it runs keep temporary files
+    if let Err(e) = temp::setup(&mut class_config, class_args.tmpdir.as_deref()) {
+        fail(&class_config, e);
+    }
+
+    // Run from another directory, e.g. an editor's task runner running
+    // `1511 --chdir ~/labs/week05 autotest lab05`
+    if let Some(dir) = &class_args.chdir {
+        if let Err(e) = env::set_current_dir(dir) {
+            fail(
+                &class_config,
+                format!("Cannot change to {}: {}", dir.display(), e),
+            );
+        }
+        if let Ok(dir) = env::current_dir() {
+            env::set_var("PWD", dir);
+        }
+    }
+
     // Setup environment for the class
+    let original_env = trace::snapshot_env();
     setup_environment(&class_config);
+    trace::env_changes(&original_env);
+
+    // Count the invocation for the class, if the user opted in
+    if !class_config.dry_run {
+        stats::record_usage(&mut class_config, &remaining_args);
+    }
+
+    // A command after -- is never a built-in or a plugin
+    if class_args.passthrough && !remaining_args.is_empty() {
+        let _ = class_config.load_class_config();
+        shell::execute_command(&class_config, &remaining_args);
+        return;
+    }
+
+    // Refuse built-in commands the class has disabled
+    if let Some(command) = remaining_args.first() {
+        if BUILT_IN_COMMANDS.iter().any(|(name, _)| name == command) {
+            let _ = class_config.load_class_config();
+            if !class_config.tool_enabled(command) {
+                fail(&class_config, class_config.disabled_tool_message(command));
+            }
+            if STAFF_COMMANDS.contains(&command.as_str()) && !class_config.is_staff() {
+                fail(&class_config, class_config.not_staff_message(command));
+            }
+        }
+    }
 
     // Handle commands based on the first argument
-    match remaining_args.get(0).map(|s| s.as_str()) {
+    match remaining_args.first().map(|s| s.as_str()) {
         None => {
             shell::run_shell(&class_config);
         }
         Some("help") => {
-            show_help(&class_config);
+            let _ = class_config.load_class_config();
+            match remaining_args.get(1) {
+                Some(command) => {
+                    if let Err(e) = command_help::print_command_help(&mut class_config, command) {
+                        fail(&class_config, e);
+                    }
+                }
+                None => show_help(&class_config),
+            }
+        }
+        Some("autotest") | Some("autotest-stage") | Some("fetch-activity")
+            if remaining_args.iter().any(|arg| arg == legacy::LEGACY_FLAG) =>
+        {
+            run_legacy_tool(&class_config, &remaining_args);
         }
         Some("autotest") | Some("autotest-stage") => {
+            ensure_compatible(&mut class_config, &remaining_args);
             match autotest::run_test(&mut class_config, &remaining_args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("Error: {}", e);
-                    exit(1);
+                    fail(&class_config, e);
                 }
             }
         }
         Some("fetch-activity") => {
+            ensure_compatible(&mut class_config, &remaining_args);
             match fetch_activity::run_fetch_activity(&mut class_config, &remaining_args[1..]) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("Error: {}", e);
-                    exit(1);
+                    fail(&class_config, e);
                 }
             }
         }
-        Some(_) => {
-            shell::execute_command(&class_config, &remaining_args);
+        Some("edit") => {
+            if let Err(e) = edit::run_edit(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("run") => {
+            if let Err(e) = run::run_program(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("compare") => {
+            if let Err(e) = compare::run_compare(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("bench") => {
+            if let Err(e) = bench::run_bench(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("du") => {
+            if let Err(e) = du::run_du(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("clean") => {
+            if let Err(e) = clean::run_clean(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("fingerprint") => {
+            if let Err(e) = fingerprint::run_fingerprint(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("config") => {
+            if let Err(e) = config_show::run_config(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("new") => {
+            let _ = class_config.load_class_config();
+            if let Err(e) = new_file::run_new(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("setenv") => {
+            if let Err(e) = session_env::run_setenv(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("unsetenv") => {
+            if let Err(e) = session_env::run_unsetenv(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("dismiss-notice") => match remaining_args.get(1) {
+            Some(id) => {
+                if let Err(e) = notices::dismiss_notice(id) {
+                    fail(&class_config, e);
+                }
+            }
+            None => {
+                eprintln!("Usage: {} dismiss-notice id", class_config.class);
+                exit(exit_codes::USAGE);
+            }
+        },
+        Some("stats") => {
+            if let Err(e) = stats::run_stats(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("help-request") => {
+            if let Err(e) = help_request::run_help_request(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("progress") => {
+            if let Err(e) = progress::run_progress(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("test-all") => {
+            if let Err(e) = test_all::run_test_all(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("which") => {
+            let _ = class_config.load_class_config();
+            if let Err(e) = which::run_which(&class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("deploy-docs") => {
+            if let Err(e) = deploy_docs::run_deploy_docs(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some("student") => {
+            if let Err(e) = student::run_student(&mut class_config, &remaining_args[1..]) {
+                fail(&class_config, e);
+            }
+        }
+        Some(command) => {
+            let _ = class_config.load_class_config();
+            // Course staff can add commands as quicktool-<command> programs, git-style, or
+            // as programs in the class bin directory
+            let program = plugins::find(&class_config, command)
+                .or_else(|| plugins::find_class_command(&class_config, command));
+            if program.is_some() && !class_config.tool_enabled(command) {
+                fail(&class_config, class_config.disabled_tool_message(command));
+            }
+            match program {
+                Some(program) => {
+                    env::set_var("QUICKTOOL_CLASS", &class_config.class);
+                    let mut args = vec![program.to_string_lossy().to_string()];
+                    args.extend(remaining_args[1..].iter().cloned());
+                    shell::execute_command(&class_config, &args);
+                }
+                None => shell::execute_command(&class_config, &remaining_args),
+            }
         }
     }
 }
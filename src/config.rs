@@ -1,7 +1,22 @@
+use colored::Colorize;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fs::File;
+use std::env;
+use std::ffi::CStr;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
-use std::path::Path;
+use std::mem::MaybeUninit;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+
+use crate::error::QuicktoolError;
+use crate::paths::Paths;
+use crate::retry;
+use crate::signals::{IoPriority, Priority};
+use crate::state;
+use crate::toml_config;
+use crate::trace;
 
 /// Stores configuration for a specific class
 #[derive(Debug)]
@@ -13,6 +28,16 @@ pub struct ClassConfig {
     pub man_path: Option<String>,
     pub newclassrc_path: Option<String>,
     pub custom_config: HashMap<String, String>,
+    /// Treat configuration warnings as errors
+    pub strict: bool,
+    /// Print the commands and file changes tools would make instead of making them
+    pub dry_run: bool,
+    /// Print results as JSON on stdout for scripts and editors, instead of text
+    pub json: bool,
+    /// Niceness to run heavy work such as autotest at, from `--nice`
+    pub nice: Option<i32>,
+    /// Helper programs already looked up by `Paths::tool`
+    pub tool_paths: RefCell<HashMap<String, Option<PathBuf>>>,
 }
 
 impl ClassConfig {
@@ -33,11 +58,16 @@ impl ClassConfig {
             man_path: None,
             newclassrc_path: None,
             custom_config: HashMap::new(),
+            strict: false,
+            dry_run: false,
+            json: false,
+            nice: None,
+            tool_paths: RefCell::new(HashMap::new()),
         };
 
         // If we have an account name, derive the other paths
         if let Some(account) = &config.account_name {
-            let home_dir = format!("/home/{}", account);
+            let home_dir = class_root().join(account).to_string_lossy().to_string();
             config.home_dir = Some(home_dir.clone());
             config.bin_path = Some(format!("{}/bin", home_dir));
             config.man_path = Some(format!("{}/man", home_dir));
@@ -49,10 +79,12 @@ impl ClassConfig {
 
     /// Parse a bash script and load all environment variables into custom_config
     pub fn load_bash_config(&mut self, file_path: &str) -> io::Result<()> {
-        let file = File::open(file_path)?;
+        let file = retry::retry_io(format!("reading {}", file_path), || File::open(file_path))?;
         let reader = io::BufReader::new(file);
+        // Settings may move the class's helpers
+        self.tool_paths.get_mut().clear();
 
-        for line in reader.lines() {
+        for (number, line) in reader.lines().enumerate() {
             let line = line?;
 
             // Skip comments, empty lines, and common bash constructs
@@ -66,31 +98,109 @@ impl ClassConfig {
             }
 
             // Extract variable assignments
-            if let Some((name, value)) = self.parse_variable_assignment(&line) {
-                self.custom_config.insert(name, value);
+            match parse_variable_assignment(&line) {
+                Some((name, value)) => {
+                    self.custom_config.insert(name, value);
+                }
+                None => self
+                    .config_warning(&format!(
+                        "{}:{}: cannot parse '{}'",
+                        file_path,
+                        number + 1,
+                        line.trim()
+                    ))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
             }
         }
 
-        Ok(())
+        self.load_toml_config()
     }
 
-    /// Parse a variable assignment line from a bash script
-    fn parse_variable_assignment(&self, line: &str) -> Option<(String, String)> {
-        let parts: Vec<&str> = line.splitn(2, '=').collect();
-        if parts.len() == 2 {
-            let name = parts[0].trim().to_string();
-            let mut value = parts[1].trim().to_string();
+    /// The settings files that exist: the class's quicktool.toml, then the user's config.toml
+    pub fn toml_config_files(&self) -> Vec<(PathBuf, bool)> {
+        let mut files = Vec::new();
+        if let Some(home_dir) = &self.home_dir {
+            files.push((Path::new(home_dir).join(toml_config::FILE_NAME), false));
+        }
+        files.push((state::config_dir().join(toml_config::USER_FILE_NAME), true));
+        files.retain(|(path, _)| path.exists());
+        files
+    }
 
-            // Handle quoted values
-            if (value.starts_with('\'') && value.ends_with('\''))
-                || (value.starts_with('"') && value.ends_with('"'))
-            {
-                value = value[1..value.len() - 1].to_string();
+    /// Merge the typed settings in the class's quicktool.toml, then in the user's
+    /// config.toml, over those from config.sh
+    fn load_toml_config(&mut self) -> io::Result<()> {
+        for (path, user) in self.toml_config_files() {
+            let file_path = path.to_string_lossy().to_string();
+            let contents = retry::retry_io(format!("reading {}", file_path), || {
+                fs::read_to_string(&path)
+            })?;
+            let settings = toml_config::parse(&file_path, &contents, user);
+            for warning in settings.warnings {
+                self.config_warning(&warning)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
             }
+            self.custom_config.extend(settings.variables);
+        }
+        Ok(())
+    }
 
-            return Some((name, value));
+    /// Load the class config.sh found through the class tools, for commands that run
+    /// before any tool has loaded it
+    pub fn load_class_config(&mut self) -> io::Result<()> {
+        let config_sh = ["autotest", "fetch-activity"]
+            .iter()
+            .find_map(|tool| self.tool_config_sh(tool))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "config.sh not found"))?;
+        self.load_bash_config(config_sh.to_string_lossy().as_ref())
+    }
+
+    /// Load the class config.sh, for commands that cannot run without it
+    pub fn require_class_config(&mut self) -> Result<(), QuicktoolError> {
+        self.load_class_config().map_err(|e| {
+            QuicktoolError::ConfigMissing(format!(
+                "Could not load config.sh for {}: {}",
+                self.class, e
+            ))
+        })
+    }
+
+    /// Where this class's files and helper programs are
+    pub fn paths(&self) -> Paths<'_> {
+        Paths::new(self)
+    }
+
+    /// Locate the config.sh next to (or one level above) a tool linked from bin_path
+    pub fn tool_config_sh(&self, tool: &str) -> Option<PathBuf> {
+        let link = Path::new(self.bin_path.as_deref()?).join(tool);
+        let resolved = retry::retry_io(format!("resolving {}", link.display()), || {
+            fs::canonicalize(&link)
+        })
+        .ok()?;
+        resolved
+            .ancestors()
+            .skip(1)
+            .take(2)
+            .map(|dir| dir.join("config.sh"))
+            .find(|path| path.exists())
+    }
+
+    /// Report a configuration problem: a warning on stderr normally, an error in strict mode
+    pub fn config_warning(&self, message: &str) -> Result<(), String> {
+        if self.strict {
+            return Err(format!("{} (--strict)", message));
         }
-        None
+        eprintln!("{} {}", "Warning:".yellow().bold(), message);
+        Ok(())
+    }
+
+    /// Get a custom configuration value that the class is expected to set
+    pub fn expect_custom_config(&self, key: &str) -> Result<Option<&String>, String> {
+        let value = self.get_custom_config(key);
+        if value.is_none() {
+            self.config_warning(&format!("{} is not set in config.sh for {}", key, self.class))?;
+        }
+        Ok(value)
     }
 
     /// Get a custom configuration value
@@ -98,6 +208,99 @@ impl ClassConfig {
         self.custom_config.get(key)
     }
 
+    /// How heavy work such as autotest runs is scheduled: at the niceness given with
+    /// `--nice`, or else the class's `quicktool_nice`, and with the I/O priority in the
+    /// class's `quicktool_ionice`, `idle` or a best-effort level from 0 to 7
+    pub fn priority(&self) -> Result<Priority, QuicktoolError> {
+        let mut priority = Priority::default();
+        match (self.nice, self.get_custom_config("quicktool_nice")) {
+            (Some(nice), _) => priority.nice = nice,
+            (None, Some(value)) => match value.parse() {
+                Ok(nice) => priority.nice = nice,
+                Err(_) => self
+                    .config_warning(&format!("quicktool_nice is not a number: '{}'", value))
+                    .map_err(QuicktoolError::ConfigMissing)?,
+            },
+            (None, None) => {}
+        }
+        if let Some(value) = self.get_custom_config("quicktool_ionice") {
+            match IoPriority::parse(value) {
+                Some(io) => priority.io = Some(io),
+                None => self
+                    .config_warning(&format!(
+                        "quicktool_ionice should be idle or a level from 0 to 7, not '{}'",
+                        value
+                    ))
+                    .map_err(QuicktoolError::ConfigMissing)?,
+            }
+        }
+        Ok(priority)
+    }
+
+    /// Whether a quicktool built-in is enabled for this class.
+    ///
+    /// `quicktool_enabled_tools` in config.sh lists the only built-ins a class offers and
+    /// `quicktool_disabled_tools` lists built-ins it turns off.
+    pub fn tool_enabled(&self, tool: &str) -> bool {
+        let listed = |key: &str| {
+            self.get_custom_config(key).map(|tools| {
+                tools
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .any(|name| name == tool)
+            })
+        };
+        listed("quicktool_enabled_tools").unwrap_or(true)
+            && !listed("quicktool_disabled_tools").unwrap_or(false)
+    }
+
+    /// Message shown when a disabled built-in is used
+    pub fn disabled_tool_message(&self, tool: &str) -> String {
+        match self.get_custom_config("quicktool_disabled_message") {
+            Some(message) => format!("{}: {}", tool, message),
+            None => format!("{}: {} is not available for {}", self.class, tool, self.class),
+        }
+    }
+
+    /// Whether the invoking user is course staff: listed in `staff_users` in config.sh, or
+    /// a member of the group named by `staff_group`
+    pub fn is_staff(&self) -> bool {
+        let Some(user) = current_user() else {
+            return false;
+        };
+        let listed = self.get_custom_config("staff_users").is_some_and(|users| {
+            users
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .any(|name| name == user)
+        });
+        listed
+            || self
+                .get_custom_config("staff_group")
+                .is_some_and(|group| user_groups().iter().any(|g| g == group))
+    }
+
+    /// Explain why the invoking user was not recognised as staff
+    pub fn not_staff_message(&self, what: &str) -> String {
+        let user = current_user().unwrap_or_else(|| "you".to_string());
+        let requirement = match (
+            self.get_custom_config("staff_users"),
+            self.get_custom_config("staff_group"),
+        ) {
+            (_, Some(group)) => format!("listed in staff_users or a member of the {} group", group),
+            (Some(_), None) => "listed in staff_users".to_string(),
+            (None, None) => {
+                return format!(
+                    "{} is only available to {} staff, and the class does not list its staff \
+                     (staff_users or staff_group in config.sh)",
+                    what, self.class
+                )
+            }
+        };
+        format!(
+            "{} is only available to {} staff, and {} is not {}",
+            what, self.class, user, requirement
+        )
+    }
+
     /// Check if the class has a .newclassrc file
     pub fn has_newclassrc(&self) -> bool {
         if let Some(path) = &self.newclassrc_path {
@@ -126,17 +329,84 @@ impl ClassConfig {
     }
 }
 
+/// Login name of the invoking user, looked up from the real uid rather than taken from
+/// USER, which anyone can set to a tutor's name
+pub fn current_user() -> Option<String> {
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut found = ptr::null_mut();
+    // SAFETY: getpwuid_r only writes into the entry and buffer it is given, and sets
+    // `found` to the entry once it has filled it in
+    let status = unsafe {
+        libc::getpwuid_r(
+            libc::getuid(),
+            passwd.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut found,
+        )
+    };
+    if status != 0 || found.is_null() {
+        return None;
+    }
+    // SAFETY: the entry was filled in, and its name points into `buffer`
+    let name = unsafe { CStr::from_ptr((*found).pw_name) };
+    Some(name.to_string_lossy().to_string()).filter(|user| !user.is_empty())
+}
+
+/// Groups the invoking user belongs to
+fn user_groups() -> Vec<String> {
+    let mut command = Command::new("id");
+    command.arg("-Gn");
+    trace::command(&command);
+    match command.output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse a variable assignment line from a bash script
+pub fn parse_variable_assignment(line: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = line.splitn(2, '=').collect();
+    if parts.len() == 2 {
+        let name = parts[0].trim().to_string();
+
+        // Only plain shell variable names are assignments
+        if name.is_empty()
+            || name.starts_with(|c: char| c.is_ascii_digit())
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return None;
+        }
+        let mut value = parts[1].trim().to_string();
+
+        // Handle quoted values
+        if value.len() >= 2
+            && ((value.starts_with('\'') && value.ends_with('\''))
+                || (value.starts_with('"') && value.ends_with('"')))
+        {
+            value = value[1..value.len() - 1].to_string();
+        }
+
+        return Some((name, value));
+    }
+    None
+}
+
 /// Parse a class code into a full class name
 pub fn parse_class_code(code: &str) -> Option<String> {
     match code {
         c if c.starts_with("109") && c.len() == 4 => Some(format!("DPST{}", c)),
-        c if c.len() == 4 && c.chars().all(|ch| ch.is_digit(10)) => Some(format!("COMP{}", c)),
-        c if c.starts_with("cs") && c.len() == 6 && c[2..].chars().all(|ch| ch.is_digit(10)) => {
+        c if c.len() == 4 && c.chars().all(|ch| ch.is_ascii_digit()) => Some(format!("COMP{}", c)),
+        c if c.starts_with("cs") && c.len() == 6 && c[2..].chars().all(|ch| ch.is_ascii_digit()) => {
             Some(format!("COMP{}", &c[2..]))
         }
         c if c.len() == 8
             && c[0..4].chars().all(|ch| ch.is_alphabetic())
-            && c[4..].chars().all(|ch| ch.is_digit(10)) =>
+            && c[4..].chars().all(|ch| ch.is_ascii_digit()) =>
         {
             Some(c.to_uppercase())
         }
@@ -144,6 +414,20 @@ pub fn parse_class_code(code: &str) -> Option<String> {
     }
 }
 
+/// Class code prefixes and the account name prefix each uses, e.g. COMP1511 is cs1511
+const ACCOUNT_PREFIXES: &[(&str, &str)] = &[
+    ("COMP", "cs"),
+    ("SENG", "se"),
+    ("BINF", "bi"),
+    ("DPST", "dp"),
+    ("ENGG", "en"),
+    ("GENE", "ge"),
+    ("GSOE", "gs"),
+    ("HSCH", "hs"),
+    ("INFS", "is"),
+    ("REGZ", "rz"),
+];
+
 /// Derive account name from class name
 fn derive_account_name(class: &str) -> Option<String> {
     if class.len() < 8 {
@@ -153,17 +437,47 @@ fn derive_account_name(class: &str) -> Option<String> {
     let prefix = &class[0..4];
     let number = &class[4..8];
 
-    match prefix {
-        "COMP" => Some(format!("cs{}", number)),
-        "SENG" => Some(format!("se{}", number)),
-        "BINF" => Some(format!("bi{}", number)),
-        "DPST" => Some(format!("dp{}", number)),
-        "ENGG" => Some(format!("en{}", number)),
-        "GENE" => Some(format!("ge{}", number)),
-        "GSOE" => Some(format!("gs{}", number)),
-        "HSCH" => Some(format!("hs{}", number)),
-        "INFS" => Some(format!("is{}", number)),
-        "REGZ" => Some(format!("rz{}", number)),
-        _ => None,
-    }
-}
\ No newline at end of file
+    ACCOUNT_PREFIXES
+        .iter()
+        .find(|(class_prefix, _)| *class_prefix == prefix)
+        .map(|(_, account_prefix)| format!("{}{}", account_prefix, number))
+}
+
+/// Directory holding the class accounts' home directories: `QUICKTOOL_CLASS_ROOT`, or
+/// else /home
+pub fn class_root() -> PathBuf {
+    env::var_os("QUICKTOOL_CLASS_ROOT")
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/home"))
+}
+
+/// Classes with an account on this machine, as they are typed on the command line:
+/// `1511` for COMP classes and the full name, e.g. `SENG2011`, for the rest
+pub fn installed_classes() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(class_root()) else {
+        return Vec::new();
+    };
+    let mut classes: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let account = entry.file_name().to_string_lossy().to_string();
+            let (account_prefix, number) = account.split_at_checked(2)?;
+            if number.len() != 4 || !number.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let (class_prefix, _) = ACCOUNT_PREFIXES
+                .iter()
+                .find(|(_, prefix)| *prefix == account_prefix)?;
+            if !entry.path().join("bin").is_dir() {
+                return None;
+            }
+            Some(match *class_prefix {
+                "COMP" => number.to_string(),
+                prefix => format!("{}{}", prefix, number),
+            })
+        })
+        .collect();
+    classes.sort();
+    classes
+}
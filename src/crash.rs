@@ -0,0 +1,165 @@
+use colored::*;
+use regex::Regex;
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use crate::config::ClassConfig;
+use crate::signals::signal_name;
+
+/// Explanations of common crashes, as `(pattern, explanation)`.
+///
+/// Patterns are regular expressions matched against the program's stderr, preceded by a
+/// `signal NAME` line when the program was killed by a signal.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "SIGSEGV",
+        "SIGSEGV usually means you dereferenced a NULL, uninitialised or freed pointer, \
+         or indexed past the end of an array",
+    ),
+    (
+        "SIGABRT",
+        "SIGABRT means the program aborted itself, usually because an assert failed \
+         or free() found the heap corrupted",
+    ),
+    (
+        "SIGFPE",
+        "SIGFPE usually means an integer division or remainder by zero",
+    ),
+    (
+        "SIGBUS",
+        "SIGBUS usually means you accessed memory through an invalid pointer",
+    ),
+    (
+        "SIGKILL",
+        "SIGKILL usually means the program was stopped for using too much memory or time",
+    ),
+    (
+        "SIGXCPU",
+        "SIGXCPU means the program ran out of CPU time, which usually means an infinite loop",
+    ),
+    (
+        r"heap-use-after-free",
+        "you used memory after it was freed; check for pointers kept after free()",
+    ),
+    (
+        r"double-free|double free",
+        "you freed the same memory twice",
+    ),
+    (
+        r"heap-buffer-overflow",
+        "you accessed past the end of memory from malloc; check your loop bounds and the size you allocated",
+    ),
+    (
+        r"stack-buffer-overflow|global-buffer-overflow",
+        "you accessed past the end of an array; check your loop bounds",
+    ),
+    (
+        r"stack-overflow",
+        "the stack overflowed, which usually means a recursive function never reaches its base case",
+    ),
+    (
+        r"(?i)uninitiali[sz]ed",
+        "you used a variable before giving it a value",
+    ),
+    (
+        r"(?i)null pointer|SEGV on unknown address 0x0+\b",
+        "you dereferenced a NULL pointer; check the result of malloc and the end of your lists",
+    ),
+    (
+        r"(?i)division by zero",
+        "you divided by zero",
+    ),
+    (
+        r"detected memory leaks",
+        "memory you allocated was never freed",
+    ),
+];
+
+/// A source location such as `list.c:42` in a crash report
+const LOCATION_PATTERN: &str = r"([A-Za-z0-9_./-]+\.[ch]):(\d+)";
+
+/// Explain why a program crashed, in terms a beginner can act on.
+///
+/// Course staff can add or replace explanations with a file named by
+/// `crash_explanations_file` in the class config.sh, holding one `pattern|explanation`
+/// per line. An entry with the same pattern as a built-in one replaces it.
+pub fn explanations(
+    config: &ClassConfig,
+    status: ExitStatus,
+    stderr: &str,
+) -> Result<Vec<String>, String> {
+    let mut report = String::new();
+    if let Some(signal) = status.signal().and_then(signal_name) {
+        report.push_str(&format!("signal {}\n", signal));
+    }
+    report.push_str(stderr);
+
+    let mut table: Vec<(String, String)> = class_explanations(config)?;
+    for (pattern, explanation) in EXPLANATIONS {
+        if !table.iter().any(|(p, _)| p == pattern) {
+            table.push((pattern.to_string(), explanation.to_string()));
+        }
+    }
+
+    let location = Regex::new(LOCATION_PATTERN)
+        .expect("valid location pattern")
+        .captures(stderr)
+        .map(|caps| format!("{}:{}", &caps[1], &caps[2]));
+
+    let mut explained = Vec::new();
+    for (pattern, explanation) in table {
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                config.config_warning(&format!("invalid crash pattern '{}': {}", pattern, e))?;
+                continue;
+            }
+        };
+        if regex.is_match(&report) && !explained.contains(&explanation) {
+            explained.push(explanation);
+        }
+    }
+
+    if let (Some(first), Some(location)) = (explained.first_mut(), location) {
+        first.push_str(&format!("; the crash was at {}", location));
+    }
+    Ok(explained)
+}
+
+/// Print the explanations of a crash, if there are any
+pub fn explain(config: &ClassConfig, status: ExitStatus, stderr: &str) -> Result<(), String> {
+    for explanation in explanations(config, status, stderr)? {
+        eprintln!("{} {}", "HINT:".cyan().bold(), explanation);
+    }
+    Ok(())
+}
+
+/// Explanations course staff added in the class's crash explanations file
+fn class_explanations(config: &ClassConfig) -> Result<Vec<(String, String)>, String> {
+    let Some(path) = config.get_custom_config("crash_explanations_file") else {
+        return Ok(Vec::new());
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            config.config_warning(&format!("cannot read {}: {}", path, e))?;
+            return Ok(Vec::new());
+        }
+    };
+
+    let mut table = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.rsplit_once('|') {
+            Some((pattern, explanation)) => {
+                table.push((pattern.to_string(), explanation.trim().to_string()))
+            }
+            None => config.config_warning(&format!("{}: cannot parse '{}'", path, line))?,
+        }
+    }
+    Ok(table)
+}
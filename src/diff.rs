@@ -0,0 +1,634 @@
+use colored::*;
+use regex::Regex;
+
+use crate::manifest::ActivityManifest;
+use crate::output;
+
+/// Number of unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Largest LCS table we are willing to build before giving up on a line diff
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A single line-level edit operation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOp<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Compute a line diff between two texts using a longest common subsequence table.
+/// Returns `None` if the inputs are too large to diff.
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<DiffOp<'a>>> {
+    let (n, m) = (old.len(), new.len());
+    if (n + 1).saturating_mul(m + 1) > MAX_DIFF_CELLS {
+        return None;
+    }
+
+    // lcs[i][j] holds the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Equal(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffOp::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| DiffOp::Delete(line)));
+    ops.extend(new[j..].iter().map(|line| DiffOp::Insert(line)));
+
+    Some(ops)
+}
+
+/// Shown with a diff of invisible characters to say what the markers mean
+pub const INVISIBLE_LEGEND: &str =
+    "(· is a trailing space, → a tab and ␍ a carriage return from a Windows line ending)";
+
+/// How closely an output matches the expected output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    Exact,
+    /// Equal once line endings, trailing whitespace and trailing blank lines are ignored
+    IgnoringWhitespace,
+    /// Equal except for numbers that are within the allowed tolerance
+    WithinTolerance,
+    Different,
+}
+
+/// How far numbers in an output may be from the expected numbers, for exercises whose
+/// answers are floating point
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tolerance {
+    pub absolute: f64,
+    /// Allowed difference as a fraction of the larger number
+    pub relative: f64,
+}
+
+impl Tolerance {
+    /// The tolerance an activity's manifest sets for a test: `float_tolerance` and
+    /// `float_relative_tolerance`, overridden for one test by the same keys ending in
+    /// `_<test>`
+    pub fn from_manifest(manifest: &ActivityManifest, test: Option<&str>) -> Self {
+        let setting = |key: &str| {
+            test.and_then(|test| manifest.get_f64(&format!("{}_{}", key, test)))
+                .or_else(|| manifest.get_f64(key))
+                .unwrap_or(0.0)
+        };
+        Tolerance {
+            absolute: setting("float_tolerance"),
+            relative: setting("float_relative_tolerance"),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.absolute <= 0.0 && self.relative <= 0.0
+    }
+
+    fn accepts(&self, expected: f64, actual: f64) -> bool {
+        let allowed = self
+            .absolute
+            .max(self.relative * expected.abs().max(actual.abs()));
+        (expected - actual).abs() <= allowed
+    }
+
+    /// Whether two texts are the same apart from numbers within the tolerance
+    fn matches(&self, expected: &str, actual: &str) -> bool {
+        let (expected, actual) = (numeric_tokens(expected), numeric_tokens(actual));
+        expected.len() == actual.len()
+            && expected.iter().zip(&actual).all(|pair| match pair {
+                (Token::Number(e), Token::Number(a)) => self.accepts(*e, *a),
+                (e, a) => e == a,
+            })
+    }
+}
+
+impl std::fmt::Display for Tolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.absolute > 0.0, self.relative > 0.0) {
+            (true, true) => write!(f, "±{} or {}%", self.absolute, self.relative * 100.0),
+            (false, true) => write!(f, "{}%", self.relative * 100.0),
+            _ => write!(f, "±{}", self.absolute),
+        }
+    }
+}
+
+/// A piece of output: a number, or the text between numbers
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Text(&'a str),
+    Number(f64),
+}
+
+fn numeric_tokens(text: &str) -> Vec<Token<'_>> {
+    let number = Regex::new(r"[-+]?(\d+\.?\d*|\.\d+)([eE][-+]?\d+)?").expect("valid pattern");
+    let mut tokens = Vec::new();
+    let mut end = 0;
+    for found in number.find_iter(text) {
+        if found.start() > end {
+            tokens.push(Token::Text(&text[end..found.start()]));
+        }
+        match found.as_str().parse() {
+            Ok(value) => tokens.push(Token::Number(value)),
+            Err(_) => tokens.push(Token::Text(found.as_str())),
+        }
+        end = found.end();
+    }
+    if end < text.len() {
+        tokens.push(Token::Text(&text[end..]));
+    }
+    tokens
+}
+
+/// Compare an output with the expected output: exactly, then ignoring whitespace that
+/// cannot be seen on a terminal, then allowing numbers to differ by the tolerance
+pub fn compare_output(expected: &str, actual: &str, tolerance: Tolerance) -> Match {
+    if expected == actual {
+        Match::Exact
+    } else if normalize_whitespace(expected) == normalize_whitespace(actual) {
+        Match::IgnoringWhitespace
+    } else if !tolerance.is_zero()
+        && tolerance.matches(
+            &normalize_whitespace(expected),
+            &normalize_whitespace(actual),
+        )
+    {
+        Match::WithinTolerance
+    } else {
+        Match::Different
+    }
+}
+
+/// Text with CRLF line endings, trailing whitespace and trailing blank lines removed
+fn normalize_whitespace(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').map(str::trim_end).collect();
+    lines.join("\n").trim_end_matches('\n').to_string()
+}
+
+/// A line with its invisible characters shown: trailing spaces, tabs, carriage returns
+/// and other control characters
+pub fn visible(line: &str) -> String {
+    let content_end = line.trim_end_matches([' ', '\t', '\r']).len();
+    let mut shown = String::with_capacity(line.len());
+    for (index, c) in line.char_indices() {
+        match c {
+            ' ' if index >= content_end => shown.push('·'),
+            '\t' => shown.push('→'),
+            '\r' => shown.push('␍'),
+            '\u{a0}' => shown.push('⍽'),
+            c if c.is_ascii_control() => {
+                shown.push('^');
+                shown.push((c as u8 ^ 0x40) as char);
+            }
+            c => shown.push(c),
+        }
+    }
+    shown
+}
+
+/// Printed under a diff of output containing characters outside ASCII
+pub const CODEPOINTS_HINT: &str =
+    "(--show-codepoints shows the differing characters as codepoints, e.g. <U+2500>)";
+
+/// A character as its codepoint, e.g. `<U+2500>` for a box-drawing line
+fn codepoint(c: char) -> String {
+    format!("<U+{:04X}>", c as u32)
+}
+
+/// How a diff shows the lines it compares
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffStyle {
+    /// Compare lines including their line endings and show invisible characters
+    pub show_invisible: bool,
+    /// Show the characters that differ in a changed line that are not plain ASCII as
+    /// codepoints, so look-alikes such as `-` and `─` can be told apart
+    pub show_codepoints: bool,
+}
+
+/// Render a unified diff between two texts, or `None` if they are identical
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> Option<String> {
+    unified_diff_with(old_name, new_name, old, new, DiffStyle::default())
+}
+
+/// Render a unified diff in the given style. A changed line is followed by a `?` line
+/// marking the columns that differ.
+pub fn unified_diff_with(
+    old_name: &str,
+    new_name: &str,
+    old: &str,
+    new: &str,
+    style: DiffStyle,
+) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    if style.show_invisible {
+        return visible_diff(old_name, new_name, old, new, style);
+    }
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    let ops = match diff_lines(&old_lines, &new_lines) {
+        Some(ops) => ops,
+        None => {
+            out.push_str(&format!(
+                "files differ ({} lines vs {} lines, too large to diff)\n",
+                old_lines.len(),
+                new_lines.len()
+            ));
+            return Some(out);
+        }
+    };
+
+    // Only line endings differ, which `lines()` hides
+    if ops.iter().all(|op| matches!(op, DiffOp::Equal(_))) {
+        out.push_str("files differ only in line endings or trailing newline\n");
+        return Some(out);
+    }
+
+    for hunk in group_hunks(&ops) {
+        out.push_str(&render_hunk(&ops, hunk, style));
+    }
+
+    Some(out)
+}
+
+/// A unified diff of lines split only at newlines, so carriage returns stay part of the
+/// line, with invisible characters shown
+fn visible_diff(
+    old_name: &str,
+    new_name: &str,
+    old: &str,
+    new: &str,
+    style: DiffStyle,
+) -> Option<String> {
+    let old_visible: Vec<String> = old.split_terminator('\n').map(visible).collect();
+    let new_visible: Vec<String> = new.split_terminator('\n').map(visible).collect();
+    let old_lines: Vec<&str> = old_visible.iter().map(String::as_str).collect();
+    let new_lines: Vec<&str> = new_visible.iter().map(String::as_str).collect();
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    match diff_lines(&old_lines, &new_lines) {
+        Some(ops) => {
+            for hunk in group_hunks(&ops) {
+                out.push_str(&render_hunk(&ops, hunk, style));
+            }
+        }
+        None => out.push_str(&format!(
+            "files differ ({} lines vs {} lines, too large to diff)\n",
+            old_lines.len(),
+            new_lines.len()
+        )),
+    }
+    for (name, text) in [(old_name, old), (new_name, new)] {
+        if !text.is_empty() && !text.ends_with('\n') {
+            out.push_str(&format!("\\ {} has no newline at the end\n", name));
+        }
+    }
+    Some(out)
+}
+
+/// Print a unified diff to stdout with added and removed lines colored
+pub fn print_diff(diff: &str) {
+    for line in diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", line.bold());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else if line.starts_with('?') {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Split the edit script into ranges of ops, each containing changes plus context
+fn group_hunks(ops: &[DiffOp]) -> Vec<(usize, usize)> {
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for idx in changes {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = (idx + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+/// Render one hunk, including its `@@` header with line numbers
+fn render_hunk(ops: &[DiffOp], (start, end): (usize, usize), style: DiffStyle) -> String {
+    // Work out the line numbers at the start of the hunk
+    let mut old_line = 1;
+    let mut new_line = 1;
+    for op in &ops[..start] {
+        match op {
+            DiffOp::Equal(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffOp::Delete(_) => old_line += 1,
+            DiffOp::Insert(_) => new_line += 1,
+        }
+    }
+
+    let mut body = String::new();
+    let (mut old_count, mut new_count) = (0, 0);
+    let mut index = start;
+    while index < end {
+        if let DiffOp::Equal(line) = ops[index] {
+            body.push_str(&format!(" {}\n", line));
+            old_count += 1;
+            new_count += 1;
+            index += 1;
+            continue;
+        }
+
+        // Removed lines, then the added lines replacing them, which are paired up in order
+        let deleted: Vec<&str> = ops[index..end]
+            .iter()
+            .map_while(|op| match op {
+                DiffOp::Delete(line) => Some(*line),
+                _ => None,
+            })
+            .collect();
+        index += deleted.len();
+        let inserted: Vec<&str> = ops[index..end]
+            .iter()
+            .map_while(|op| match op {
+                DiffOp::Insert(line) => Some(*line),
+                _ => None,
+            })
+            .collect();
+        index += inserted.len();
+        old_count += deleted.len();
+        new_count += inserted.len();
+
+        let changes: Vec<Change> = deleted
+            .iter()
+            .zip(&inserted)
+            .map(|(old, new)| Change::new(old, new, style.show_codepoints))
+            .collect();
+        for (number, line) in deleted.iter().enumerate() {
+            match changes.get(number) {
+                Some(change) => body.push_str(&format!("-{}\n", change.old)),
+                None => body.push_str(&format!("-{}\n", line)),
+            }
+        }
+        for (number, line) in inserted.iter().enumerate() {
+            match changes.get(number) {
+                Some(change) => body.push_str(&format!("+{}\n?{}\n", change.new, change.marker)),
+                None => body.push_str(&format!("+{}\n", line)),
+            }
+        }
+    }
+
+    // An empty side of a hunk is reported at the line before it, as diff(1) does
+    let old_start = if old_count == 0 {
+        old_line - 1
+    } else {
+        old_line
+    };
+    let new_start = if new_count == 0 {
+        new_line - 1
+    } else {
+        new_line
+    };
+
+    format!(
+        "@@ -{},{} +{},{} @@\n{}",
+        old_start, old_count, new_start, new_count, body
+    )
+}
+
+/// A line and the line that replaced it, rendered with the part that differs marked
+struct Change {
+    old: String,
+    new: String,
+    /// Spaces up to the first column that differs in the new line, then a `^` under
+    /// each column of the difference, measured in terminal columns
+    marker: String,
+}
+
+impl Change {
+    fn new(old: &str, new: &str, show_codepoints: bool) -> Self {
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((index, _), _)| index)
+            .unwrap_or(old.len().min(new.len()));
+        let suffix = old[prefix..]
+            .chars()
+            .rev()
+            .zip(new[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>();
+        let middle = |line: &str| {
+            let middle = &line[prefix..line.len() - suffix];
+            if show_codepoints {
+                middle
+                    .chars()
+                    .map(|c| match c {
+                        c if c == ' ' || c.is_ascii_graphic() => c.to_string(),
+                        c => codepoint(c),
+                    })
+                    .collect()
+            } else {
+                middle.to_string()
+            }
+        };
+        let (old_middle, new_middle) = (middle(old), middle(new));
+        let join = |line: &str, middle: &str| {
+            format!(
+                "{}{}{}",
+                &line[..prefix],
+                middle,
+                &line[line.len() - suffix..]
+            )
+        };
+        let marker = format!(
+            "{}{}",
+            " ".repeat(output::display_width(&new[..prefix])),
+            "^".repeat(output::display_width(&new_middle).max(1))
+        );
+        Change {
+            old: join(old, &old_middle),
+            new: join(new, &new_middle),
+            marker,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_diff_keeps_the_longest_common_lines() {
+        let ops = diff_lines(&["a", "b", "c", "d"], &["a", "c", "x", "d"]).unwrap();
+        assert_eq!(
+            ops,
+            [
+                DiffOp::Equal("a"),
+                DiffOp::Delete("b"),
+                DiffOp::Equal("c"),
+                DiffOp::Insert("x"),
+                DiffOp::Equal("d"),
+            ]
+        );
+        assert_eq!(diff_lines(&[], &["a"]).unwrap(), [DiffOp::Insert("a")]);
+        assert_eq!(diff_lines(&["a"], &[]).unwrap(), [DiffOp::Delete("a")]);
+    }
+
+    #[test]
+    fn line_diff_gives_up_on_huge_inputs() {
+        let lines = vec!["line"; 2_001];
+        assert_eq!(diff_lines(&lines, &lines), None);
+    }
+
+    #[test]
+    fn unified_diff_marks_changed_columns() {
+        let diff = unified_diff(
+            "expected",
+            "actual",
+            "one\ntwo\nthree\n",
+            "one\ntwo!\nthree\n",
+        );
+        assert_eq!(
+            diff.unwrap(),
+            "--- expected\n+++ actual\n@@ -1,3 +1,3 @@\n one\n-two\n+two!\n?   ^\n three\n"
+        );
+        assert_eq!(unified_diff("a", "b", "same\n", "same\n"), None);
+    }
+
+    #[test]
+    fn unified_diff_reports_empty_sides_at_the_line_before() {
+        let diff = unified_diff("expected", "actual", "a\n", "a\nb\n").unwrap();
+        assert_eq!(diff, "--- expected\n+++ actual\n@@ -1,1 +1,2 @@\n a\n+b\n");
+        let diff = unified_diff("expected", "actual", "", "b\n").unwrap();
+        assert_eq!(diff, "--- expected\n+++ actual\n@@ -0,0 +1,1 @@\n+b\n");
+    }
+
+    #[test]
+    fn unified_diff_splits_distant_changes_into_hunks() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replace("19\n", "nineteen\n");
+        let diff = unified_diff("old", "new", &old, &new).unwrap();
+        assert_eq!(diff.matches("\n@@ ").count(), 2, "{}", diff);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n"), "{}", diff);
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n"), "{}", diff);
+    }
+
+    #[test]
+    fn invisible_differences() {
+        let diff = unified_diff("expected", "actual", "a\n", "a\r\n").unwrap();
+        assert!(diff.ends_with("files differ only in line endings or trailing newline\n"));
+
+        let style = DiffStyle {
+            show_invisible: true,
+            ..DiffStyle::default()
+        };
+        let diff = unified_diff_with("expected", "actual", "a\n", "a \n", style).unwrap();
+        assert_eq!(
+            diff,
+            "--- expected\n+++ actual\n@@ -1,1 +1,1 @@\n-a\n+a·\n? ^\n"
+        );
+        assert_eq!(visible("x\t\u{7} \r"), "x→^G·␍");
+        assert_eq!(visible("a b"), "a b");
+    }
+
+    #[test]
+    fn codepoints_show_look_alikes() {
+        let style = DiffStyle {
+            show_codepoints: true,
+            ..DiffStyle::default()
+        };
+        let diff = unified_diff_with("expected", "actual", "a-b\n", "a─b\n", style).unwrap();
+        assert!(
+            diff.contains("\n-a-b\n+a<U+2500>b\n? ^^^^^^^^\n"),
+            "{}",
+            diff
+        );
+    }
+
+    #[test]
+    fn output_comparison() {
+        let none = Tolerance::default();
+        assert_eq!(compare_output("1\n", "1\n", none), Match::Exact);
+        assert_eq!(
+            compare_output("1\n2\n", "1  \r\n2\n\n", none),
+            Match::IgnoringWhitespace
+        );
+        assert_eq!(
+            compare_output("x = 0.5\n", "x = 0.51\n", none),
+            Match::Different
+        );
+
+        let tolerance = Tolerance {
+            absolute: 0.01,
+            relative: 0.0,
+        };
+        assert_eq!(
+            compare_output("x = 0.5\n", "x = 0.505\n", tolerance),
+            Match::WithinTolerance
+        );
+        assert_eq!(
+            compare_output("x = 0.5\n", "x = 0.52\n", tolerance),
+            Match::Different
+        );
+        assert_eq!(
+            compare_output("x = 0.5\n", "y = 0.5\n", tolerance),
+            Match::Different
+        );
+
+        let relative = Tolerance {
+            absolute: 0.0,
+            relative: 0.1,
+        };
+        assert_eq!(
+            compare_output("1000 2\n", "1090 2.1\n", relative),
+            Match::WithinTolerance
+        );
+        assert_eq!(
+            compare_output("1000\n", "1200\n", relative),
+            Match::Different
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use colored::*;
+use std::process::Command;
+
+use crate::shell_quote;
+
+/// Say what a tool would have done, for `--dry-run`
+pub fn report(action: &str) {
+    println!("{} {}", "dry run:".cyan().bold(), action);
+}
+
+/// Say which command a tool would have run, for `--dry-run`
+pub fn command(command: &Command) {
+    report(&format!("would run {}", shell_quote::command_line(command)));
+}
@@ -0,0 +1,60 @@
+use thiserror::Error;
+
+use crate::exit_codes;
+
+/// Why a command failed. Each kind has its own exit code, so scripts and editors can tell
+/// a broken class setup from a failing program; anything else is `Other`.
+#[derive(Debug, Error)]
+pub enum QuicktoolError {
+    /// The command line was wrong, e.g. a missing argument
+    #[error("{0}")]
+    Usage(String),
+    /// The class's config.sh is missing or cannot be read, or lacks a setting the command needs
+    #[error("{0}")]
+    ConfigMissing(String),
+    /// A program the class should provide, e.g. its autotest, is not installed
+    #[error("{0}")]
+    ToolNotFound(String),
+    /// A program quicktool ran failed with this exit code
+    #[error("{message}")]
+    ChildFailed { message: String, code: i32 },
+    #[error("{0}")]
+    Other(String),
+}
+
+impl QuicktoolError {
+    /// The exit code quicktool fails with, from [`exit_codes`]; a failed program's own code
+    /// is passed on
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            QuicktoolError::Usage(_) => exit_codes::USAGE,
+            QuicktoolError::ConfigMissing(_) => exit_codes::CONFIG,
+            QuicktoolError::ToolNotFound(_) => exit_codes::TOOL_MISSING,
+            QuicktoolError::ChildFailed { code, .. } if (1..=255).contains(code) => *code,
+            QuicktoolError::ChildFailed { .. } | QuicktoolError::Other(_) => exit_codes::FAILURE,
+        }
+    }
+
+    /// The kind's name in `--json` errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QuicktoolError::Usage(_) => "usage",
+            QuicktoolError::ConfigMissing(_) => "config_missing",
+            QuicktoolError::ToolNotFound(_) => "tool_not_found",
+            QuicktoolError::ChildFailed { .. } => "child_failed",
+            QuicktoolError::Other(_) => "other",
+        }
+    }
+}
+
+impl From<String> for QuicktoolError {
+    fn from(message: String) -> Self {
+        QuicktoolError::Other(message)
+    }
+}
+
+impl From<&str> for QuicktoolError {
+    fn from(message: &str) -> Self {
+        QuicktoolError::Other(message.to_string())
+    }
+}
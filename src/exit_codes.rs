@@ -0,0 +1,37 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+/// The command did what was asked
+pub const SUCCESS: i32 = 0;
+
+/// A test or comparison failed, or an error with no more specific code
+pub const FAILURE: i32 = 1;
+
+/// The command line was wrong: an unknown class, command or option, or a missing argument
+pub const USAGE: i32 = 2;
+
+/// The class's config.sh is missing or cannot be read
+pub const CONFIG: i32 = 3;
+
+/// A program the class should provide, e.g. its autotest, is not installed
+pub const TOOL_MISSING: i32 = 4;
+
+/// The codes quicktool itself exits with and what they mean, as the help and man pages
+/// list them
+pub const DOCUMENTED: &[(i32, &str)] = &[
+    (SUCCESS, "success"),
+    (FAILURE, "a test or comparison failed, or another error"),
+    (USAGE, "the command line was wrong"),
+    (CONFIG, "the class's config.sh is missing"),
+    (TOOL_MISSING, "a class tool is not installed"),
+];
+
+/// The exit code to pass on for a program quicktool ran: its own code, or 128 plus the
+/// signal that killed it, as a shell does
+pub fn of_child(status: ExitStatus) -> i32 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => FAILURE,
+    }
+}
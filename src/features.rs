@@ -0,0 +1,157 @@
+use crate::config::ClassConfig;
+
+/// Features this build of quicktool provides, as they are named in class configuration
+pub const FEATURES: &[&str] = &[
+    "autotest",
+    "autotest-stage",
+    "fetch-activity",
+    "fetch-activity-update",
+    "fetch-activity-source",
+    "notices",
+    "usage-stats",
+    "healthcheck",
+    "legacy-passthrough",
+    "edit",
+    "run",
+    "bench",
+    "crash-explanations",
+    "du",
+    "clean",
+    "roster",
+    "prerequisites",
+    "progress",
+    "help-request",
+    "autotest-forum-report",
+    "test-all",
+    "autotest-ccache",
+    "autotest-probe",
+    "completions",
+    "autotest-infer-exercise",
+    "compiler-version-pinning",
+    "fingerprint",
+    "unit-tests",
+    "list-classes",
+    "plugins",
+    "generated-tests",
+    "autotest-fuzz",
+    "json-output",
+    "doctor",
+    "run-transcript",
+    "whitespace-diff",
+    "config-show",
+    "float-tolerance",
+    "class-picker",
+    "unicode-diff",
+    "install-symlinks",
+    "deploy-docs",
+    "command-help",
+    "signal-forwarding",
+    "tmpdir",
+    "command-history",
+    "process-priority",
+    "healthcheck-metrics",
+    "which",
+    "default-class",
+    "exit-report",
+    "multi-class",
+    "session-env",
+    "shell-hook",
+    "new-file",
+    "version",
+    "toml-config",
+];
+
+/// Printed when the class needs a newer quicktool
+pub const UPGRADE_INSTRUCTIONS: &str = "To upgrade, download the latest release from \
+https://github.com/first-storm/comp-quicktool-rs/releases/latest \
+(or re-run the class install script).";
+
+/// Version of this build
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// The commit this build was made from, with `-dirty` for uncommitted changes, or
+/// `unknown`
+pub fn git_commit() -> &'static str {
+    env!("QUICKTOOL_GIT_COMMIT")
+}
+
+/// The UTC date this build was made, as `YYYY-MM-DD`
+pub fn build_date() -> &'static str {
+    env!("QUICKTOOL_BUILD_DATE")
+}
+
+/// The platform this build is for, e.g. `x86_64-unknown-linux-gnu`
+pub fn target() -> &'static str {
+    env!("QUICKTOOL_TARGET")
+}
+
+/// The version with the commit and build date, e.g. `0.1.0 (3f2a9c1, built 2024-03-05)`,
+/// which identifies the exact build
+pub fn long_version() -> String {
+    format!("{} ({}, built {})", version(), git_commit(), build_date())
+}
+
+/// Whether this build provides a feature
+pub fn supports(feature: &str) -> bool {
+    FEATURES.contains(&feature)
+}
+
+/// Check the requirements a class declares in its config.sh against this build.
+///
+/// `quicktool_min_version` names the oldest acceptable version and
+/// `quicktool_features` lists features the class relies on.
+pub fn check_compatibility(config: &ClassConfig) -> Result<(), String> {
+    if let Some(required) = config.get_custom_config("quicktool_min_version") {
+        if parse_version(version()) < parse_version(required) {
+            return Err(format!(
+                "{} requires quicktool {} or newer, but this is quicktool {}",
+                config.class,
+                required,
+                version()
+            ));
+        }
+    }
+
+    if let Some(required) = config.get_custom_config("quicktool_features") {
+        let missing: Vec<&str> = required
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|feature| !feature.is_empty() && !supports(feature))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "{} relies on features this quicktool {} does not provide: {}",
+                config.class,
+                version(),
+                missing.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the class allows falling back to its own scripts when quicktool is incompatible
+pub fn legacy_fallback_allowed(config: &ClassConfig) -> bool {
+    config
+        .get_custom_config("quicktool_legacy_fallback")
+        .map(|value| matches!(value.as_str(), "1" | "yes" | "true"))
+        .unwrap_or(false)
+}
+
+/// Parse a dotted version into comparable numeric components
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+use std::process::{exit, Command};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::signals::{self, Work};
+use crate::trace;
+
+/// Flag that selects the class's own script instead of quicktool's implementation
+pub const LEGACY_FLAG: &str = "--legacy";
+
+/// Run the class's own script for a tool quicktool reimplements, translating the
+/// quicktool command line into the script's interface
+pub fn run_legacy(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let tool = args[0].as_str();
+    let script = Path::new(config.bin_path.as_deref().unwrap_or("")).join(tool);
+    if !script.exists() {
+        return Err(QuicktoolError::ToolNotFound(format!(
+            "{}: the class does not provide its own {}",
+            config.class, tool
+        )));
+    }
+
+    let translated = translate_args(tool, &args[1..])?;
+
+    let mut command = Command::new(&script);
+    command.args(&translated);
+    trace::command(&command);
+
+    match signals::status(&mut command, Work::Interactive) {
+        Ok(status) => {
+            if !status.success() {
+                exit(exit_codes::of_child(status));
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to execute {}: {}", script.display(), e).into()),
+    }
+}
+
+/// Translate quicktool arguments for a tool into the legacy script's arguments
+fn translate_args(tool: &str, args: &[String]) -> Result<Vec<String>, String> {
+    // Options only quicktool understands, which the class scripts would misread
+    let quicktool_only: &[&str] = match tool {
+        "fetch-activity" => &["--update", "--source"],
+        _ => &[],
+    };
+
+    let mut translated = Vec::new();
+    for arg in args {
+        if arg == LEGACY_FLAG {
+            continue;
+        }
+        if quicktool_only.contains(&arg.as_str()) {
+            return Err(format!(
+                "{} is a quicktool option and is not supported by the class's {}",
+                arg, tool
+            ));
+        }
+        translated.push(arg.clone());
+    }
+    Ok(translated)
+}
@@ -1,9 +1,33 @@
+mod artifacts;
 mod cli;
 mod config;
+mod crash;
+mod diff;
+mod dry_run;
+mod error;
+mod exit_codes;
+mod features;
+mod legacy;
+mod manifest;
+mod metadata;
+mod notices;
+mod output;
+mod paths;
+mod plugins;
+mod retry;
+mod roster;
 mod shell;
+mod shell_quote;
+mod signals;
+mod state;
+mod temp;
+mod toml_config;
+mod toolchain;
+mod trace;
+mod ui;
+mod user_config;
 mod tools;
 
 fn main() {
-    env_logger::init();
     cli::run();
 }
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::parse_variable_assignment;
+
+/// File an activity may publish next to its `files/` directory, holding `key=value`
+/// settings in the same syntax as config.sh
+pub const MANIFEST_FILE: &str = "manifest.sh";
+
+/// Settings course staff publish for a single activity
+#[derive(Debug, Default)]
+pub struct ActivityManifest {
+    values: HashMap<String, String>,
+}
+
+impl ActivityManifest {
+    /// Load the manifest of an activity directory, if it has one
+    pub fn load(activity_dir: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(activity_dir.join(MANIFEST_FILE)).ok()?;
+        let values = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(parse_variable_assignment)
+            .collect();
+        Some(ActivityManifest { values })
+    }
+
+    /// Get a manifest value
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Get a numeric manifest value, ignoring values that are not numbers
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key)?.parse().ok()
+    }
+}
@@ -0,0 +1,187 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::state;
+
+/// Directory, relative to a working directory, holding quicktool metadata
+pub const METADATA_DIR: &str = ".quicktool";
+
+/// Record of an activity fetched into a working directory
+#[derive(Debug)]
+pub struct FetchRecord {
+    pub activity: String,
+    pub class: String,
+    /// Local activities tree the activity was fetched from, if not the class account
+    pub source: Option<String>,
+    pub fetched_at: u64,
+    pub files: Vec<String>,
+}
+
+impl FetchRecord {
+    /// Create an empty record for a fresh fetch
+    pub fn new(activity: &str, class: &str) -> Self {
+        FetchRecord {
+            activity: activity.to_string(),
+            class: class.to_string(),
+            source: None,
+            fetched_at: unix_time(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Load the record for an activity fetched into `dir`, if there is one
+    pub fn load(dir: &Path, activity: &str) -> Option<Self> {
+        let contents = state::read_to_string(&record_path(dir, activity)).ok()?;
+        let value: Value = serde_json::from_str(&contents).ok()?;
+
+        Some(FetchRecord {
+            activity: value.get("activity")?.as_str()?.to_string(),
+            class: value.get("class")?.as_str()?.to_string(),
+            source: value
+                .get("source")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            fetched_at: value.get("fetched_at")?.as_u64()?,
+            files: value
+                .get("files")?
+                .as_array()?
+                .iter()
+                .filter_map(|f| f.as_str().map(str::to_string))
+                .collect(),
+        })
+    }
+
+    /// Load every activity record in `dir`, oldest fetch first
+    pub fn list(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(dir.join(METADATA_DIR)) else {
+            return Vec::new();
+        };
+        let mut records: Vec<Self> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Self::load(dir, &entry.file_name().to_string_lossy()))
+            .collect();
+        records.sort_by(|a, b| {
+            a.fetched_at
+                .cmp(&b.fetched_at)
+                .then(a.activity.cmp(&b.activity))
+        });
+        records
+    }
+
+    /// Write the record into the metadata directory under `dir`
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let value = json!({
+            "activity": self.activity,
+            "class": self.class,
+            "source": self.source,
+            "fetched_at": self.fetched_at,
+            "files": self.files,
+        });
+        let contents = serde_json::to_string_pretty(&value).map_err(io::Error::other)?;
+        state::write(&record_path(dir, &self.activity), contents)
+    }
+
+    /// Keep a copy of a provided file as it was fetched, so later updates can be compared
+    pub fn store_original(&mut self, dir: &Path, file_name: &str, source: &Path) -> io::Result<()> {
+        state::copy(source, &self.original_path(dir, file_name))?;
+
+        if !self.files.iter().any(|f| f == file_name) {
+            self.files.push(file_name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Path of the stored original copy of a provided file
+    pub fn original_path(&self, dir: &Path, file_name: &str) -> PathBuf {
+        activity_dir(dir, &self.activity)
+            .join("original")
+            .join(file_name)
+    }
+}
+
+/// Outcomes of autotest runs for an activity in a working directory
+#[derive(Debug, Default)]
+pub struct AutotestHistory {
+    pub runs: u64,
+    pub passes: u64,
+    /// When autotest last passed every test
+    pub last_passed_at: Option<u64>,
+    /// Environment fingerprint of the latest run
+    pub last_fingerprint: Option<Value>,
+}
+
+impl AutotestHistory {
+    /// Load the autotest history of an activity in `dir`, empty if it was never tested
+    pub fn load(dir: &Path, activity: &str) -> Self {
+        let Some(value) = state::read_to_string(&history_path(dir, activity))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        else {
+            return AutotestHistory::default();
+        };
+
+        AutotestHistory {
+            runs: value.get("runs").and_then(Value::as_u64).unwrap_or(0),
+            passes: value.get("passes").and_then(Value::as_u64).unwrap_or(0),
+            last_passed_at: value.get("last_passed_at").and_then(Value::as_u64),
+            last_fingerprint: value.get("last_fingerprint").cloned(),
+        }
+    }
+
+    /// Record the outcome of one autotest run of an activity in `dir`, and the
+    /// environment it ran in
+    pub fn record(
+        dir: &Path,
+        activity: &str,
+        passed: bool,
+        fingerprint: &Value,
+    ) -> io::Result<()> {
+        let mut history = Self::load(dir, activity);
+        history.runs += 1;
+        history.last_fingerprint = Some(fingerprint.clone());
+        if passed {
+            history.passes += 1;
+            history.last_passed_at = Some(unix_time());
+        }
+
+        let value = json!({
+            "runs": history.runs,
+            "passes": history.passes,
+            "last_passed_at": history.last_passed_at,
+            "last_fingerprint": history.last_fingerprint,
+        });
+        let contents = serde_json::to_string_pretty(&value).map_err(io::Error::other)?;
+        state::write(&history_path(dir, activity), contents)
+    }
+
+    /// Whether autotest has ever passed for the activity
+    pub fn passed(&self) -> bool {
+        self.last_passed_at.is_some()
+    }
+}
+
+/// Metadata directory for one activity
+fn activity_dir(dir: &Path, activity: &str) -> PathBuf {
+    dir.join(METADATA_DIR).join(activity)
+}
+
+/// Path of the JSON record for one activity
+fn record_path(dir: &Path, activity: &str) -> PathBuf {
+    activity_dir(dir, activity).join("record.json")
+}
+
+/// Path of the autotest history for one activity
+fn history_path(dir: &Path, activity: &str) -> PathBuf {
+    activity_dir(dir, activity).join("autotest.json")
+}
+
+/// Current time in seconds since the Unix epoch
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
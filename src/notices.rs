@@ -0,0 +1,116 @@
+use colored::*;
+use std::fs;
+use std::io::Write;
+
+use crate::config::ClassConfig;
+use crate::state;
+
+/// File in the state directory listing notice ids the user has dismissed
+const DISMISSED_FILE: &str = "dismissed-notices";
+
+/// A class-wide notice published by course staff
+#[derive(Debug)]
+pub struct Notice {
+    pub id: String,
+    pub pattern: String,
+    pub message: String,
+}
+
+/// Display the class notices relevant to an activity that the user has not dismissed.
+///
+/// The notices file is named by `notices_file` in the class config.sh and holds one
+/// notice per line as `id|activity pattern|message`, where the pattern may use `*`.
+pub fn show_notices(config: &ClassConfig, activity: Option<&str>) {
+    let Some(path) = config.get_custom_config("notices_file") else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(path) else {
+        return;
+    };
+
+    let dismissed = dismissed_ids();
+    for notice in parse_notices(&contents) {
+        if dismissed.contains(&notice.id) {
+            continue;
+        }
+        let relevant = match activity {
+            Some(activity) => pattern_matches(&notice.pattern, activity),
+            None => notice.pattern == "*",
+        };
+        if relevant {
+            eprintln!("{} {}", "NOTICE:".yellow().bold(), notice.message);
+            eprintln!(
+                "        (hide this with: quicktool {} dismiss-notice {})",
+                config.class, notice.id
+            );
+        }
+    }
+}
+
+/// Remember that the user has dismissed a notice
+pub fn dismiss_notice(id: &str) -> Result<(), String> {
+    if dismissed_ids().iter().any(|d| d == id) {
+        return Ok(());
+    }
+
+    let dir = state::state_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(DISMISSED_FILE))
+        .map_err(|e| format!("Failed to record dismissed notice: {}", e))?;
+    writeln!(file, "{}", id).map_err(|e| format!("Failed to record dismissed notice: {}", e))?;
+
+    println!("Notice {} will no longer be shown.", id);
+    Ok(())
+}
+
+/// Parse the notices file, skipping comments and malformed lines
+fn parse_notices(contents: &str) -> Vec<Notice> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '|');
+            let id = parts.next()?.trim();
+            let pattern = parts.next()?.trim();
+            let message = parts.next()?.trim();
+            if id.is_empty() || message.is_empty() {
+                return None;
+            }
+            Some(Notice {
+                id: id.to_string(),
+                pattern: pattern.to_string(),
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Ids of notices the user has dismissed
+fn dismissed_ids() -> Vec<String> {
+    fs::read_to_string(state::state_dir().join(DISMISSED_FILE))
+        .map(|contents| contents.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Match a name against a pattern where `*` matches any run of characters
+pub fn pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(remaining) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=remaining.len())
+                .filter(|&i| remaining.is_char_boundary(i))
+                .any(|i| pattern_matches(rest, &remaining[i..]))
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use clap::ValueEnum;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::signals::{self, Work};
+
+/// When quicktool colours its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Colour a terminal, unless NO_COLOR is set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Apply the colour policy for this run. Always and never also reach the logger and the
+/// programs quicktool runs, through CLICOLOR_FORCE and NO_COLOR.
+pub fn set_color_choice(choice: ColorChoice) {
+    match choice {
+        // colored already checks NO_COLOR and whether stdout is a terminal
+        ColorChoice::Auto => {}
+        ColorChoice::Always => {
+            colored::control::set_override(true);
+            env::remove_var("NO_COLOR");
+            env::set_var("CLICOLOR_FORCE", "1");
+        }
+        ColorChoice::Never => {
+            colored::control::set_override(false);
+            env::remove_var("CLICOLOR_FORCE");
+            env::set_var("NO_COLOR", "1");
+        }
+    }
+}
+
+/// Columns a character takes up on a terminal: 2 for wide East Asian characters and
+/// emoji, 0 for combining marks, zero-width characters and control characters
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0..=0x1f | 0x7f..=0x9f => 0,
+        0x0300..=0x036f | 0x1ab0..=0x1aff | 0x1dc0..=0x1dff | 0x20d0..=0x20ff => 0,
+        0x200b..=0x200f | 0x2060..=0x2064 | 0xfe00..=0xfe0f | 0xfe20..=0xfe2f | 0xfeff => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f680..=0x1f6ff
+        | 0x1f900..=0x1faff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Columns a string takes up on a terminal, which for UTF-8 text is not its length
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// What happens to one output stream of a child process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Written straight to our own stream, not captured
+    Inherit,
+    /// Captured without being shown
+    Capture,
+    /// Shown on our own stream as it arrives and captured
+    Tee,
+}
+
+impl Stream {
+    fn stdio(self) -> Stdio {
+        match self {
+            Stream::Inherit => Stdio::inherit(),
+            Stream::Capture | Stream::Tee => Stdio::piped(),
+        }
+    }
+}
+
+/// How to route a child's stdout and stderr
+#[derive(Debug, Clone, Copy)]
+pub struct Plumbing<'a> {
+    pub stdout: Stream,
+    pub stderr: Stream,
+    /// File that every captured stream is also appended to, as it arrives
+    pub log: Option<&'a Path>,
+    /// What kind of work the child does; signals quicktool receives are passed on to it
+    pub work: Work,
+}
+
+impl Default for Plumbing<'_> {
+    fn default() -> Self {
+        Plumbing {
+            stdout: Stream::Tee,
+            stderr: Stream::Tee,
+            log: None,
+            work: Work::Interactive,
+        }
+    }
+}
+
+/// Run a command to completion with its output routed as described by `plumbing`.
+///
+/// Streams that are inherited come back empty in the returned `Output`.
+pub fn run(command: &mut Command, plumbing: &Plumbing) -> io::Result<Output> {
+    let log = match plumbing.log {
+        Some(path) => Some(Arc::new(Mutex::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        ))),
+        None => None,
+    };
+
+    let mut forwarded = signals::spawn(
+        command
+            .stdout(plumbing.stdout.stdio())
+            .stderr(plumbing.stderr.stdio()),
+        plumbing.work,
+    )?;
+    let child = &mut forwarded.child;
+
+    let stdout = child.stdout.take().map(|out| {
+        let echo = (plumbing.stdout == Stream::Tee).then(|| Box::new(io::stdout()) as Box<_>);
+        tee(out, echo, log.clone())
+    });
+    let stderr = child.stderr.take().map(|err| {
+        let echo = (plumbing.stderr == Stream::Tee).then(|| Box::new(io::stderr()) as Box<_>);
+        tee(err, echo, log.clone())
+    });
+
+    let status = forwarded.wait()?;
+    Ok(Output {
+        status,
+        stdout: stdout.and_then(|h| h.join().ok()).unwrap_or_default(),
+        stderr: stderr.and_then(|h| h.join().ok()).unwrap_or_default(),
+    })
+}
+
+/// Copy a child's output stream to an optional terminal stream and log file while
+/// keeping a copy of it
+fn tee<R>(
+    mut source: R,
+    mut echo: Option<Box<dyn Write + Send>>,
+    log: Option<Arc<Mutex<File>>>,
+) -> thread::JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut captured = Vec::new();
+        let mut buffer = [0u8; 8192];
+        while let Ok(n) = source.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            if let Some(echo) = echo.as_mut() {
+                let _ = echo.write_all(&buffer[..n]);
+                let _ = echo.flush();
+            }
+            if let Some(log) = &log {
+                if let Ok(mut file) = log.lock() {
+                    let _ = file.write_all(&buffer[..n]);
+                }
+            }
+            captured.extend_from_slice(&buffer[..n]);
+        }
+        captured
+    })
+}
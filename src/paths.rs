@@ -0,0 +1,101 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+
+/// Where the lab machines install autotest.py
+const AUTOTEST_SCRIPT: &str = "/usr/local/share/autotest/autotest.py";
+
+/// Where a class's files and helper programs are, worked out from its config.sh with
+/// built-in fallbacks. Get one from `ClassConfig::paths`.
+pub struct Paths<'a> {
+    config: &'a ClassConfig,
+}
+
+impl<'a> Paths<'a> {
+    pub fn new(config: &'a ClassConfig) -> Self {
+        Paths { config }
+    }
+
+    /// The class's web directory for this session: `public_html_session_directory`, or
+    /// else the course account's `current` directory, with a configuration warning
+    pub fn session_dir(&self) -> Result<PathBuf, String> {
+        if let Some(dir) = self
+            .config
+            .expect_custom_config("public_html_session_directory")?
+        {
+            return Ok(PathBuf::from(dir));
+        }
+        match self.config.get_custom_config("course_account") {
+            Some(account) => Ok(Path::new("/web").join(account).join("current")),
+            None => Err(format!(
+                "{}: config.sh sets neither public_html_session_directory nor course_account",
+                self.config.class
+            )),
+        }
+    }
+
+    /// Directory holding every activity: `activities_directory`, or else `activities` in
+    /// the session directory
+    pub fn activities_dir(&self) -> Result<PathBuf, String> {
+        match self.config.get_custom_config("activities_directory") {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => Ok(self.session_dir()?.join("activities")),
+        }
+    }
+
+    /// A helper program or script by name, e.g. `autotest`, `c_check` or `ccache`:
+    /// `<name>_path` from config.sh, else where the lab machines install it, else PATH.
+    ///
+    /// Only files that exist are found. Each name is looked up once per config.
+    pub fn tool(&self, name: &str) -> Result<PathBuf, QuicktoolError> {
+        let not_found = || {
+            QuicktoolError::ToolNotFound(format!(
+                "{} is not installed on this machine (set {}_path in config.sh)",
+                name, name
+            ))
+        };
+        if let Some(found) = self.config.tool_paths.borrow().get(name) {
+            return found.clone().ok_or_else(not_found);
+        }
+        let found = match self.config.get_custom_config(&format!("{}_path", name)) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => self.installed_location(name).or_else(|| find_in_path(name)),
+        }
+        .filter(|path| path.is_file());
+        self.config
+            .tool_paths
+            .borrow_mut()
+            .insert(name.to_string(), found.clone());
+        found.ok_or_else(not_found)
+    }
+
+    /// Where a class helper is installed, for helpers that are not found through PATH
+    fn installed_location(&self, name: &str) -> Option<PathBuf> {
+        match name {
+            "autotest" => Some(PathBuf::from(AUTOTEST_SCRIPT)),
+            "c_check" => Some(
+                self.session_dir()
+                    .ok()?
+                    .join("public/_infra/extern/c_check/c_check.py"),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Find an executable by name in PATH
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Whether a path is a file someone may execute
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::BUILT_IN_COMMANDS;
+use crate::config::ClassConfig;
+use crate::paths::is_executable;
+
+/// Prefix of the programs that add commands to quicktool, e.g. `quicktool-marks`
+const PLUGIN_PREFIX: &str = "quicktool-";
+
+/// The plugin program for a command: `quicktool-<command>` in the class bin directory or
+/// on PATH
+pub fn find(config: &ClassConfig, command: &str) -> Option<PathBuf> {
+    if command.is_empty() || command.contains('/') {
+        return None;
+    }
+    config
+        .paths()
+        .tool(&format!("{}{}", PLUGIN_PREFIX, command))
+        .ok()
+}
+
+/// Commands provided by plugins in the class bin directory or on PATH, sorted
+pub fn list(config: &ClassConfig) -> Vec<String> {
+    let mut dirs: Vec<PathBuf> = config.bin_path.iter().map(PathBuf::from).collect();
+    if let Some(path) = env::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+
+    let mut commands = BTreeSet::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(command) = name.strip_prefix(PLUGIN_PREFIX) {
+                if !command.is_empty() && entry.path().is_file() {
+                    commands.insert(command.to_string());
+                }
+            }
+        }
+    }
+    commands.into_iter().collect()
+}
+
+/// The program for a command the class provides: an executable of that name in the
+/// class bin directory
+pub fn find_class_command(config: &ClassConfig, command: &str) -> Option<PathBuf> {
+    if command.is_empty() || command.contains('/') {
+        return None;
+    }
+    let program = Path::new(config.bin_path.as_deref()?).join(command);
+    is_executable(&program).then_some(program)
+}
+
+/// Commands the class provides as programs in its bin directory, other than its own
+/// versions of built-in commands and plugins, leaving out any the class disables; sorted
+pub fn class_commands(config: &ClassConfig) -> Vec<String> {
+    let Some(Ok(entries)) = config.bin_path.as_ref().map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut commands: Vec<String> = entries
+        .flatten()
+        .filter(|entry| is_executable(&entry.path()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.') && !name.starts_with(PLUGIN_PREFIX))
+        .filter(|name| {
+            name != "help" && !BUILT_IN_COMMANDS.iter().any(|(command, _)| command == name)
+        })
+        .filter(|name| config.tool_enabled(name))
+        .collect();
+    commands.sort();
+    commands
+}
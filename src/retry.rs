@@ -0,0 +1,61 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// Number of attempts made before giving up on a transient failure
+const ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; each further retry waits twice as long
+const INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// Run a filesystem operation, retrying with exponential backoff while it fails with an
+/// error that a stalled NFS mount can produce. Other errors are returned immediately.
+pub fn retry_io<T, F>(what: impl Display, mut op: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    let mut delay = INITIAL_DELAY;
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < ATTEMPTS && is_transient(&e) => {
+                attempt += 1;
+                eprintln!(
+                    "quicktool: {} failed ({}), retrying ({}/{})...",
+                    what, e, attempt, ATTEMPTS
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Check whether a path exists, retrying transient errors instead of reporting them as missing
+pub fn exists(path: &Path) -> bool {
+    retry_io(format!("checking {}", path.display()), || {
+        fs::metadata(path)
+    })
+    .is_ok()
+}
+
+/// Whether an error is worth retrying
+fn is_transient(error: &io::Error) -> bool {
+    // ESTALE, EIO, ETIMEDOUT and EAGAIN are what NFS stalls surface as on Linux
+    const ESTALE: i32 = 116;
+    const EIO: i32 = 5;
+    const ETIMEDOUT: i32 = 110;
+    const EAGAIN: i32 = 11;
+
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+    ) || matches!(
+        error.raw_os_error(),
+        Some(ESTALE | EIO | ETIMEDOUT | EAGAIN)
+    )
+}
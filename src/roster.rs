@@ -0,0 +1,161 @@
+use std::fs;
+use std::process::Command;
+
+use crate::config::{current_user, ClassConfig};
+use crate::trace;
+
+/// One enrolled student
+#[derive(Debug, Clone)]
+pub struct Student {
+    pub zid: String,
+    pub name: String,
+    pub tutorial: String,
+}
+
+impl Student {
+    /// Whether the student is in one of the given tutorials, e.g. `H13A`
+    pub fn in_tutorials(&self, tutorials: &[String]) -> bool {
+        tutorials
+            .iter()
+            .any(|tutorial| self.tutorial.eq_ignore_ascii_case(tutorial))
+    }
+}
+
+/// The class enrolment list
+#[derive(Debug, Default)]
+pub struct Roster {
+    pub students: Vec<Student>,
+}
+
+impl Roster {
+    /// Load the roster named by the class config.sh: `roster_file` is a CSV file, and
+    /// `roster_command` is a shell command printing the same CSV.
+    ///
+    /// Each line holds `zid,name,tutorial`; a header line is skipped.
+    pub fn load(config: &ClassConfig) -> Result<Roster, String> {
+        let contents = if let Some(path) = config.get_custom_config("roster_file") {
+            fs::read_to_string(path).map_err(|e| format!("Cannot read roster {}: {}", path, e))?
+        } else if let Some(script) = config.get_custom_config("roster_command") {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(script);
+            trace::command(&command);
+            let output = command
+                .output()
+                .map_err(|e| format!("Failed to run roster command: {}", e))?;
+            if !output.status.success() {
+                return Err(format!("roster command exited with {}", output.status));
+            }
+            String::from_utf8_lossy(&output.stdout).to_string()
+        } else {
+            return Err(format!(
+                "{} has no roster (set roster_file or roster_command in config.sh)",
+                config.class
+            ));
+        };
+
+        let students = contents
+            .lines()
+            .filter_map(|line| {
+                let fields = split_csv_line(line);
+                let zid = normalise_zid(fields.first()?)?;
+                Some(Student {
+                    zid,
+                    name: fields.get(1).cloned().unwrap_or_default(),
+                    tutorial: fields.get(2).cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+        Ok(Roster { students })
+    }
+
+    /// Find a student by zID, with or without the leading z
+    pub fn lookup(&self, zid: &str) -> Option<&Student> {
+        let zid = normalise_zid(zid)?;
+        self.students.iter().find(|student| student.zid == zid)
+    }
+
+    /// Students matching a query: a zID prefix, or letters that appear in order in the
+    /// student's name, ignoring case
+    pub fn search(&self, query: &str) -> Vec<&Student> {
+        let query = query.trim().to_lowercase();
+        let zid_query = query
+            .strip_prefix('z')
+            .unwrap_or(&query)
+            .chars()
+            .all(|c| c.is_ascii_digit());
+        self.students
+            .iter()
+            .filter(|student| {
+                if zid_query {
+                    student.zid[1..].starts_with(query.trim_start_matches('z'))
+                } else {
+                    fuzzy_match(&query, &student.name.to_lowercase())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Expand the value of a `--tut` option into tutorial names: a comma-separated list
+/// such as `H13A,H14B`, where `mine` stands for the invoking tutor's own tutorials.
+///
+/// Tutors' tutorials are set by `tutor_groups` in config.sh, e.g.
+/// `tutor_groups="jsmith:H13A,H14B akhan:T11B"`.
+pub fn expand_tutorials(config: &ClassConfig, value: &str) -> Result<Vec<String>, String> {
+    let mut tutorials = Vec::new();
+    for tutorial in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if tutorial != "mine" {
+            tutorials.push(tutorial.to_string());
+            continue;
+        }
+        let user = current_user().ok_or("cannot tell who you are to find your tutorials")?;
+        let own = config
+            .get_custom_config("tutor_groups")
+            .and_then(|groups| {
+                groups.split_whitespace().find_map(|entry| {
+                    let (tutor, groups) = entry.split_once(':')?;
+                    (tutor == user).then_some(groups)
+                })
+            })
+            .ok_or_else(|| format!("no tutorials are listed for {} in tutor_groups", user))?;
+        tutorials.extend(own.split(',').map(str::to_string));
+    }
+    Ok(tutorials)
+}
+
+/// Whether the letters of `query` appear in order in `text`
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|q| text.any(|c| c == q))
+}
+
+/// `z1234567` from `z1234567` or `1234567`, or None if it is not a zID
+fn normalise_zid(zid: &str) -> Option<String> {
+    let digits = zid.trim().trim_start_matches(['z', 'Z']);
+    (digits.len() == 7 && digits.chars().all(|c| c.is_ascii_digit()))
+        .then(|| format!("z{}", digits))
+}
+
+/// Split a CSV line into fields, allowing double-quoted fields containing commas
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
@@ -1,13 +1,25 @@
 use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::exit_codes;
+use crate::shell_quote;
+use crate::signals::{self, Work};
+use crate::tools::session_env;
+use crate::trace;
+use crate::user_config::UserConfig;
 use colored::*;
 use std::env;
+use std::os::unix::process::ExitStatusExt;
 use std::process::{exit, Command};
 
 /// Run a new shell for the class
 pub fn run_shell(class_config: &ClassConfig) {
+    if class_config.dry_run {
+        dry_run::report(&format!("would start a shell for class {}", class_config.class));
+        return;
+    }
+
     println!(
-        "{} {}...",
-        "quicktool starting new subshell for class",
+        "quicktool starting new subshell for class {}...",
         class_config.class.green().bold()
     );
 
@@ -15,31 +27,41 @@ pub fn run_shell(class_config: &ClassConfig) {
         "{} This shell is configured for the class environment and quicktool built-in tools will NOT work in this shell.",
         "WARNING:".red().bold()
     );
+
+    let session = session_env::variables(&class_config.class);
+    if !session.is_empty() {
+        let names: Vec<&str> = session.iter().map(|(key, _)| key.as_str()).collect();
+        println!(
+            "Set with setenv: {} (unsetenv to remove)",
+            names.join(", ").bold()
+        );
+    }
     
     // Get user's shell or default to bash
     let shell = env::var("SHELL").unwrap_or_else(|_| String::from("/bin/bash"));
 
     // If .newclassrc exists, source it via -c; otherwise, just run the shell
     if let Some(newclassrc_path) = class_config.newclassrc_path.as_ref().filter(|_| class_config.has_newclassrc()) {
-        let cmd_string = format!("source {} && exec $SHELL --norc", newclassrc_path);
-        let status = Command::new(&shell)
-            .arg("-c")
-            .arg(cmd_string)
-            .status();
+        let cmd_string = format!("source {} && exec $SHELL --norc", shell_quote::quote(newclassrc_path));
+        let mut command = Command::new(&shell);
+        command.arg("-c").arg(cmd_string);
+        trace::command(&command);
+        let status = signals::status(&mut command, Work::Interactive);
 
-        if let Err(_) = status {
+        if status.is_err() {
             eprintln!("quicktool: cannot find shell {}: giving up", shell);
-            exit(1);
+            exit(exit_codes::FAILURE);
         }
     } else {
         // If no .newclassrc, just start the shell with --norc
-        let status = Command::new(&shell)
-            .arg("--norc")
-            .status();
+        let mut command = Command::new(&shell);
+        command.arg("--norc");
+        trace::command(&command);
+        let status = signals::status(&mut command, Work::Interactive);
 
-        if let Err(_) = status {
+        if status.is_err() {
             eprintln!("quicktool: cannot find shell {}: giving up", shell);
-            exit(1);
+            exit(exit_codes::FAILURE);
         }
     }
 }
@@ -47,29 +69,63 @@ pub fn run_shell(class_config: &ClassConfig) {
 /// Execute a command with the class environment
 pub fn execute_command(class_config: &ClassConfig, args: &[String]) {
     // Source .newclassrc if it exists
-    if let Some(newclassrc_path) = class_config.newclassrc_path.as_ref().filter(|_| class_config.has_newclassrc()) {
-        execute_with_newclassrc(newclassrc_path, args);
+    let mut command = if let Some(newclassrc_path) = class_config.newclassrc_path.as_ref().filter(|_| class_config.has_newclassrc()) {
+        command_with_newclassrc(newclassrc_path, args)
     } else {
         // Regular command execution without .newclassrc
-        execute_direct_command(args);
+        direct_command(args)
+    };
+
+    if class_config.dry_run {
+        dry_run::command(&command);
+        return;
+    }
+    trace::command(&command);
+    match signals::status(&mut command, Work::Interactive) {
+        Ok(status) => {
+            // Ctrl-C is left out, as the user knows why the command stopped
+            if status.signal() != Some(signals::SIGINT) && report_exit(class_config) {
+                if let Some(line) = signals::describe_exit(&args[0], status) {
+                    eprintln!("{}", line.yellow());
+                }
+            }
+            exit(exit_codes::of_child(status))
+        }
+        Err(e) => {
+            eprintln!("quicktool: error executing command: {}", e);
+            exit(exit_codes::FAILURE);
+        }
     }
 }
 
-/// Execute a command with .newclassrc sourcing
-fn execute_with_newclassrc(newclassrc_path: &str, args: &[String]) {
+/// Whether to say how a command ended when it fails, for beginners who otherwise see the
+/// prompt come back without a word: `report_exit = true` in the user's config, or
+/// `quicktool_report_exit=yes` in the class config.sh
+fn report_exit(class_config: &ClassConfig) -> bool {
+    let user_config = UserConfig::load();
+    user_config
+        .class_setting(&class_config.class, "report_exit")
+        .or_else(|| class_config.get_custom_config("quicktool_report_exit").map(String::as_str))
+        .is_some_and(|value| matches!(value, "1" | "yes" | "true"))
+}
+
+/// Build a command that sources .newclassrc first
+fn command_with_newclassrc(newclassrc_path: &str, args: &[String]) -> Command {
     let shell = env::var("SHELL").unwrap_or_else(|_| String::from("/bin/bash"));
-    let cmd_str = format!("source {} && exec {}", newclassrc_path, args.join(" "));
+    let cmd_str = format!(
+        "source {} && exec {}",
+        shell_quote::quote(newclassrc_path),
+        shell_quote::join(args)
+    );
 
-    if let Err(e) = Command::new(&shell).arg("-c").arg(cmd_str).status() {
-        eprintln!("quicktool: error executing command: {}", e);
-        exit(1);
-    }
+    let mut command = Command::new(&shell);
+    command.arg("-c").arg(cmd_str);
+    command
 }
 
-/// Execute a command directly without .newclassrc
-fn execute_direct_command(args: &[String]) {
-    if let Err(e) = Command::new(&args[0]).args(&args[1..]).status() {
-        eprintln!("quicktool: error executing command: {}", e);
-        exit(1);
-    }
+/// Build a command run directly without .newclassrc
+fn direct_command(args: &[String]) -> Command {
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    command
 }
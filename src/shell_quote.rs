@@ -0,0 +1,60 @@
+use std::process::Command;
+
+/// Quote an argument for a POSIX shell command line, leaving plain words as they are
+pub fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    // Inside single quotes only a single quote is special: close, escape it, reopen
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Quote every argument and join them into a shell command line
+pub fn join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A subprocess command as a shell command line, e.g. for `--dry-run`
+pub fn command_line(command: &Command) -> String {
+    let mut args = vec![command.get_program().to_string_lossy().to_string()];
+    args.extend(
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string()),
+    );
+    join(&args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_words_are_left_alone() {
+        assert_eq!(quote("gcc"), "gcc");
+        assert_eq!(quote("--flag=a,b:c@d%e+f"), "--flag=a,b:c@d%e+f");
+        assert_eq!(quote("./dir/file.c"), "./dir/file.c");
+    }
+
+    #[test]
+    fn other_arguments_are_single_quoted() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("two words"), "'two words'");
+        assert_eq!(quote("$HOME `id` \"x\" \\"), "'$HOME `id` \"x\" \\'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("~user"), "'~user'");
+        assert_eq!(quote("*.c"), "'*.c'");
+        assert_eq!(quote("café"), "'café'");
+    }
+
+    #[test]
+    fn command_lines() {
+        let mut command = Command::new("autotest");
+        command.args(["lab 01", "-a", "it's"]);
+        assert_eq!(command_line(&command), r"autotest 'lab 01' -a 'it'\''s'");
+    }
+}
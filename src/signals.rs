@@ -0,0 +1,257 @@
+use std::io;
+use std::os::raw::c_int;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{exit, Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+
+use log::warn;
+use signal_hook::consts::SIGTERM;
+use signal_hook::iterator::exfiltrator::WithOrigin;
+use signal_hook::iterator::SignalsInfo;
+use signal_hook::low_level::{self, siginfo::Origin};
+
+use crate::exit_codes;
+
+pub use signal_hook::consts::SIGINT;
+
+/// Pids of the running children SIGINT and SIGTERM are passed on to
+static CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// The last signal received while children ran, or 0
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+static INSTALL: Once = Once::new();
+
+/// Niceness heavy work runs at, or 0 to leave it alone
+static NICE: AtomicI32 = AtomicI32::new(0);
+
+/// I/O priority heavy work runs with, as `ioprio_set` takes it, or 0 to leave it alone
+static IO_PRIORITY: AtomicI32 = AtomicI32::new(0);
+
+/// `ioprio_set`'s way of naming a single process; the system call has no libc wrapper
+const IOPRIO_WHO_PROCESS: c_int = 1;
+
+/// How heavy work is scheduled, so that autotest sweeps do not slow down shared lab and
+/// login servers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Priority {
+    /// Niceness, from 0 (normal) to 19 (lowest)
+    pub nice: i32,
+    pub io: Option<IoPriority>,
+}
+
+/// An I/O scheduling class, as `ionice` sets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoPriority {
+    /// Disk access only when no one else needs the disk
+    Idle,
+    /// Normal scheduling at a level from 0 (highest) to 7 (lowest)
+    BestEffort(u8),
+}
+
+impl IoPriority {
+    /// Parse `idle` or a best-effort level, as in `quicktool_ionice`
+    pub fn parse(value: &str) -> Option<IoPriority> {
+        match value {
+            "idle" => Some(IoPriority::Idle),
+            level => level
+                .parse()
+                .ok()
+                .filter(|level| *level <= 7)
+                .map(IoPriority::BestEffort),
+        }
+    }
+
+    /// The value `ioprio_set` takes: the class shifted above the level
+    fn value(self) -> c_int {
+        match self {
+            IoPriority::Idle => 3 << 13,
+            IoPriority::BestEffort(level) => 2 << 13 | level as c_int,
+        }
+    }
+}
+
+/// Set how heavy work started from now on is scheduled
+pub fn set_priority(priority: Priority) {
+    NICE.store(priority.nice.clamp(0, 19), Ordering::SeqCst);
+    IO_PRIORITY.store(priority.io.map_or(0, IoPriority::value), Ordering::SeqCst);
+}
+
+/// What kind of work a child does. Either way it stays in quicktool's process group, so it
+/// can read the terminal, Ctrl-C and Ctrl-Z reach it directly, and anything it starts is
+/// interrupted along with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Work {
+    /// A program the user is working with, e.g. their shell or a student's program
+    Interactive,
+    /// Heavy work, such as autotest runs, which runs at the priority given to
+    /// `set_priority`. Quicktool stops once it is interrupted rather than carrying on.
+    Heavy,
+}
+
+/// A child that SIGINT and SIGTERM sent to quicktool are passed on to until it exits
+pub struct Forwarded {
+    pub child: Child,
+    work: Work,
+    registered: bool,
+}
+
+impl Forwarded {
+    /// Wait for the child. If it was heavy work and quicktool was interrupted, exit as an
+    /// interrupted program does, with 128 plus the signal, e.g. 130 for Ctrl-C.
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait();
+        self.release();
+        if self.work == Work::Heavy {
+            exit_if_interrupted();
+        }
+        status
+    }
+
+    fn release(&mut self) {
+        if std::mem::take(&mut self.registered) {
+            let pid = self.child.id();
+            if let Ok(mut children) = CHILDREN.lock() {
+                children.retain(|&child| child != pid);
+            }
+        }
+    }
+}
+
+impl Drop for Forwarded {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Start a command, passing on the signals quicktool receives
+pub fn spawn(command: &mut Command, work: Work) -> io::Result<Forwarded> {
+    INSTALL.call_once(install);
+    if work == Work::Heavy {
+        let nice = NICE.load(Ordering::SeqCst);
+        let io_priority = IO_PRIORITY.load(Ordering::SeqCst);
+        if nice != 0 || io_priority != 0 {
+            // SAFETY: the closure only makes system calls, which are safe after fork.
+            // Failures are ignored, as a lower priority is only a courtesy.
+            unsafe {
+                command.pre_exec(move || {
+                    if nice != 0 {
+                        libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                    }
+                    if io_priority != 0 {
+                        libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, io_priority);
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
+    let child = command.spawn()?;
+    let registered = match CHILDREN.lock() {
+        Ok(mut children) => {
+            children.push(child.id());
+            true
+        }
+        Err(_) => false,
+    };
+    Ok(Forwarded {
+        child,
+        work,
+        registered,
+    })
+}
+
+/// Run a command to completion, passing on signals; see `spawn`
+pub fn status(command: &mut Command, work: Work) -> io::Result<ExitStatus> {
+    spawn(command, work)?.wait()
+}
+
+/// Exit with 128 plus the signal if quicktool was interrupted while running a child
+pub fn exit_if_interrupted() {
+    match RECEIVED.load(Ordering::SeqCst) {
+        0 => {}
+        signum => exit(128 + signum),
+    }
+}
+
+/// Have signal-hook deliver SIGINT and SIGTERM to a thread that passes them on
+fn install() {
+    let mut signals = match SignalsInfo::<WithOrigin>::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            warn!("Cannot pass signals on to children: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for origin in signals.forever() {
+            pass_on(origin);
+        }
+    });
+}
+
+/// Pass a signal on to every child, or with none running, take the default action as if
+/// there were no handler. A signal from the terminal, e.g. Ctrl-C, has already reached the
+/// children through the process group, so only one sent by another process is passed on.
+fn pass_on(origin: Origin) {
+    let children = match CHILDREN.lock() {
+        Ok(children) => children.clone(),
+        Err(_) => Vec::new(),
+    };
+    if children.is_empty() {
+        let _ = low_level::emulate_default_handler(origin.signal);
+        return;
+    }
+    RECEIVED.store(origin.signal, Ordering::SeqCst);
+    if origin.process.is_some() {
+        for pid in children {
+            unsafe { libc::kill(pid as libc::pid_t, origin.signal) };
+        }
+    }
+}
+
+/// Name of a signal that commonly ends a student's program, e.g. SIGSEGV for 11
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        24 => "SIGXCPU",
+        25 => "SIGXFSZ",
+        _ => return None,
+    })
+}
+
+/// How a program that failed ended, in a line, e.g. `./a.out terminated by SIGSEGV
+/// (exit 139)` or `./a.out exited with status 3`; None if it succeeded
+pub fn describe_exit(program: &str, status: ExitStatus) -> Option<String> {
+    if status.success() {
+        return None;
+    }
+    let code = exit_codes::of_child(status);
+    Some(match status.signal() {
+        Some(signal) => format!(
+            "{} terminated by {} (exit {}{})",
+            program,
+            signal_name(signal).map_or_else(|| format!("signal {}", signal), str::to_string),
+            code,
+            if status.core_dumped() {
+                ", core dumped"
+            } else {
+                ""
+            }
+        ),
+        None => format!("{} exited with status {}", program, code),
+    })
+}
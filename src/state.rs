@@ -0,0 +1,106 @@
+use colored::Colorize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Set once a state write has failed because the disk is read-only or full
+static IN_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// State written since writes started failing, kept for the rest of the run
+fn memory() -> &'static Mutex<HashMap<PathBuf, Vec<u8>>> {
+    static MEMORY: OnceLock<Mutex<HashMap<PathBuf, Vec<u8>>>> = OnceLock::new();
+    MEMORY.get_or_init(Mutex::default)
+}
+
+/// Directory holding per-user quicktool state, following the XDG base directory spec
+pub fn state_dir() -> PathBuf {
+    match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("quicktool"),
+        None => PathBuf::from(env::var("HOME").unwrap_or_default())
+            .join(".local")
+            .join("state")
+            .join("quicktool"),
+    }
+}
+
+/// Directory holding per-user quicktool settings, following the XDG base directory spec
+pub fn config_dir() -> PathBuf {
+    match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("quicktool"),
+        None => PathBuf::from(env::var("HOME").unwrap_or_default())
+            .join(".config")
+            .join("quicktool"),
+    }
+}
+
+/// Whether a write failed because the disk is read-only, full or over quota, as on exam
+/// accounts and full home directories, rather than because of a bug
+fn unwritable(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::ReadOnlyFilesystem
+            | ErrorKind::StorageFull
+            | ErrorKind::QuotaExceeded
+            | ErrorKind::PermissionDenied
+    )
+}
+
+/// Keep state in memory from now on, warning the first time
+fn switch_to_memory(path: &Path, error: &io::Error) {
+    if !IN_MEMORY.swap(true, Ordering::SeqCst) {
+        eprintln!(
+            "{} cannot save {}: {}; quicktool will not remember anything from this run",
+            "Warning:".yellow().bold(),
+            path.display(),
+            error
+        );
+    }
+}
+
+/// Write a state file, such as a cache or history, creating its directory. Once the disk
+/// turns out to be read-only or full, state is kept in memory for the rest of the run
+/// instead, with a single warning. Other failures are returned.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let contents = contents.as_ref();
+    if !IN_MEMORY.load(Ordering::SeqCst) {
+        let written = match path.parent() {
+            Some(parent) => fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|()| fs::write(path, contents));
+        match written {
+            Err(e) if unwritable(&e) => switch_to_memory(path, &e),
+            written => return written,
+        }
+    }
+    if let Ok(mut memory) = memory().lock() {
+        memory.insert(path.to_path_buf(), contents.to_vec());
+    }
+    Ok(())
+}
+
+/// Copy a file into state like `write`
+pub fn copy(source: &Path, path: &Path) -> io::Result<()> {
+    write(path, fs::read(source)?)
+}
+
+/// Read a state file, as kept in memory if writes to it have failed this run
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    if let Some(contents) = memory()
+        .lock()
+        .ok()
+        .and_then(|memory| memory.get(path).cloned())
+    {
+        return Ok(contents);
+    }
+    fs::read(path)
+}
+
+/// Read a state file as text; see `read`
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(io::Error::other)
+}
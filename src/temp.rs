@@ -0,0 +1,182 @@
+use log::{info, warn};
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::config::ClassConfig;
+use crate::tools::du::format_size;
+
+/// Start of the name of every directory quicktool makes in the temp root, so ones left
+/// behind by a crash can be found
+const PREFIX: &str = "quicktool-";
+
+/// Age after which a directory left behind is removed
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Names tried for a new directory before giving up
+const CREATE_ATTEMPTS: u32 = 16;
+
+/// Choose where quicktool and the commands it runs keep temporary files, by setting
+/// TMPDIR: `--tmpdir`, or else TMPDIR as the user set it, or else the class's
+/// `quicktool_tmpdir`, for lab machines with a tiny /tmp
+pub fn setup(config: &mut ClassConfig, tmpdir: Option<&Path>) -> Result<(), String> {
+    let root = match tmpdir {
+        Some(dir) => dir.to_path_buf(),
+        None if env::var_os("TMPDIR").is_some_and(|dir| !dir.is_empty()) => return Ok(()),
+        None => {
+            let _ = config.load_class_config();
+            match config.get_custom_config("quicktool_tmpdir") {
+                Some(dir) => PathBuf::from(dir),
+                None => return Ok(()),
+            }
+        }
+    };
+    // A class may name a directory per user that does not exist yet
+    if tmpdir.is_none() {
+        if let Err(e) = fs::create_dir_all(&root) {
+            return config.config_warning(&format!(
+                "quicktool_tmpdir {} cannot be created: {}",
+                root.display(),
+                e
+            ));
+        }
+    }
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    // Commands may run from another directory, where a relative root names another place
+    let root = root.canonicalize().unwrap_or(root);
+    info!("Keeping temporary files in {}", root.display());
+    env::set_var("TMPDIR", &root);
+    Ok(())
+}
+
+/// A directory for a command's temporary files in the temp root, removed when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Make `quicktool-PURPOSE-RANDOM` in the temp root, first removing any of the user's
+    /// directories that runs which crashed left there
+    pub fn new(purpose: &str) -> Result<TempDir, String> {
+        let root = env::temp_dir();
+        let path = create_private_dir(&root, purpose)?;
+        let dir = TempDir { path };
+        dir.remove_stale(&root);
+        Ok(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the user's quicktool directories in the temp root not touched for a day
+    fn remove_stale(&self, root: &Path) {
+        let Ok(owner) = fs::metadata(&self.path).map(|metadata| metadata.uid()) else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(root) else {
+            return;
+        };
+        let now = SystemTime::now();
+        let mut reclaimed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let stale = entry.file_name().to_string_lossy().starts_with(PREFIX)
+                && path != self.path
+                && entry.metadata().is_ok_and(|metadata| {
+                    metadata.is_dir()
+                        && metadata.uid() == owner
+                        && metadata
+                            .modified()
+                            .ok()
+                            .and_then(|modified| now.duration_since(modified).ok())
+                            .is_some_and(|age| age > STALE_AFTER)
+                });
+            if !stale {
+                continue;
+            }
+            let size = size_of(&path);
+            match fs::remove_dir_all(&path) {
+                Ok(()) => reclaimed += size,
+                Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+            }
+        }
+        if reclaimed > 0 {
+            info!(
+                "Removed old temporary files in {}, freeing {}",
+                root.display(),
+                format_size(reclaimed)
+            );
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        info!(
+            "Removing {}, which held {}",
+            self.path.display(),
+            format_size(size_of(&self.path))
+        );
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Make a new directory only the user can use. The temp root is shared, so its name
+/// cannot be guessed and a path someone else made first is never used: programs are
+/// compiled into it and then run.
+fn create_private_dir(root: &Path, purpose: &str) -> Result<PathBuf, String> {
+    for _ in 0..CREATE_ATTEMPTS {
+        let path = root.join(format!("{}{}-{:016x}", PREFIX, purpose, random_suffix()));
+        match DirBuilder::new().mode(0o700).create(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+        }
+        let metadata = fs::symlink_metadata(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+            return Err(format!(
+                "{} is not a private directory of yours; someone may have replaced it",
+                path.display()
+            ));
+        }
+        return Ok(path);
+    }
+    Err(format!(
+        "Failed to create a temporary directory in {}: every name tried was taken",
+        root.display()
+    ))
+}
+
+/// A number others cannot predict, from the keys std draws from the system for hashing
+fn random_suffix() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    hasher.write_u128(now.map_or(0, |elapsed| elapsed.as_nanos()));
+    hasher.write_u32(std::process::id());
+    hasher.finish()
+}
+
+/// Bytes the files under a directory take up
+fn size_of(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
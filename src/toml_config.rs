@@ -0,0 +1,677 @@
+//! quicktool.toml in the class account and config.toml in the user's quicktool config
+//! directory.
+//!
+//! Settings are applied in this order, each over the ones before: the class's config.sh,
+//! the class's quicktool.toml, then the user's config.toml. The user's file may only
+//! change settings that affect no one else, such as `[autotest] nice`.
+//!
+//! The user's `[user]` section holds their own settings, e.g. `compiler = "dcc"` or
+//! `history = true`. A setting named after a class, e.g. `COMP1511_compiler`, applies to
+//! that class only and wins over the one for every class. `default_class` is the class
+//! `quicktool` runs commands for when it is not given one: a class in the program's name,
+//! e.g. `1511 autotest`, or named first, e.g. `quicktool 1521 autotest`, wins over it.
+
+use std::collections::HashSet;
+
+/// Name of the settings file in the class account
+pub const FILE_NAME: &str = "quicktool.toml";
+
+/// Name of the user's settings file in their quicktool config directory
+pub const USER_FILE_NAME: &str = "config.toml";
+
+/// The type a setting's value must have
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Number,
+    Flag,
+    List,
+}
+
+impl Kind {
+    fn describe(self) -> &'static str {
+        match self {
+            Kind::Text => "a string",
+            Kind::Number => "a number",
+            Kind::Flag => "true or false",
+            Kind::List => "a list of strings",
+        }
+    }
+}
+
+/// A typed setting and the config.sh variable it sets
+struct Setting {
+    section: &'static str,
+    key: &'static str,
+    variable: &'static str,
+    kind: Kind,
+    /// Users may set it for themselves, as it only changes how quicktool behaves for them
+    user: bool,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        section: "autotest",
+        key: "path",
+        variable: "autotest_path",
+        kind: Kind::Text,
+        user: false,
+    },
+    Setting {
+        section: "autotest",
+        key: "stage_class_codes",
+        variable: "autotest_stage_class_codes",
+        kind: Kind::List,
+        user: false,
+    },
+    Setting {
+        section: "autotest",
+        key: "compiler_version_mismatch",
+        variable: "compiler_version_mismatch",
+        kind: Kind::Text,
+        user: false,
+    },
+    Setting {
+        section: "autotest",
+        key: "nice",
+        variable: "quicktool_nice",
+        kind: Kind::Number,
+        user: true,
+    },
+    Setting {
+        section: "autotest",
+        key: "ionice",
+        variable: "quicktool_ionice",
+        kind: Kind::Text,
+        user: true,
+    },
+    Setting {
+        section: "fetch-activity",
+        key: "activities_directory",
+        variable: "activities_directory",
+        kind: Kind::Text,
+        user: false,
+    },
+    Setting {
+        section: "fetch-activity",
+        key: "session_directory",
+        variable: "public_html_session_directory",
+        kind: Kind::Text,
+        user: false,
+    },
+    Setting {
+        section: "fetch-activity",
+        key: "course_account",
+        variable: "course_account",
+        kind: Kind::Text,
+        user: false,
+    },
+    Setting {
+        section: "shell",
+        key: "editor",
+        variable: "quicktool_editor",
+        kind: Kind::Text,
+        user: true,
+    },
+    Setting {
+        section: "shell",
+        key: "report_exit",
+        variable: "quicktool_report_exit",
+        kind: Kind::Flag,
+        user: true,
+    },
+    Setting {
+        section: "shell",
+        key: "templates_directory",
+        variable: "templates_directory",
+        kind: Kind::Text,
+        user: false,
+    },
+];
+
+/// Section of the class's file whose keys are config.sh variable names, for settings
+/// without a typed section
+const CLASS_SECTION: &str = "class";
+
+/// Section of the user's file holding their own settings
+const USER_SECTION: &str = "user";
+
+/// A value in a quicktool.toml
+enum Value {
+    Text(String),
+    Number(i64),
+    Flag(bool),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Text(_) => Kind::Text,
+            Value::Number(_) => Kind::Number,
+            Value::Flag(_) => Kind::Flag,
+            Value::List(_) => Kind::List,
+        }
+    }
+
+    /// The value as config.sh would hold it: flags as yes or no, lists separated by spaces
+    fn to_variable(&self) -> String {
+        match self {
+            Value::Text(text) => text.clone(),
+            Value::Number(number) => number.to_string(),
+            Value::Flag(true) => "yes".to_string(),
+            Value::Flag(false) => "no".to_string(),
+            Value::List(items) => items
+                .iter()
+                .map(Value::to_variable)
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// What a settings file sets
+#[derive(Debug, Default, PartialEq)]
+pub struct Settings {
+    /// config.sh variables, in the order they are set
+    pub variables: Vec<(String, String)>,
+    /// The user's own settings from `[user]`, as config.sh would hold their values
+    pub user: Vec<(String, String)>,
+    /// One for each line that could not be used
+    pub warnings: Vec<String>,
+}
+
+/// Read a quicktool.toml, or with `user` a user's config.toml, which may only set the
+/// settings users may override and their own.
+///
+/// quicktool reads the part of TOML these files need: `[section]` headers, `#` comments
+/// and `key = value` lines with strings, integers, booleans and single-line arrays.
+pub fn parse(path: &str, contents: &str, user: bool) -> Settings {
+    let mut variables = Vec::new();
+    let mut user_settings = Vec::new();
+    let mut warnings = Vec::new();
+    let mut section = String::new();
+    let mut seen = HashSet::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let mut warn =
+            |message: String| warnings.push(format!("{}:{}: {}", path, number + 1, message));
+        let (key, value) = match parse_line(line) {
+            Ok(Line::Blank) => continue,
+            Ok(Line::Section(name)) => {
+                section = name;
+                continue;
+            }
+            Ok(Line::Entry(key, value)) => (key, value),
+            Err(e) => {
+                warn(e);
+                continue;
+            }
+        };
+        if section.is_empty() {
+            warn(format!("{} is not in a section such as [autotest]", key));
+            continue;
+        }
+        if !seen.insert((section.clone(), key.clone())) {
+            warn(format!("[{}] {} is set twice", section, key));
+            continue;
+        }
+
+        if section == CLASS_SECTION || section == USER_SECTION {
+            let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if user != (section == USER_SECTION) {
+                warn(format!(
+                    "[{}] can only be set in {}",
+                    section,
+                    if user {
+                        format!("the class's {}", FILE_NAME)
+                    } else {
+                        format!("a user's own {}", USER_FILE_NAME)
+                    }
+                ));
+            } else if !valid {
+                warn(format!("[{}] {} is not a setting name", section, key));
+            } else if nested_list(&value) {
+                warn(format!("[{}] {} cannot hold nested lists", section, key));
+            } else if user {
+                user_settings.push((key, value.to_variable()));
+            } else {
+                variables.push((key, value.to_variable()));
+            }
+            continue;
+        }
+
+        let Some(setting) = SETTINGS
+            .iter()
+            .find(|setting| setting.section == section && setting.key == key)
+        else {
+            warn(format!("unknown setting [{}] {}", section, key));
+            continue;
+        };
+        let well_typed = match &value {
+            Value::List(items) => {
+                setting.kind == Kind::List && items.iter().all(|item| item.kind() == Kind::Text)
+            }
+            value => value.kind() == setting.kind,
+        };
+        if !well_typed {
+            warn(format!(
+                "[{}] {} should be {}",
+                section,
+                key,
+                setting.kind.describe()
+            ));
+        } else if user && !setting.user {
+            warn(format!(
+                "[{}] {} can only be set in the class's {}",
+                section, key, FILE_NAME
+            ));
+        } else {
+            variables.push((setting.variable.to_string(), value.to_variable()));
+        }
+    }
+    Settings {
+        variables,
+        user: user_settings,
+        warnings,
+    }
+}
+
+/// Whether a list holds lists, which a config.sh variable has no way to hold
+fn nested_list(value: &Value) -> bool {
+    matches!(value, Value::List(items) if items.iter().any(|item| item.kind() == Kind::List))
+}
+
+/// What one line of a quicktool.toml holds
+enum Line {
+    Blank,
+    Section(String),
+    Entry(String, Value),
+}
+
+fn parse_line(line: &str) -> Result<Line, String> {
+    let mut cursor = Cursor { rest: line };
+    cursor.skip_space();
+    let line = match cursor.peek() {
+        None | Some('#') => return Ok(Line::Blank),
+        Some('[') => {
+            cursor.bump();
+            if cursor.peek() == Some('[') {
+                return Err("arrays of tables are not supported".to_string());
+            }
+            cursor.skip_space();
+            let name = cursor.key()?;
+            cursor.skip_space();
+            if !cursor.eat(']') {
+                return Err(format!("expected ] after [{}", name));
+            }
+            Line::Section(name)
+        }
+        Some(_) => {
+            let key = cursor.key()?;
+            cursor.skip_space();
+            if cursor.peek() == Some('.') {
+                return Err(format!("dotted keys such as {}. are not supported", key));
+            }
+            if !cursor.eat('=') {
+                return Err(format!("expected = after {}", key));
+            }
+            cursor.skip_space();
+            Line::Entry(key, cursor.value()?)
+        }
+    };
+    cursor.skip_space();
+    match cursor.peek() {
+        None | Some('#') => Ok(line),
+        Some(_) => Err(format!(
+            "unexpected '{}' at the end of the line",
+            cursor.rest
+        )),
+    }
+}
+
+/// The part of a line still to be parsed
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let matched = self.peek() == Some(expected);
+        if matched {
+            self.bump();
+        }
+        matched
+    }
+
+    fn skip_space(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t']);
+    }
+
+    /// A bare key such as `report_exit` or `fetch-activity`, or a quoted one
+    fn key(&mut self) -> Result<String, String> {
+        if self.eat('"') {
+            return self.basic_string();
+        }
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err(format!("expected a name at '{}'", self.rest));
+        }
+        let (key, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(key.to_string())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => {
+                self.bump();
+                self.basic_string().map(Value::Text)
+            }
+            Some('\'') => {
+                self.bump();
+                let end = self
+                    .rest
+                    .find('\'')
+                    .ok_or("a string is missing its closing '")?;
+                let text = self.rest[..end].to_string();
+                self.rest = &self.rest[end + 1..];
+                Ok(Value::Text(text))
+            }
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                const UNCLOSED: &str = "expected , or ] in a list (lists must fit on one line)";
+                loop {
+                    self.skip_space();
+                    if self.eat(']') {
+                        break;
+                    }
+                    if matches!(self.peek(), None | Some('#')) {
+                        return Err(UNCLOSED.to_string());
+                    }
+                    items.push(self.value()?);
+                    self.skip_space();
+                    if !self.eat(',') && self.peek() != Some(']') {
+                        return Err(UNCLOSED.to_string());
+                    }
+                }
+                Ok(Value::List(items))
+            }
+            _ => {
+                let end = self
+                    .rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '#'))
+                    .unwrap_or(self.rest.len());
+                let (token, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                match token {
+                    "true" => Ok(Value::Flag(true)),
+                    "false" => Ok(Value::Flag(false)),
+                    _ => token
+                        .replace('_', "")
+                        .parse()
+                        .map(Value::Number)
+                        .map_err(|_| {
+                            format!(
+                                "'{}' is not a string, number, true, false or list (quote strings)",
+                                token
+                            )
+                        }),
+                }
+            }
+        }
+    }
+
+    /// The rest of a `"`-quoted string, with its escapes
+    fn basic_string(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        loop {
+            match self.bump().ok_or("a string is missing its closing \"")? {
+                '"' => return Ok(text),
+                '\\' => text.push(match self.bump() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('u') => {
+                        let digits = self
+                            .rest
+                            .get(..4)
+                            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+                            .ok_or("\\u needs four hex digits")?;
+                        let c = u32::from_str_radix(digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("\\u{} is not a character", digits))?;
+                        self.rest = &self.rest[4..];
+                        c
+                    }
+                    other => {
+                        return Err(format!(
+                            "unknown escape \\{} in a string",
+                            other.map(String::from).unwrap_or_default()
+                        ))
+                    }
+                }),
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(contents: &str) -> Settings {
+        parse("quicktool.toml", contents, false)
+    }
+
+    fn user(contents: &str) -> Settings {
+        parse("config.toml", contents, true)
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn typed_settings_become_variables() {
+        let settings = class(
+            "# comment\n\
+             [autotest]\n\
+             path = \"/opt/autotest\"  # trailing comment\n\
+             nice = 1_0\n\
+             stage_class_codes = [\"1511\", '1911',]\n\
+             [shell]\n\
+             report_exit = false\n",
+        );
+        assert_eq!(settings.warnings, Vec::<String>::new());
+        assert_eq!(
+            settings.variables,
+            pairs(&[
+                ("autotest_path", "/opt/autotest"),
+                ("quicktool_nice", "10"),
+                ("autotest_stage_class_codes", "1511 1911"),
+                ("quicktool_report_exit", "no"),
+            ])
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let settings = class(
+            r#"[class]
+quoted = "a \"b\" \\ c\td\n"
+unicode = "caf\u00e9 \u2500"
+literal = 'C:\no\escapes'
+"#,
+        );
+        assert_eq!(settings.warnings, Vec::<String>::new());
+        assert_eq!(
+            settings.variables,
+            pairs(&[
+                ("quoted", "a \"b\" \\ c\td\n"),
+                ("unicode", "café ─"),
+                ("literal", "C:\\no\\escapes"),
+            ])
+        );
+    }
+
+    #[test]
+    fn bad_escapes_are_reported() {
+        for (line, message) in [
+            (r#"a = "\q""#, "unknown escape \\q"),
+            (r#"a = "\u00""#, "\\u needs four hex digits"),
+            (r#"a = "\u+041""#, "\\u needs four hex digits"),
+            (r#"a = "\ud800""#, "\\ud800 is not a character"),
+            (r#"a = "open"#, "missing its closing"),
+        ] {
+            let settings = class(&format!("[class]\n{}\n", line));
+            assert!(settings.variables.is_empty(), "{}", line);
+            assert_eq!(settings.warnings.len(), 1, "{}", line);
+            assert!(
+                settings.warnings[0].contains(message),
+                "{}: {}",
+                line,
+                settings.warnings[0]
+            );
+        }
+    }
+
+    #[test]
+    fn lists() {
+        let settings = class(
+            "[class]\n\
+             empty = []\n\
+             words = [ \"a\" , \"b c\" ]\n\
+             nested = [[\"a\"], \"b\"]\n\
+             [autotest]\n\
+             stage_class_codes = [[\"1511\"]]\n\
+             unclosed = [\"a\",\n",
+        );
+        assert_eq!(
+            settings.variables,
+            pairs(&[("empty", ""), ("words", "a b c")])
+        );
+        assert_eq!(
+            settings.warnings,
+            [
+                "quicktool.toml:4: [class] nested cannot hold nested lists",
+                "quicktool.toml:6: [autotest] stage_class_codes should be a list of strings",
+                "quicktool.toml:7: expected , or ] in a list (lists must fit on one line)",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_first() {
+        let settings = class("[class]\na = \"1\"\nb = \"2\"\na = \"3\"\n");
+        assert_eq!(settings.variables, pairs(&[("a", "1"), ("b", "2")]));
+        assert_eq!(
+            settings.warnings,
+            ["quicktool.toml:4: [class] a is set twice"]
+        );
+    }
+
+    #[test]
+    fn keys_outside_a_section() {
+        let settings = class("nice = 5\n[autotest]\nnice = 6\n");
+        assert_eq!(settings.variables, pairs(&[("quicktool_nice", "6")]));
+        assert_eq!(
+            settings.warnings,
+            ["quicktool.toml:1: nice is not in a section such as [autotest]"]
+        );
+    }
+
+    #[test]
+    fn wrong_types_and_unknown_settings() {
+        let settings = class(
+            "[autotest]\n\
+             nice = \"5\"\n\
+             speed = 1\n\
+             [shell]\n\
+             report_exit = yes\n",
+        );
+        assert!(settings.variables.is_empty());
+        assert_eq!(
+            settings.warnings,
+            [
+                "quicktool.toml:2: [autotest] nice should be a number",
+                "quicktool.toml:3: unknown setting [autotest] speed",
+                "quicktool.toml:5: 'yes' is not a string, number, true, false or list \
+                 (quote strings)",
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_syntax() {
+        let settings = class("[[autotest]]\n[autotest]\npath.x = 1\nnice = 1 2\n");
+        assert_eq!(
+            settings.warnings,
+            [
+                "quicktool.toml:1: arrays of tables are not supported",
+                "quicktool.toml:3: dotted keys such as path. are not supported",
+                "quicktool.toml:4: unexpected '2' at the end of the line",
+            ]
+        );
+    }
+
+    #[test]
+    fn users_may_only_set_their_own_settings() {
+        let settings = user(
+            "[autotest]\n\
+             nice = 19\n\
+             path = \"/tmp/mine\"\n\
+             [class]\n\
+             course_account = \"me\"\n\
+             [user]\n\
+             COMP1511_compiler = \"dcc\"\n\
+             history = true\n\
+             not-a-name = 1\n",
+        );
+        assert_eq!(settings.variables, pairs(&[("quicktool_nice", "19")]));
+        assert_eq!(
+            settings.user,
+            pairs(&[("COMP1511_compiler", "dcc"), ("history", "yes")])
+        );
+        assert_eq!(
+            settings.warnings,
+            [
+                "config.toml:3: [autotest] path can only be set in the class's quicktool.toml",
+                "config.toml:5: [class] can only be set in the class's quicktool.toml",
+                "config.toml:9: [user] not-a-name is not a setting name",
+            ]
+        );
+    }
+
+    #[test]
+    fn classes_may_not_set_user_settings() {
+        let settings = class("[user]\ncompiler = \"dcc\"\n");
+        assert!(settings.user.is_empty());
+        assert_eq!(
+            settings.warnings,
+            ["quicktool.toml:2: [user] can only be set in a user's own config.toml"]
+        );
+    }
+}
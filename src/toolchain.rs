@@ -0,0 +1,132 @@
+use colored::*;
+use regex::Regex;
+use std::cmp::Ordering;
+
+use crate::config::ClassConfig;
+use crate::tools::healthcheck::probe_version;
+
+/// Flag that compiles even when the compiler differs from the pinned version
+pub const IGNORE_FLAG: &str = "--ignore-toolchain";
+
+/// A compiler version the class marks with, from `compiler_version_<compiler>` in
+/// config.sh: `14` matches any 14.x, while `>=2.26`, `>`, `<=` and `<` compare
+#[derive(Debug)]
+struct Requirement {
+    comparison: &'static str,
+    version: Vec<u64>,
+}
+
+impl Requirement {
+    fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let comparison = [">=", "<=", ">", "<", "="]
+            .into_iter()
+            .find(|op| spec.starts_with(op))
+            .unwrap_or("");
+        let version = parse_version(spec[comparison.len()..].trim())?;
+        Some(Requirement {
+            comparison,
+            version,
+        })
+    }
+
+    fn matches(&self, found: &[u64]) -> bool {
+        let ordering = compare(found, &self.version);
+        match self.comparison {
+            ">=" => ordering != Ordering::Less,
+            ">" => ordering == Ordering::Greater,
+            "<=" => ordering != Ordering::Greater,
+            "<" => ordering == Ordering::Less,
+            // A bare version pins only the components it gives
+            _ => found
+                .iter()
+                .take(self.version.len())
+                .eq(self.version.iter()),
+        }
+    }
+}
+
+/// Why the compiler on PATH is not the one the class pins, if the class pins one and
+/// it can be found. An unreadable pin is a configuration warning and is not checked.
+pub fn mismatch(config: &ClassConfig, compiler: &str) -> Result<Option<String>, String> {
+    let Some(spec) = config.get_custom_config(&format!("compiler_version_{}", compiler)) else {
+        return Ok(None);
+    };
+    let Some(requirement) = Requirement::parse(spec) else {
+        config.config_warning(&format!(
+            "compiler_version_{} is not a version such as 14 or >=2.26: {:?}",
+            compiler, spec
+        ))?;
+        return Ok(None);
+    };
+    // A missing compiler fails on its own when autotest runs it
+    let Some(line) = probe_version(compiler) else {
+        return Ok(None);
+    };
+    let found = Regex::new(r"\d+(\.\d+)*")
+        .expect("valid version pattern")
+        .find(&line)
+        .and_then(|version| parse_version(version.as_str()));
+    Ok(match found {
+        Some(found) if requirement.matches(&found) => None,
+        Some(found) => Some(format!(
+            "{} {} is not the version {} is marked with ({})",
+            compiler,
+            join_version(&found),
+            config.class,
+            spec.trim()
+        )),
+        None => Some(format!(
+            "could not tell which version of {} this is ({:?}); {} is marked with {}",
+            compiler,
+            line,
+            config.class,
+            spec.trim()
+        )),
+    })
+}
+
+/// Check the compiler against the class's pin before compiling: a warning by default,
+/// or an error when config.sh sets `compiler_version_mismatch=refuse`, unless `ignore`
+pub fn enforce(config: &ClassConfig, compiler: &str, ignore: bool) -> Result<(), String> {
+    let Some(problem) = mismatch(config, compiler)? else {
+        return Ok(());
+    };
+    let refuse = config
+        .get_custom_config("compiler_version_mismatch")
+        .is_some_and(|setting| setting == "refuse");
+    if refuse && !ignore {
+        return Err(format!(
+            "{}; your results may differ from marking (use {} to compile anyway)",
+            problem, IGNORE_FLAG
+        ));
+    }
+    eprintln!(
+        "{} {}; your results may differ from marking",
+        "Warning:".yellow().bold(),
+        problem
+    );
+    Ok(())
+}
+
+fn parse_version(text: &str) -> Option<Vec<u64>> {
+    text.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn join_version(version: &[u64]) -> String {
+    version
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Compare versions component by component, missing components counting as 0
+fn compare(a: &[u64], b: &[u64]) -> Ordering {
+    let len = a.len().max(b.len());
+    let component = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| component(a, i).cmp(&component(b, i)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
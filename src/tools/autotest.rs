@@ -1,26 +1,61 @@
+use clap::{CommandFactory, Parser, ValueEnum};
+use colored::*;
 use log::error;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::env;
 use std::ffi::OsStr;
-use std::path::Path;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 use std::process::{exit, Command, Stdio};
+use std::time::Duration;
 
+use crate::cli;
 use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::metadata::{AutotestHistory, FetchRecord};
+use crate::notices;
+use crate::output::{self, Plumbing};
+use crate::retry;
+use crate::shell_quote;
+use crate::signals::{self, Work};
+use crate::tools::autotest_backend::{self, Capabilities};
+use crate::tools::autotest_parameters::AutotestParameters;
+use crate::tools::fingerprint::Fingerprint;
+use crate::tools::forum_report;
+use crate::tools::fuzz;
+use crate::tools::unit_tests;
+use crate::toolchain;
+use crate::trace;
+use crate::user_config::UserConfig;
+
+/// Compilers autotest can be asked to use
+const COMPILERS: &[&str] = &["dcc", "gcc", "clang"];
+
+/// Class codes autotest-stage skips before its arguments unless the class sets
+/// `autotest_stage_class_codes`
+const DEFAULT_STAGE_CLASS_CODES: &str = "1091";
 
 /// Common function to handle both autotest and autotest-stage
-pub fn run_test(config: &mut ClassConfig, args: &[String]) -> Result<(), String> {
+pub fn run_test(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
     // Path to the "autotest" symlink
     let bin_path = config.bin_path.as_deref().unwrap_or("");
     let original_autotest_softlink = Path::new(bin_path).join("autotest");
 
     // Ensure autotest exists
-    if !original_autotest_softlink.exists() {
-        return Err(format!("{}: autotest not found", config.class));
+    if !retry::exists(&original_autotest_softlink) {
+        return Err(QuicktoolError::ToolNotFound(format!(
+            "{}: autotest not found",
+            config.class
+        )));
     }
 
     // Resolve symlink to get the real path
-    let autotest_path = std::fs::canonicalize(&original_autotest_softlink)
-        .map_err(|e| format!("Failed to canonicalize autotest path: {}", e))?;
+    let autotest_path = retry::retry_io("resolving autotest", || {
+        std::fs::canonicalize(&original_autotest_softlink)
+    })
+    .map_err(|e| format!("Failed to canonicalize autotest path: {}", e))?;
 
     // Load the config from config.sh (only once)
     let config_sh = autotest_path
@@ -29,7 +64,10 @@ pub fn run_test(config: &mut ClassConfig, args: &[String]) -> Result<(), String>
         .join("config.sh");
     config
         .load_bash_config(config_sh.to_string_lossy().as_ref())
-        .map_err(|e| format!("Could not load bash config: {}", e))?;
+        .map_err(|e| {
+            QuicktoolError::ConfigMissing(format!("Could not load bash config: {}", e))
+        })?;
+    signals::set_priority(config.priority()?);
 
     // Determine which functionality to run based on the first argument
     let binary_name = Path::new(&args[0])
@@ -41,39 +79,39 @@ pub fn run_test(config: &mut ClassConfig, args: &[String]) -> Result<(), String>
     match binary_name {
         "autotest" => run_autotest(config, passed_args),
         "autotest-stage" => run_autotest_stage(config, passed_args),
-        _ => Err(String::from(
-            "Error: Binary must be called as 'autotest' or 'autotest-stage'",
-        )),
+        _ => Err("Error: Binary must be called as 'autotest' or 'autotest-stage'".into()),
     }
 }
 
 /// Run the main autotest flow.
-fn run_autotest(config: &ClassConfig, args: &[String]) -> Result<(), String> {
+fn run_autotest(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
     // Build relevant paths
-    let activities_dir = Path::new(
-        config
-            .get_custom_config("public_html_session_directory")
-            .unwrap_or(&String::new()),
-    )
-    .join("activities");
-
-    let autotest_script = Path::new("/usr/local/share/autotest/autotest.py");
-    let c_check_path = Path::new(
-        config
-            .get_custom_config("public_html_session_directory")
-            .unwrap_or(&String::new()),
-    )
-    .join("public/_infra/extern/c_check/c_check.py");
+    let (activities_dir, autotest_script, c_check_path) = autotest_paths(config)?;
+    let capabilities = autotest_backend::probe(&autotest_script);
 
     // Figure out compiler & arguments
-    let (compiler, remaining_args) = select_compiler(args);
+    let options: AutotestArgs = cli::parse_passthrough_args(&config.class, "autotest", args)?;
+    let forum_report = options.report.is_some();
+    let (compiler, mut remaining_args) =
+        select_compiler(&options.args, default_compiler(config)?);
+    toolchain::enforce(config, &compiler, options.ignore_toolchain)?;
+    let launcher = compiler_launcher(config, options.ccache, &compiler)?;
+    if let Some(target) = &options.fuzz {
+        let time = Duration::from_secs(options.time);
+        return fuzz::run_fuzz(config, target, time, &compiler, &activities_dir);
+    }
+
+    if !remaining_args.iter().any(|arg| !arg.starts_with('-')) {
+        if let Some(exercise) = infer_exercise(config, &activities_dir)? {
+            remaining_args.insert(0, exercise);
+        }
+    }
+    let exercise = remaining_args.iter().find(|arg| !arg.starts_with('-'));
+    notices::show_notices(config, exercise.map(String::as_str));
 
     // Prepare parameters for autotest
-    let parameters = format!(
-        "default_compilers = {{'c': [['{compiler}', '-Werror']]}} \
-         default_checkers = {{'c': [['python3', '{}']]}}",
-        c_check_path.display()
-    );
+    let parameters = default_parameters(launcher.as_deref(), &compiler, &c_check_path);
+    let parameters = parameter_args(config, &capabilities, &autotest_script, parameters)?;
 
     // Build the command
     let mut command = Command::new("python3");
@@ -87,29 +125,119 @@ fn run_autotest(config: &ClassConfig, args: &[String]) -> Result<(), String> {
         .arg(&autotest_script)
         .arg("--exercise_directory")
         .arg(&activities_dir)
-        .arg("--parameters")
-        .arg(&parameters);
+        .args(&parameters);
 
     // Add remaining arguments
     command.args(&remaining_args);
 
-    // Execute
-    run_and_propagate_exit_status(command)
+    // Staff unit tests of the exercise run after autotest's own tests
+    let unit_test_dir = exercise.map(|exercise| activities_dir.join(exercise));
+    let mut unit_compile: Vec<String> = launcher.into_iter().collect();
+    unit_compile.push(compiler.clone());
+
+    if config.dry_run {
+        dry_run::command(&command);
+        if let Some(dir) = &unit_test_dir {
+            for test_file in unit_tests::unit_test_files(dir) {
+                dry_run::report(&format!(
+                    "would compile and run {} with your code",
+                    test_file.display()
+                ));
+            }
+        }
+        return Ok(());
+    }
+
+    // Execute, remembering whether the exercise passed for fetch-activity's prerequisites
+    trace::command(&command);
+    let fingerprint = Fingerprint::collect(config);
+    let (status, mut text) = if forum_report {
+        let plumbing = Plumbing {
+            work: Work::Heavy,
+            ..Plumbing::default()
+        };
+        let output = output::run(&mut command, &plumbing)
+            .map_err(|e| format!("Failed to execute process: {}", e))?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        (output.status, Some(text))
+    } else {
+        let status = signals::status(&mut command, Work::Heavy)
+            .map_err(|e| format!("Failed to execute process: {}", e))?;
+        (status, None)
+    };
+
+    let unit_results = match (exercise, &unit_test_dir) {
+        (Some(exercise), Some(dir)) => {
+            unit_tests::run_unit_tests(exercise, dir, &unit_compile)?
+        }
+        _ => Vec::new(),
+    };
+    if !unit_results.is_empty() {
+        unit_tests::print_results(&unit_results);
+        if let Some(text) = &mut text {
+            text.push_str(&unit_tests::results_text(&unit_results));
+        }
+    }
+    let unit_tests_passed = unit_results.iter().all(|result| result.passed);
+
+    if let Some(text) = text {
+        let exercise = exercise.map(String::as_str).unwrap_or("autotest");
+        let report = forum_report::forum_report(exercise, &compiler, &text, &fingerprint);
+        println!();
+        println!("----- copy everything below into your forum post -----");
+        print!("{}", report);
+    }
+    if let Some(exercise) = exercise {
+        let recorded = AutotestHistory::record(
+            Path::new("."),
+            exercise,
+            status.success() && unit_tests_passed,
+            &fingerprint.to_json(),
+        );
+        // Even with --strict, quicktool exits with autotest's status below, which matters
+        // more than the record of it
+        if let Err(e) = recorded {
+            if let Err(e) =
+                config.config_warning(&format!("Could not record autotest result: {}", e))
+            {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+    if !status.success() {
+        exit(exit_codes::of_child(status));
+    }
+    if !unit_tests_passed {
+        exit(exit_codes::FAILURE);
+    }
+    Ok(())
 }
 
 /// Run the autotest-stage flow.
-fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), String> {
-    // Accept an optional "1091" prefix, then optional compiler, then a stage prefix, then a command
-    let compiler_options = ["dcc", "gcc", "clang"];
+fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let options: AutotestStageArgs =
+        cli::parse_passthrough_args(&config.class, "autotest-stage", args)?;
+    let args = &options.args;
+    let ccache = options.ccache;
+
+    // Accept an optional class code prefix, then optional compiler, then a stage prefix, then a command
     let mut idx = 0;
 
-    // If the first arg is "1091", skip it
-    if idx < args.len() && args[idx] == "1091" {
+    // Skip a leading class code left by the class's own wrapper scripts, e.g. "1091"
+    let class_codes = config
+        .get_custom_config("autotest_stage_class_codes")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_STAGE_CLASS_CODES);
+    if idx < args.len() && class_codes.split_whitespace().any(|code| code == args[idx]) {
         idx += 1;
     }
 
     let mut compiler = None;
-    if idx < args.len() && compiler_options.contains(&args[idx].as_str()) {
+    if idx < args.len() && COMPILERS.contains(&args[idx].as_str()) {
         compiler = Some(args[idx].clone());
         idx += 1;
     }
@@ -117,44 +245,57 @@ fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), Strin
     // We need at least 2 more arguments: prefix + the subcommand
     if args.len() < idx + 2 {
         error!("Usage: autotest-stage [compiler] stage_prefix command...");
-        return Err("Invalid arguments for autotest-stage".to_string());
+        return Err(QuicktoolError::Usage(
+            "Invalid arguments for autotest-stage".to_string(),
+        ));
     }
 
     let stage_prefix = &args[idx];
     let command_args = &args[idx + 1..];
 
+    notices::show_notices(config, command_args.first().map(String::as_str));
+
     // Disallow .c files in arguments
     if args.iter().any(|arg| arg.contains(".c")) {
         error!("autotest-stage does not accept .c file names in arguments.");
         error!("Please remove .c file references; they must already be in the directory.");
-        return Err("Invalid .c files in arguments".to_string());
+        return Err(QuicktoolError::Usage(
+            "Invalid .c files in arguments".to_string(),
+        ));
     }
 
     // Prepare paths
-    let activities_dir = Path::new(
-        config
-            .get_custom_config("public_html_session_directory")
-            .unwrap_or(&String::new()),
-    )
-    .join("activities");
-
-    let autotest_script = Path::new("/usr/local/share/autotest/autotest.py");
-    let c_check_path = Path::new(
-        config
-            .get_custom_config("public_html_session_directory")
-            .unwrap_or(&String::new()),
-    )
-    .join("public/_infra/extern/c_check/c_check.py");
-
-    // Determine compiler or default to clang
-    let chosen_compiler = compiler.unwrap_or_else(|| "clang".to_string());
-
-    let parameters = format!(
-        "default_compilers = {{'c': [['{compiler}', '-Werror']]}} \
-         default_checkers = {{'c': [['python3', '{}']]}}",
-        c_check_path.display(),
-        compiler = chosen_compiler
-    );
+    let (activities_dir, autotest_script, c_check_path) = autotest_paths(config)?;
+    let capabilities = autotest_backend::probe(&autotest_script);
+
+    // Determine compiler or fall back to the user's default for the class
+    let chosen_compiler = match compiler {
+        Some(compiler) => compiler,
+        None => default_compiler(config)?,
+    };
+    if !options.list {
+        toolchain::enforce(config, &chosen_compiler, options.ignore_toolchain)?;
+    }
+    let launcher = compiler_launcher(config, ccache, &chosen_compiler)?;
+
+    let parameters = default_parameters(launcher.as_deref(), &chosen_compiler, &c_check_path);
+    let parameters = parameter_args(config, &capabilities, &autotest_script, parameters)?;
+
+    // Both calls below rely on flags that not every autotest has
+    for (supported, flag) in [
+        (capabilities.print_test_names, "--print_test_names"),
+        (capabilities.labels, "-l"),
+    ] {
+        if !supported {
+            return Err(format!(
+                "{} does not support {}, which autotest-stage needs; \
+                 run autotest on the whole exercise instead",
+                capabilities.describe(&autotest_script),
+                flag
+            )
+            .into());
+        }
+    }
 
     // First call: gather tests with --print_test_names
     let mut test_command = Command::new("python3");
@@ -167,13 +308,13 @@ fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), Strin
         .arg(&autotest_script)
         .arg("--exercise_directory")
         .arg(&activities_dir)
-        .arg("--parameters")
-        .arg(&parameters)
+        .args(&parameters)
         .args(command_args)
         .arg("--print_test_names")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
+    trace::command(&test_command);
     let output = test_command.output().map_err(|e| {
         error!("Failed to run autotest command: {}", e);
         format!("Failed to execute autotest: {}", e)
@@ -182,40 +323,54 @@ fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), Strin
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         error!("Autotest failed: {}", stderr);
-        return Err(stderr.to_string());
+        return Err(stderr.to_string().into());
     }
 
-    // Parse JSON output to get test labels
-    let json_output = String::from_utf8_lossy(&output.stdout);
-    let json_value: Value = serde_json::from_str(&json_output).map_err(|e| {
-        error!("Failed to parse JSON output: {}", e);
-        error!("Output was: {}", json_output);
-        "JSON parsing error".to_string()
+    // Parse the test labels, whichever shape this autotest prints them in
+    let names_output = String::from_utf8_lossy(&output.stdout);
+    let tests = autotest_backend::parse_test_names(&names_output).ok_or_else(|| {
+        error!("Output was: {}", names_output);
+        format!(
+            "{} printed no test names autotest-stage can read; \
+             it may be a different autotest version than quicktool expects",
+            capabilities.describe(&autotest_script)
+        )
     })?;
 
-    let tests = json_value
-        .get(0)
-        .and_then(|obj| obj.get("labels"))
-        .ok_or_else(|| {
-            error!("Could not find 'labels' in autotest output");
-            "No labels found in autotest output".to_string()
-        })?;
-
     // Filter labels that start with the given stage_prefix
-    let run_labels: Vec<String> = tests
-        .as_array()
-        .unwrap_or(&vec![])
-        .iter()
-        .filter_map(|label_val| label_val.as_str().map(str::to_string))
-        .filter(|label_str| label_str.starts_with(stage_prefix))
+    let stage_tests: Vec<_> = tests
+        .into_iter()
+        .filter(|test| test.label.starts_with(stage_prefix))
         .collect();
+    let run_labels: Vec<String> = stage_tests.iter().map(|test| test.label.clone()).collect();
 
     if run_labels.is_empty() {
         error!(
             "Could not find any autotests that start with {}!",
             stage_prefix
         );
-        return Err(format!("No tests found with prefix '{}'", stage_prefix));
+        return Err(format!("No tests found with prefix '{}'", stage_prefix).into());
+    }
+
+    let stage_json = |extra: Value| {
+        let mut report = json!({
+            "stage": stage_prefix,
+            "tests": stage_tests.iter().map(|test| test.to_json()).collect::<Vec<_>>(),
+        });
+        if let (Some(report), Value::Object(extra)) = (report.as_object_mut(), extra) {
+            report.extend(extra);
+        }
+        println!("{}", report);
+    };
+    if options.list {
+        if config.json {
+            stage_json(json!({}));
+            return Ok(());
+        }
+        for test in &stage_tests {
+            println!("{}", test.describe());
+        }
+        return Ok(());
     }
 
     // Second call: run only these filtered labels
@@ -229,28 +384,265 @@ fn run_autotest_stage(config: &ClassConfig, args: &[String]) -> Result<(), Strin
         .arg(&autotest_script)
         .arg("--exercise_directory")
         .arg(&activities_dir)
-        .arg("--parameters")
-        .arg(&parameters)
+        .args(&parameters)
         .args(command_args)
         .arg("-l")
         .args(run_labels);
 
+    // Listing the tests above only reads, so a dry run stops here
+    if config.dry_run {
+        if config.json {
+            stage_json(json!({
+                "dry_run": true,
+                "command": shell_quote::command_line(&final_command),
+            }));
+        } else {
+            dry_run::command(&final_command);
+        }
+        return Ok(());
+    }
+    if config.json {
+        // Autotest's own output goes to stderr, leaving stdout to the JSON report
+        final_command.stdout(io::stderr());
+        trace::command(&final_command);
+        let status = signals::status(&mut final_command, Work::Heavy)
+            .map_err(|e| format!("Failed to execute process: {}", e))?;
+        let exit_code = exit_codes::of_child(status);
+        stage_json(json!({ "exit_code": exit_code }));
+        exit(exit_code);
+    }
     run_and_propagate_exit_status(final_command)
 }
 
+/// Guess the exercise to test when none is given: the working directory's name if it
+/// names an activity, and activities of the class fetched into it, most recent first.
+/// Several guesses are offered as a choice, or refused if there is no one to ask.
+fn infer_exercise(
+    config: &ClassConfig,
+    activities_dir: &Path,
+) -> Result<Option<String>, String> {
+    let mut candidates = Vec::new();
+    let directory_name = env::current_dir()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()));
+    if let Some(name) = directory_name.filter(|name| activities_dir.join(name).is_dir()) {
+        candidates.push(name);
+    }
+    for record in FetchRecord::list(Path::new(".")).into_iter().rev() {
+        if record.class == config.class && !candidates.contains(&record.activity) {
+            candidates.push(record.activity);
+        }
+    }
+
+    match candidates.len() {
+        0 => Ok(None),
+        1 => {
+            eprintln!(
+                "Testing {} (give an exercise to test another)",
+                candidates[0].bold()
+            );
+            Ok(candidates.pop())
+        }
+        _ if !(io::stdin().is_terminal() && io::stderr().is_terminal()) => Err(format!(
+            "several exercises could be meant here ({}); name the one to test",
+            candidates.join(", ")
+        )),
+        _ => {
+            eprintln!("Which exercise do you want to test?");
+            for (index, candidate) in candidates.iter().enumerate() {
+                eprintln!("  {}) {}", index + 1, candidate);
+            }
+            eprint!("Choose 1-{} [1]: ", candidates.len());
+            let mut answer = String::new();
+            io::stdin()
+                .read_line(&mut answer)
+                .map_err(|e| format!("Failed to read your choice: {}", e))?;
+            let answer = answer.trim();
+            let index = if answer.is_empty() {
+                0
+            } else {
+                answer
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=candidates.len()).contains(n))
+                    .ok_or_else(|| format!("{:?} is not one of the choices", answer))?
+                    - 1
+            };
+            Ok(Some(candidates.swap_remove(index)))
+        }
+    }
+}
+
+/// The activities directory, autotest.py and c_check.py, for both autotest flows
+fn autotest_paths(config: &ClassConfig) -> Result<(PathBuf, PathBuf, PathBuf), QuicktoolError> {
+    let paths = config.paths();
+    let activities_dir = paths.activities_dir().map_err(QuicktoolError::ConfigMissing)?;
+    let autotest_script = paths.tool("autotest")?;
+    let c_check_path = paths.tool("c_check")?;
+    Ok((activities_dir, autotest_script, c_check_path))
+}
+
+/// Whether to run the compiler through ccache or sccache
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Ccache {
+    /// When a cache is found, except with dcc
+    Auto,
+    /// Always, failing if no cache is found
+    On,
+    /// Never
+    Off,
+}
+
+/// Reports autotest can print after running
+#[derive(Clone, Copy, ValueEnum)]
+enum Report {
+    /// A markdown post describing the failing tests, for the course forum
+    Forum,
+}
+
+/// Text shown after the options in `autotest --help` and `autotest-stage --help`
+const PASSTHROUGH_HELP: &str = "Other arguments are passed to the class's autotest; \
+                                use `-- --help` for its own help.";
+
+/// Options quicktool adds to the class's autotest
+#[derive(Parser)]
+#[command(after_help = PASSTHROUGH_HELP)]
+struct AutotestArgs {
+    /// Also print a report of the failures to paste into a forum post
+    #[arg(long, value_enum)]
+    report: Option<Report>,
+    /// Run the compiler through ccache or sccache
+    #[arg(long, value_enum, default_value_t = Ccache::Auto)]
+    ccache: Ccache,
+    /// Compile even if the compiler is not the version the class is marked with
+    #[arg(long)]
+    ignore_toolchain: bool,
+    /// Run the class's own autotest script instead
+    #[arg(long)]
+    legacy: bool,
+    /// Fuzz a program or exercise with mutated test inputs instead of running the tests
+    #[arg(long, value_name = "TARGET")]
+    fuzz: Option<String>,
+    /// How long to fuzz for
+    #[arg(long, value_name = "SECONDS", default_value_t = 60, requires = "fuzz")]
+    time: u64,
+    /// Optional compiler (dcc, gcc or clang), the exercise and autotest's own options
+    args: Vec<String>,
+}
+
+/// Options quicktool adds to the class's autotest-stage
+#[derive(Parser)]
+#[command(after_help = PASSTHROUGH_HELP)]
+struct AutotestStageArgs {
+    /// Run the compiler through ccache or sccache
+    #[arg(long, value_enum, default_value_t = Ccache::Auto)]
+    ccache: Ccache,
+    /// List the stage's tests, with any descriptions and marks, instead of running them
+    #[arg(long)]
+    list: bool,
+    /// Compile even if the compiler is not the version the class is marked with
+    #[arg(long)]
+    ignore_toolchain: bool,
+    /// Run the class's own autotest-stage script instead
+    #[arg(long)]
+    legacy: bool,
+    /// Optional compiler, the stage prefix, then the exercise and autotest's own options
+    args: Vec<String>,
+}
+
+/// The options of autotest and autotest-stage, for documentation
+pub fn commands() -> [(&'static str, clap::Command); 2] {
+    [
+        ("autotest", AutotestArgs::command()),
+        ("autotest-stage", AutotestStageArgs::command()),
+    ]
+}
+
+/// The compiler cache to put in front of `compiler`, if any: ccache, or else sccache,
+/// as found by `Paths::tool`.
+///
+/// In auto mode dcc is left alone, since it runs its own compilers and links in its
+/// runtime; `--ccache on` wraps it anyway and fails if no cache can be found.
+fn compiler_launcher(
+    config: &ClassConfig,
+    mode: Ccache,
+    compiler: &str,
+) -> Result<Option<String>, String> {
+    if mode == Ccache::Off || (mode == Ccache::Auto && compiler == "dcc") {
+        return Ok(None);
+    }
+    let paths = config.paths();
+    let launcher = ["ccache", "sccache"]
+        .iter()
+        .find_map(|name| paths.tool(name).ok())
+        .map(|path| path.to_string_lossy().to_string());
+    if launcher.is_none() && mode == Ccache::On {
+        return Err("--ccache on was given but neither ccache nor sccache was found".to_string());
+    }
+    Ok(launcher)
+}
+
+/// The `--parameters` option for autotest.py, or nothing, with a configuration warning,
+/// when this autotest does not accept it
+fn parameter_args(
+    config: &ClassConfig,
+    capabilities: &Capabilities,
+    autotest_script: &Path,
+    parameters: String,
+) -> Result<Vec<String>, String> {
+    if capabilities.parameters {
+        return Ok(vec!["--parameters".to_string(), parameters]);
+    }
+    config.config_warning(&format!(
+        "{} does not accept --parameters, so it will use its own compiler settings",
+        capabilities.describe(autotest_script)
+    ))?;
+    Ok(Vec::new())
+}
+
+/// The `--parameters` both autotest flows give autotest.py: compile C with `compiler`,
+/// behind the compiler cache if there is one, and style-check it with c_check
+fn default_parameters(launcher: Option<&str>, compiler: &str, c_check_path: &Path) -> String {
+    let mut compile: Vec<String> = launcher.map(str::to_string).into_iter().collect();
+    compile.extend([compiler.to_string(), "-Werror".to_string()]);
+    let check = [
+        "python3".to_string(),
+        c_check_path.to_string_lossy().to_string(),
+    ];
+    AutotestParameters::default()
+        .add_compiler("c", &compile)
+        .add_checker("c", &check)
+        .to_argument()
+}
+
+/// The compiler used when none is given: the user's `compiler` setting for the class,
+/// or clang
+pub fn default_compiler(config: &ClassConfig) -> Result<String, String> {
+    let user_config = UserConfig::load();
+    match user_config.class_setting(&config.class, "compiler") {
+        Some(compiler) if COMPILERS.contains(&compiler) => Ok(compiler.to_string()),
+        Some(compiler) => Err(format!(
+            "{}: the compiler must be dcc, gcc or clang, not '{}'",
+            user_config.path.display(),
+            compiler
+        )),
+        None => Ok("clang".to_string()),
+    }
+}
+
 /// Utility to pick the compiler from arguments (dcc/gcc/clang) if present.
-fn select_compiler(args: &[String]) -> (&str, Vec<String>) {
+fn select_compiler(args: &[String], default: String) -> (String, Vec<String>) {
     if !args.is_empty() {
         let first_arg = args[0].as_str();
-        if ["dcc", "gcc", "clang"].contains(&first_arg) {
+        if COMPILERS.contains(&first_arg) {
             let mut remaining = args.to_vec();
             remaining.remove(0);
-            (first_arg, remaining)
+            (first_arg.to_string(), remaining)
         } else {
-            ("clang", args.to_vec())
+            (default, args.to_vec())
         }
     } else {
-        ("clang", vec![])
+        (default, vec![])
     }
 }
 
@@ -271,16 +663,17 @@ fn extend_path_with_dir(original_path: Option<std::ffi::OsString>, dir: Option<&
     new_path
 }
 
-/// Run the command and propagate its exit status if it fails.
+/// Run the command as heavy work and propagate its exit status if it fails.
 /// Returns `Ok(())` if the command exits successfully, or an `Err` if it fails to start.
-fn run_and_propagate_exit_status(mut command: Command) -> Result<(), String> {
-    match command.status() {
+fn run_and_propagate_exit_status(mut command: Command) -> Result<(), QuicktoolError> {
+    trace::command(&command);
+    match signals::status(&mut command, Work::Heavy) {
         Ok(status) => {
             if !status.success() {
-                exit(status.code().unwrap_or(1));
+                exit(exit_codes::of_child(status));
             }
             Ok(())
         }
-        Err(e) => Err(format!("Failed to execute process: {}", e)),
+        Err(e) => Err(format!("Failed to execute process: {}", e).into()),
     }
 }
\ No newline at end of file
@@ -0,0 +1,246 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::state;
+use crate::trace;
+
+/// File in the state directory remembering what each autotest.py supports
+const CAPABILITIES_FILE: &str = "autotest-capabilities.json";
+
+/// What the installed autotest.py accepts, found by asking it for `--help`.
+///
+/// Terms ship different autotest versions, so flags are checked before relying on them.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    /// First line of `--version`, if it has one
+    pub version: Option<String>,
+    /// `--parameters`, used to choose the compiler
+    pub parameters: bool,
+    /// `--print_test_names`, used by autotest-stage to list tests
+    pub print_test_names: bool,
+    /// `-l`, used by autotest-stage to run chosen tests
+    pub labels: bool,
+}
+
+impl Capabilities {
+    /// What to assume when autotest.py cannot be asked: everything quicktool uses
+    fn assumed() -> Self {
+        Capabilities {
+            version: None,
+            parameters: true,
+            print_test_names: true,
+            labels: true,
+        }
+    }
+
+    /// How to name this autotest in diagnostics
+    pub fn describe(&self, script: &Path) -> String {
+        match &self.version {
+            Some(version) => format!("{} ({})", script.display(), version),
+            None => script.display().to_string(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "parameters": self.parameters,
+            "print_test_names": self.print_test_names,
+            "labels": self.labels,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Capabilities {
+            version: value
+                .get("version")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            parameters: value.get("parameters")?.as_bool()?,
+            print_test_names: value.get("print_test_names")?.as_bool()?,
+            labels: value.get("labels")?.as_bool()?,
+        })
+    }
+}
+
+/// Find out what the autotest.py at `script` supports, reusing the answer from an earlier
+/// run while the script is unchanged
+pub fn probe(script: &Path) -> Capabilities {
+    let key = script.to_string_lossy().to_string();
+    let modified = fs::metadata(script)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let cache_path = state::state_dir().join(CAPABILITIES_FILE);
+    let mut cache: Value = state::read_to_string(&cache_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .filter(Value::is_object)
+        .unwrap_or_else(|| json!({}));
+
+    if let Some(entry) = cache.get(&key) {
+        if modified.is_some() && entry.get("modified").and_then(Value::as_u64) == modified {
+            if let Some(capabilities) = entry.get("capabilities").and_then(Capabilities::from_json)
+            {
+                return capabilities;
+            }
+        }
+    }
+
+    // Without usage text there is nothing to go on, so don't rule any flag out
+    let Some(help) = run_script(script, "--help").filter(|help| help.contains("usage")) else {
+        return Capabilities::assumed();
+    };
+    let flag = |pattern: &str| {
+        Regex::new(pattern)
+            .expect("valid flag pattern")
+            .is_match(&help)
+    };
+    let capabilities = Capabilities {
+        version: run_script(script, "--version")
+            .and_then(|output| output.lines().next().map(|line| line.trim().to_string()))
+            .filter(|line| !line.is_empty()),
+        parameters: flag(r"--parameters\b"),
+        print_test_names: flag(r"--print_test_names\b"),
+        labels: flag(r"(^|[\s,\[])-l\b|--labels?\b"),
+    };
+
+    cache[key] = json!({
+        "modified": modified,
+        "capabilities": capabilities.to_json(),
+    });
+    let _ = state::write(&cache_path, cache.to_string());
+    capabilities
+}
+
+/// One test autotest can run, with whatever autotest says about it
+#[derive(Debug, Clone)]
+pub struct TestInfo {
+    pub label: String,
+    pub description: Option<String>,
+    pub marks: Option<f64>,
+    pub category: Option<String>,
+}
+
+impl TestInfo {
+    fn new(label: &str) -> Self {
+        TestInfo {
+            label: label.to_string(),
+            description: None,
+            marks: None,
+            category: None,
+        }
+    }
+
+    /// One line about the test, e.g. `stage1_02 — insert at head (1 mark)`
+    pub fn describe(&self) -> String {
+        let mut line = self.label.clone();
+        if let Some(description) = &self.description {
+            line.push_str(&format!(" — {}", description));
+        }
+        let mut details = Vec::new();
+        if let Some(marks) = self.marks {
+            let unit = if marks == 1.0 { "mark" } else { "marks" };
+            details.push(format!("{} {}", marks, unit));
+        }
+        details.extend(self.category.clone());
+        if !details.is_empty() {
+            line.push_str(&format!(" ({})", details.join(", ")));
+        }
+        line
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "label": self.label,
+            "description": self.description,
+            "marks": self.marks,
+            "category": self.category,
+        })
+    }
+}
+
+/// Tests listed in the output of `--print_test_names`.
+///
+/// Autotest versions print `[{"labels": [...]}]`, a bare object or list, or objects
+/// per test with a `label` or `name` and perhaps a `description`, `marks` and
+/// `category`. Anything that is not JSON is read as a label at the start of each line,
+/// optionally followed by `:` or `-` and a description. None if no tests are found.
+pub fn parse_test_names(output: &str) -> Option<Vec<TestInfo>> {
+    let tests = match serde_json::from_str::<Value>(output) {
+        Ok(value) => tests_from_json(&value),
+        Err(_) => output.lines().filter_map(test_from_line).collect(),
+    };
+    let mut unique: Vec<TestInfo> = Vec::new();
+    for test in tests {
+        if !unique.iter().any(|seen| seen.label == test.label) {
+            unique.push(test);
+        }
+    }
+    (!unique.is_empty()).then_some(unique)
+}
+
+/// Tests anywhere in a JSON listing
+fn tests_from_json(value: &Value) -> Vec<TestInfo> {
+    match value {
+        Value::String(label) => vec![TestInfo::new(label)],
+        Value::Array(items) => items.iter().flat_map(tests_from_json).collect(),
+        Value::Object(object) => {
+            if let Some(list) = ["labels", "tests", "test_names"]
+                .iter()
+                .find_map(|key| object.get(*key))
+            {
+                return tests_from_json(list);
+            }
+            let text = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| object.get(*key)?.as_str())
+                    .map(str::to_string)
+            };
+            let Some(label) = text(&["label", "name"]) else {
+                return Vec::new();
+            };
+            vec![TestInfo {
+                description: text(&["description", "summary"]),
+                marks: ["marks", "mark", "points"]
+                    .iter()
+                    .find_map(|key| object.get(*key)?.as_f64()),
+                category: text(&["category"]),
+                ..TestInfo::new(&label)
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A test from a line of plain-text output, e.g. `stage1_02: insert at head`
+fn test_from_line(line: &str) -> Option<TestInfo> {
+    let line = line.trim();
+    let (label, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let label = label.trim_end_matches([':', ',']);
+    if label.is_empty() {
+        return None;
+    }
+    let description = rest.trim().trim_start_matches(['-', ':']).trim();
+    Some(TestInfo {
+        description: (!description.is_empty()).then(|| description.to_string()),
+        ..TestInfo::new(label)
+    })
+}
+
+/// Run autotest.py with one flag, returning its output if it succeeded
+fn run_script(script: &Path, flag: &str) -> Option<String> {
+    let mut command = Command::new("python3");
+    command.arg("-I").arg(script).arg(flag);
+    trace::command(&command);
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
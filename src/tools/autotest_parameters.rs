@@ -0,0 +1,118 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Parameters given to autotest.py with `--parameters`: Python assignments such as
+/// `default_compilers = {"c": [["clang", "-Werror"]]}`
+#[derive(Debug, Default)]
+pub struct AutotestParameters {
+    values: BTreeMap<String, Value>,
+}
+
+impl AutotestParameters {
+    /// Add a command that compiles files of `language`, e.g. `c`
+    pub fn add_compiler(&mut self, language: &str, command: &[String]) -> &mut Self {
+        self.add_command("default_compilers", language, command)
+    }
+
+    /// Add a command that style-checks files of `language`
+    pub fn add_checker(&mut self, language: &str, command: &[String]) -> &mut Self {
+        self.add_command("default_checkers", language, command)
+    }
+
+    fn add_command(&mut self, name: &str, language: &str, command: &[String]) -> &mut Self {
+        let commands = self
+            .values
+            .entry(name.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(languages) = commands {
+            let list = languages
+                .entry(language)
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(list) = list {
+                list.push(Value::from(command.to_vec()));
+            }
+        }
+        self
+    }
+
+    /// The `--parameters` argument, one assignment per parameter
+    pub fn to_argument(&self) -> String {
+        self.values
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, python_literal(value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Write a value as a Python literal. Strings use JSON escaping, which Python reads the
+/// same way, so quotes and backslashes in paths survive.
+fn python_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::String(string) => Value::from(string.as_str()).to_string(),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(python_literal).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", Value::from(key.as_str()), python_literal(value))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn no_parameters() {
+        assert_eq!(AutotestParameters::default().to_argument(), "");
+    }
+
+    #[test]
+    fn commands_are_grouped_by_language() {
+        let mut parameters = AutotestParameters::default();
+        parameters
+            .add_compiler("c", &command(&["clang", "-Werror"]))
+            .add_checker("c", &command(&["python3", "c_check.py"]))
+            .add_compiler("c", &command(&["gcc"]))
+            .add_compiler("cc", &command(&["g++"]));
+        assert_eq!(
+            parameters.to_argument(),
+            r#"default_checkers = {"c": [["python3", "c_check.py"]]} default_compilers = {"c": [["clang", "-Werror"], ["gcc"]], "cc": [["g++"]]}"#
+        );
+    }
+
+    #[test]
+    fn strings_survive_as_python_literals() {
+        let mut parameters = AutotestParameters::default();
+        parameters.add_compiler("c", &command(&[r#"C:\dcc "quoted""#, "line\nbreak"]));
+        assert_eq!(
+            parameters.to_argument(),
+            r#"default_compilers = {"c": [["C:\\dcc \"quoted\"", "line\nbreak"]]}"#
+        );
+    }
+
+    #[test]
+    fn json_values_become_python_literals() {
+        let value = serde_json::json!([null, true, false, 1.5, {"k": []}]);
+        assert_eq!(
+            python_literal(&value),
+            r#"[None, True, False, 1.5, {"k": []}]"#
+        );
+    }
+}
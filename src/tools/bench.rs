@@ -0,0 +1,236 @@
+use colored::Colorize;
+use std::fs::File;
+use std::os::raw::{c_int, c_long};
+use std::path::Path;
+use std::process::{exit, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::manifest::ActivityManifest;
+use crate::metadata::FetchRecord;
+use crate::tools::fetch_activity;
+use crate::trace;
+
+/// `struct timeval` as used inside `struct rusage`
+#[repr(C)]
+#[derive(Default)]
+struct Timeval {
+    tv_sec: c_long,
+    tv_usec: c_long,
+}
+
+/// `struct rusage`; only the fields up to ru_maxrss are read
+#[repr(C)]
+#[derive(Default)]
+struct Rusage {
+    ru_utime: Timeval,
+    ru_stime: Timeval,
+    ru_maxrss: c_long,
+    ru_rest: [c_long; 13],
+}
+
+extern "C" {
+    fn wait4(pid: c_int, status: *mut c_int, options: c_int, rusage: *mut Rusage) -> c_int;
+}
+
+/// Resources used by one run of the program
+struct Measurement {
+    wall: Duration,
+    user: Duration,
+    sys: Duration,
+    max_rss_kb: u64,
+}
+
+/// Run the bench tool: time repeated executions of a program, e.g.
+/// `bench ./prog --input big.txt --repeat 5 [--activity name]`
+pub fn run_bench(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let usage = || {
+        QuicktoolError::Usage(format!(
+            "usage: {} bench program [args...] [--input file] [--repeat n] [--activity name]",
+            config.class
+        ))
+    };
+
+    let mut input = None;
+    let mut repeat = 5;
+    let mut activity = None;
+    let mut program_args: Vec<&String> = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--input" => input = Some(iter.next().ok_or_else(usage)?),
+            "--repeat" => {
+                repeat = iter
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n: &usize| n > 0)
+                    .ok_or_else(usage)?
+            }
+            "--activity" => activity = Some(iter.next().ok_or_else(usage)?.clone()),
+            "--" => program_args.extend(iter.by_ref()),
+            _ => program_args.push(arg),
+        }
+    }
+    let (program, program_args) = program_args.split_first().ok_or_else(usage)?;
+
+    let mut measurements = Vec::with_capacity(repeat);
+    for run in 1..=repeat {
+        let measurement = measure(program, program_args, input.map(String::as_str))?;
+        println!(
+            "run {:>2}: {:>8.3}s wall {:>8.3}s user {:>8.3}s sys {:>8} KB max RSS",
+            run,
+            measurement.wall.as_secs_f64(),
+            measurement.user.as_secs_f64(),
+            measurement.sys.as_secs_f64(),
+            measurement.max_rss_kb
+        );
+        measurements.push(measurement);
+    }
+
+    let walls: Vec<f64> = measurements.iter().map(|m| m.wall.as_secs_f64()).collect();
+    let mean = walls.iter().sum::<f64>() / walls.len() as f64;
+    let variance = walls.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / walls.len() as f64;
+    let slowest = walls.iter().cloned().fold(0.0, f64::max);
+    let fastest = walls.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_rss = measurements.iter().map(|m| m.max_rss_kb).max().unwrap_or(0);
+
+    println!();
+    println!(
+        "wall time: mean {:.3}s, min {:.3}s, max {:.3}s, stddev {:.3}s",
+        mean,
+        fastest,
+        slowest,
+        variance.sqrt()
+    );
+    println!(
+        "cpu time:  mean {:.3}s user, {:.3}s sys",
+        mean_secs(measurements.iter().map(|m| m.user)),
+        mean_secs(measurements.iter().map(|m| m.sys))
+    );
+    println!("memory:    {} KB max RSS", max_rss);
+
+    // Compare against the thresholds course staff publish for the activity
+    let activity = activity.or_else(|| {
+        FetchRecord::list(Path::new("."))
+            .pop()
+            .map(|record| record.activity)
+    });
+    let Some(activity) = activity else {
+        return Ok(());
+    };
+    let activity_dir = fetch_activity::locate_activity(config, &activity)?;
+    let Some(manifest) = ActivityManifest::load(&activity_dir) else {
+        return Ok(());
+    };
+
+    let mut passed = true;
+    if let Some(limit) = manifest.get_f64("bench_max_seconds") {
+        passed &= report_threshold("time", slowest, limit, "s", 3);
+    }
+    if let Some(limit) = manifest.get_f64("bench_max_rss_kb") {
+        passed &= report_threshold("memory", max_rss as f64, limit, " KB", 0);
+    }
+    if !passed {
+        exit(exit_codes::FAILURE);
+    }
+    Ok(())
+}
+
+/// Run the program once, measuring its resource usage with wait4
+fn measure(
+    program: &str,
+    args: &[&String],
+    input: Option<&str>,
+) -> Result<Measurement, QuicktoolError> {
+    let mut command = Command::new(program);
+    command.args(args).stdout(Stdio::null());
+    if let Some(path) = input {
+        let file = File::open(path).map_err(|e| format!("Cannot open input {}: {}", path, e))?;
+        command.stdin(Stdio::from(file));
+    }
+    trace::command(&command);
+
+    let start = Instant::now();
+    let child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let mut status: c_int = 0;
+    let mut usage = Rusage::default();
+    // SAFETY: the pid is our own unwaited child and both pointers are valid for writes
+    let pid = unsafe { wait4(child.id() as c_int, &mut status, 0, &mut usage) };
+    let wall = start.elapsed();
+    if pid < 0 {
+        return Err(format!(
+            "Failed to wait for {}: {}",
+            program,
+            std::io::Error::last_os_error()
+        )
+        .into());
+    }
+
+    // Report failures, since timing a crashing program is meaningless
+    let signal = status & 0x7f;
+    let code = (status >> 8) & 0xff;
+    if signal != 0 {
+        return Err(QuicktoolError::ChildFailed {
+            message: format!("{} was killed by signal {}", program, signal),
+            code: 128 + signal,
+        });
+    }
+    if code != 0 {
+        return Err(QuicktoolError::ChildFailed {
+            message: format!("{} exited with status {}", program, code),
+            code,
+        });
+    }
+
+    Ok(Measurement {
+        wall,
+        user: timeval_duration(&usage.ru_utime),
+        sys: timeval_duration(&usage.ru_stime),
+        max_rss_kb: usage.ru_maxrss.max(0) as u64,
+    })
+}
+
+/// Convert a timeval to a Duration
+fn timeval_duration(tv: &Timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec.max(0) as u64) + Duration::from_micros(tv.tv_usec.max(0) as u64)
+}
+
+/// Mean of a set of durations, in seconds
+fn mean_secs(durations: impl Iterator<Item = Duration>) -> f64 {
+    let secs: Vec<f64> = durations.map(|d| d.as_secs_f64()).collect();
+    secs.iter().sum::<f64>() / secs.len().max(1) as f64
+}
+
+/// Print whether a measurement is within a published threshold
+fn report_threshold(what: &str, value: f64, limit: f64, unit: &str, decimals: usize) -> bool {
+    if value <= limit {
+        println!(
+            "{} {} {:.*}{} is within the limit of {}{}",
+            "PASS".green().bold(),
+            what,
+            decimals,
+            value,
+            unit,
+            limit,
+            unit
+        );
+        true
+    } else {
+        println!(
+            "{} {} {:.*}{} exceeds the limit of {}{}",
+            "FAIL".red().bold(),
+            what,
+            decimals,
+            value,
+            unit,
+            limit,
+            unit
+        );
+        false
+    }
+}
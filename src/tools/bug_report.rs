@@ -0,0 +1,171 @@
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::features;
+use crate::shell_quote;
+use crate::signals::{self, Work};
+use crate::temp::TempDir;
+use crate::tools::fingerprint::Fingerprint;
+use crate::trace;
+
+/// Run a quicktool command while capturing what it does into a redacted tarball that
+/// students can attach to an issue: `quicktool --bug-report <class> [command...]`.
+/// Returns the exit code of the captured command.
+pub fn run_bug_report(args: &[String]) -> Result<i32, QuicktoolError> {
+    if args.is_empty() {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool --bug-report classname [command...]".to_string(),
+        ));
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let staging = TempDir::new("bug-report")?;
+
+    let code = capture(args, staging.path())?;
+    let archive = PathBuf::from(format!("quicktool-bug-report-{}.tar.gz", timestamp));
+    write_archive(staging.path(), &archive)?;
+    eprintln!(
+        "quicktool: bug report written to {} (zIDs and home paths have been removed)",
+        archive.display()
+    );
+    Ok(code)
+}
+
+/// Run the command with tracing enabled, writing everything it did into `staging`
+fn capture(args: &[String], staging: &Path) -> Result<i32, String> {
+    let exe = env::current_exe().map_err(|e| format!("Cannot find quicktool itself: {}", e))?;
+
+    // Resolved configuration of the class, as the command will see it
+    let config = match ClassConfig::new(&args[0]) {
+        Some(mut config) => {
+            let _ = config.load_class_config();
+            write_file(
+                &staging.join("fingerprint.txt"),
+                &Fingerprint::collect(&config).to_text(),
+            )?;
+            format!("{:#?}\n", config)
+        }
+        None => format!("{} is not a valid class\n", args[0]),
+    };
+    write_file(&staging.join("config.txt"), &config)?;
+
+    let trace_file = staging.join("trace.txt");
+    // Both streams as the user saw them, recorded by script(1) so the command still has
+    // the terminal to prompt on, colour for and read from
+    let output_file = staging.join("output.txt");
+    let mut command = Command::new(&exe);
+    command.args(args).env(trace::TRACE_ENV, &trace_file);
+    let mut recorder = Command::new("script");
+    recorder
+        .args(["--quiet", "--return", "--command"])
+        .arg(shell_quote::command_line(&command))
+        .arg(&output_file)
+        .env(trace::TRACE_ENV, &trace_file);
+    let status = match signals::status(&mut recorder, Work::Interactive) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!(
+                "quicktool: script is not installed, so the report will not include the output"
+            );
+            signals::status(&mut command, Work::Interactive)
+        }
+        status => status,
+    }
+    .map_err(|e| format!("Failed to run quicktool: {}", e))?;
+
+    let summary = format!(
+        "command: quicktool {}\nresult: {}\nquicktool version: {}\nworking directory: {}\n",
+        args.join(" "),
+        status,
+        features::long_version(),
+        env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default()
+    );
+    write_file(&staging.join("summary.txt"), &summary)?;
+
+    // Files written while the command ran still need scrubbing; the trace file only
+    // exists if the command ran anything
+    for file in [&trace_file, &output_file] {
+        if let Ok(contents) = fs::read_to_string(file) {
+            write_file(file, &contents)?;
+        }
+    }
+
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Write a text file into the report, scrubbing personal details
+pub fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, scrub(contents))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Pack the staging directory into a gzipped tarball
+pub fn write_archive(staging: &Path, archive: &Path) -> Result<(), String> {
+    let status = Command::new("tar")
+        .arg("czf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .arg(".")
+        .status()
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+    if !status.success() {
+        return Err(format!("tar failed to create {}", archive.display()));
+    }
+    Ok(())
+}
+
+/// Remove zIDs, home directory paths, the user name and obvious secrets from text
+pub fn scrub(text: &str) -> String {
+    let mut scrubbed = text.to_string();
+
+    if let Ok(home) = env::var("HOME") {
+        if home.len() > 1 {
+            scrubbed = scrubbed.replace(&home, "~");
+        }
+    }
+
+    let zid = Regex::new(r"\bz\d{7}\b").expect("valid zID pattern");
+    scrubbed = zid.replace_all(&scrubbed, "zXXXXXXX").to_string();
+
+    // Personal home directories, e.g. /home/jsmith or /import/kamen/1/jsmith, but not
+    // class accounts such as /home/cs1511 which are needed to debug the class setup
+    let home_path =
+        Regex::new(r"/(home|import/[^/\s]+/\d+)/([A-Za-z0-9._-]+)").expect("valid home pattern");
+    let class_account = Regex::new(r"^[a-z]{2}\d{4}$").expect("valid account pattern");
+    scrubbed = home_path
+        .replace_all(&scrubbed, |caps: &regex::Captures| {
+            if class_account.is_match(&caps[2]) {
+                caps[0].to_string()
+            } else {
+                format!("/{}/USER", &caps[1])
+            }
+        })
+        .to_string();
+
+    // Values assigned to things that look like credentials
+    let secret = Regex::new(
+        r#"(?i)\b(password|passwd|secret|token|api_?key)(\s*[=:]\s*)("[^"]*"|'[^']*'|\S+)"#,
+    )
+    .expect("valid secret pattern");
+    scrubbed = secret.replace_all(&scrubbed, "${1}${2}REDACTED").to_string();
+
+    if let Ok(user) = env::var("USER") {
+        if user.len() > 2 {
+            scrubbed = scrubbed.replace(&user, "USER");
+        }
+    }
+
+    scrubbed
+}
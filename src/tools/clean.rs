@@ -0,0 +1,153 @@
+use clap::{CommandFactory, Parser};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::artifacts::{self, Artifact};
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{FetchRecord, METADATA_DIR};
+use crate::notices::pattern_matches;
+use crate::tools::du::format_size;
+use crate::tools::fetch_activity;
+
+/// Options of the clean tool
+#[derive(Parser)]
+struct CleanArgs {
+    /// List what would be removed without removing it
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Activity whose manifest says what to keep and remove, instead of the one fetched
+    /// here
+    #[arg(long, value_name = "NAME")]
+    activity: Option<String>,
+}
+
+/// The options of clean, for documentation
+pub fn command() -> clap::Command {
+    CleanArgs::command()
+}
+
+/// Run the clean tool: remove build artifacts before submitting, e.g.
+/// `clean [--dry-run] [--activity name]`.
+///
+/// The activity's manifest.sh may list file name patterns (using `*`) in `clean_keep`,
+/// which are never removed, and in `clean_remove`, which are removed as well.
+pub fn run_clean(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let CleanArgs { dry_run, activity } = cli::parse_tool_args(&config.class, "clean", args)?;
+    let dry_run = dry_run || config.dry_run;
+
+    let activity = activity.or_else(|| {
+        FetchRecord::list(Path::new("."))
+            .pop()
+            .map(|record| record.activity)
+    });
+    let manifest = match activity {
+        Some(activity) => {
+            ActivityManifest::load(&fetch_activity::locate_activity(config, &activity)?)
+        }
+        None => None,
+    };
+    let patterns = |key: &str| -> Vec<String> {
+        manifest
+            .as_ref()
+            .and_then(|manifest| manifest.get(key))
+            .map(|value| value.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let keep = patterns("clean_keep");
+    let remove = patterns("clean_remove");
+
+    let mut found = Vec::new();
+    find_junk(Path::new("."), &keep, &remove, &mut found);
+    if found.is_empty() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for (path, description) in &found {
+        let size = disk_usage(path);
+        if dry_run {
+            dry_run::report(&format!(
+                "would remove {} ({}, {})",
+                path.display(),
+                description,
+                format_size(size)
+            ));
+            continue;
+        }
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        match removed {
+            Ok(()) => {
+                println!(
+                    "removed {} ({}, {})",
+                    path.display(),
+                    description,
+                    format_size(size)
+                );
+                freed += size;
+            }
+            Err(e) => eprintln!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    if !dry_run {
+        println!("Freed {}.", format_size(freed));
+    }
+    Ok(())
+}
+
+/// Find the files and directories below `dir` that should not be submitted
+fn find_junk(dir: &Path, keep: &[String], remove: &[String], found: &mut Vec<(PathBuf, String)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name == METADATA_DIR || keep.iter().any(|pattern| pattern_matches(pattern, &name)) {
+            continue;
+        }
+        if remove.iter().any(|pattern| pattern_matches(pattern, &name)) {
+            found.push((path, "listed in the activity manifest".to_string()));
+            continue;
+        }
+        match artifacts::classify(&path) {
+            Some(Artifact::ValgrindLog) | None => {
+                if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+                    find_junk(&path, keep, remove, found);
+                }
+            }
+            Some(artifact) => found.push((path, artifact.description().to_string())),
+        }
+    }
+}
+
+/// Disk space used by a file or directory tree, without following symlinks
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    let mut size = metadata.blocks() * 512;
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            size += entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum::<u64>();
+        }
+    }
+    size
+}
@@ -0,0 +1,217 @@
+use colored::*;
+use std::path::PathBuf;
+
+use crate::cli::{BUILT_IN_COMMANDS, STAFF_COMMANDS};
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::plugins;
+use crate::tools::{autotest, clean, compare, du, fetch_activity, run, test_all};
+use crate::user_config::UserConfig;
+
+/// Synopsis of the built-in commands that parse their own options without clap
+const USAGE: &[(&str, &str)] = &[
+    ("edit", "edit [--fetch] activity"),
+    ("new", "new file... [--force]"),
+    (
+        "bench",
+        "bench program [args...] [--input file] [--repeat n] [--activity name]",
+    ),
+    ("dismiss-notice", "dismiss-notice id"),
+    ("stats", "stats [opt-in|opt-out]"),
+    ("help-request", "help-request activity"),
+    ("progress", "progress [--format text|json|csv]"),
+    ("fingerprint", "fingerprint [--json]"),
+    ("config", "config show [--json]"),
+    (
+        "student",
+        "student [zid|name] [--tut tutorial[,tutorial...]|mine]",
+    ),
+    ("which", "which tool"),
+    ("setenv", "setenv [KEY=VALUE...] [--for DURATION]"),
+    ("unsetenv", "unsetenv KEY...|--all"),
+    ("deploy-docs", "deploy-docs [--dir DIR]"),
+];
+
+/// Example invocations of each built-in command, without the class code
+const EXAMPLES: &[(&str, &[&str])] = &[
+    (
+        "autotest",
+        &[
+            "autotest",
+            "autotest lab01",
+            "autotest gcc lab01",
+            "autotest --fuzz lab01 --time 30",
+        ],
+    ),
+    (
+        "autotest-stage",
+        &["autotest-stage 01 ass1", "autotest-stage --list 01 ass1"],
+    ),
+    (
+        "fetch-activity",
+        &["fetch-activity lab01", "fetch-activity --update lab01"],
+    ),
+    ("edit", &["edit lab01", "edit --fetch lab02"]),
+    ("new", &["new hello.c", "new list.h list.c"]),
+    (
+        "run",
+        &[
+            "run ./hello --stdin input1.txt --expect output1.txt",
+            "run ./hello --transcript",
+        ],
+    ),
+    ("bench", &["bench ./hello --input input1.txt --repeat 20"]),
+    ("du", &["du", "du --clean"]),
+    ("clean", &["clean --dry-run", "clean"]),
+    ("dismiss-notice", &["dismiss-notice all"]),
+    ("stats", &["stats", "stats opt-in"]),
+    ("help-request", &["help-request lab01"]),
+    ("progress", &["progress", "progress --format csv"]),
+    ("test-all", &["test-all", "test-all --jobs 2 3"]),
+    (
+        "compare",
+        &["compare ./hello", "compare ./hello --count 500 --seed 7"],
+    ),
+    ("fingerprint", &["fingerprint", "fingerprint --json"]),
+    ("config", &["config show"]),
+    ("student", &["student z5555555", "student --tut mine"]),
+    ("which", &["which gcc", "which dcc"]),
+    (
+        "setenv",
+        &[
+            "setenv DCC_OUTPUT=json",
+            "setenv DCC_OUTPUT=json --for 2h",
+            "setenv",
+        ],
+    ),
+    ("unsetenv", &["unsetenv DCC_OUTPUT", "unsetenv --all"]),
+    (
+        "deploy-docs",
+        &["deploy-docs", "deploy-docs --dir /tmp/man"],
+    ),
+];
+
+/// Commands that work with the class's activities
+const USES_ACTIVITIES: &[&str] = &[
+    "autotest",
+    "autotest-stage",
+    "fetch-activity",
+    "edit",
+    "help-request",
+    "bench",
+    "clean",
+    "compare",
+    "test-all",
+];
+
+/// Print detailed help for one command, `help <command>`: its usage and options, some
+/// examples and the class's paths and settings it uses
+pub fn print_command_help(config: &mut ClassConfig, command: &str) -> Result<(), QuicktoolError> {
+    let Some((_, description)) = BUILT_IN_COMMANDS.iter().find(|(name, _)| *name == command) else {
+        if plugins::find(config, command).is_some() {
+            println!(
+                "{} is provided by quicktool-{}; try {} {} --help",
+                command, command, config.class, command
+            );
+            return Ok(());
+        }
+        if let Some(program) = plugins::find_class_command(config, command) {
+            println!(
+                "{} is provided by the class as {}; try {} {} --help",
+                command,
+                program.display(),
+                config.class,
+                command
+            );
+            return Ok(());
+        }
+        return Err(QuicktoolError::Usage(format!(
+            "there is no command called {}; {} help lists them",
+            command, config.class
+        )));
+    };
+    if !config.tool_enabled(command) {
+        return Err(config.disabled_tool_message(command).into());
+    }
+    if STAFF_COMMANDS.contains(&command) && !config.is_staff() {
+        return Err(config.not_staff_message(command).into());
+    }
+
+    let mut parsers = autotest::commands().to_vec();
+    parsers.extend([
+        ("fetch-activity", fetch_activity::command()),
+        ("run", run::command()),
+        ("compare", compare::command()),
+        ("test-all", test_all::command()),
+        ("du", du::command()),
+        ("clean", clean::command()),
+    ]);
+    match parsers.into_iter().find(|(name, _)| *name == command) {
+        Some((_, parser)) => {
+            let mut parser = parser
+                .about(description.lines().collect::<Vec<_>>().join(" "))
+                .bin_name(format!("{} {}", config.class, command));
+            print!("{}", parser.render_long_help());
+        }
+        None => {
+            let usage = USAGE
+                .iter()
+                .find(|(name, _)| *name == command)
+                .map_or(command, |(_, usage)| usage);
+            println!("{} {} {}", "Usage:".bold(), config.class, usage);
+            println!();
+            for line in description.lines() {
+                println!("{}", line);
+            }
+        }
+    }
+
+    if let Some((_, examples)) = EXAMPLES.iter().find(|(name, _)| *name == command) {
+        println!();
+        println!("{}", "Examples:".bold());
+        for example in *examples {
+            println!("  {} {}", config.class, example);
+        }
+    }
+
+    let _ = config.load_class_config();
+    let mut settings: Vec<(&str, String)> = Vec::new();
+    let paths = config.paths();
+    if USES_ACTIVITIES.contains(&command) {
+        settings.push((
+            "activities",
+            match paths.activities_dir() {
+                Ok(dir) => dir.display().to_string(),
+                Err(e) => e.dimmed().to_string(),
+            },
+        ));
+    }
+    if command == "autotest" || command == "autotest-stage" {
+        let found = |path: Result<PathBuf, _>| match path {
+            Ok(path) => path.display().to_string(),
+            Err(_) => "(not found)".dimmed().to_string(),
+        };
+        settings.push(("autotest", found(paths.tool("autotest"))));
+        let compiler = autotest::default_compiler(config)?;
+        settings.push((
+            "compiler",
+            format!(
+                "{} by default (set compiler for {} in {} to change it)",
+                compiler,
+                config.class,
+                UserConfig::load().path.display()
+            ),
+        ));
+        if let Some(pin) = config.get_custom_config(&format!("compiler_version_{}", compiler)) {
+            settings.push(("compiler version", format!("{} {}", compiler, pin)));
+        }
+    }
+    if !settings.is_empty() {
+        println!();
+        println!("{}", format!("{} paths and settings:", config.class).bold());
+        for (name, value) in settings {
+            println!("  {:<18} {}", name, value);
+        }
+    }
+    Ok(())
+}
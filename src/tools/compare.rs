@@ -0,0 +1,352 @@
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use std::fs;
+use std::io::{Read, Write};
+use std::num::NonZeroU64;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::diff::{self, DiffStyle, Match, Tolerance};
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{FetchRecord, METADATA_DIR};
+use crate::shell_quote;
+use crate::tools::fetch_activity;
+use crate::tools::run::PROGRAM_ARGS_HELP;
+use crate::trace;
+
+/// Generated inputs tried unless the manifest sets `generated_tests` or `--count` is given
+const DEFAULT_COUNT: u64 = 100;
+
+/// How long one run of the generator, the reference or the program may take
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a program did with one input
+#[derive(Debug)]
+pub struct Outcome {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code, or a description such as `killed by signal 11` or `timed out`
+    pub status: String,
+}
+
+impl Outcome {
+    /// How closely this run's output matches an expected run's; runs that exited
+    /// differently never match
+    fn compare(&self, expected: &Outcome, tolerance: Tolerance) -> Match {
+        if self.status != expected.status {
+            return Match::Different;
+        }
+        diff::compare_output(&expected.stdout, &self.stdout, tolerance)
+    }
+}
+
+/// Options of the compare tool
+#[derive(Parser)]
+#[command(after_help = PROGRAM_ARGS_HELP)]
+struct CompareArgs {
+    /// Activity whose generator and reference solution to use, instead of the one
+    /// fetched here
+    #[arg(long, value_name = "NAME")]
+    activity: Option<String>,
+    /// How many generated inputs to try, instead of the manifest's generated_tests
+    #[arg(long, value_name = "N")]
+    count: Option<NonZeroU64>,
+    /// Seed for the generator, to try other inputs
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    seed: u64,
+    /// Accept output that differs only in line endings or trailing whitespace
+    #[arg(long)]
+    normalize_eol: bool,
+    /// Show whitespace and line endings in the diff
+    #[arg(long)]
+    show_invisible: bool,
+    /// Show differing non-ASCII characters as codepoints
+    #[arg(long)]
+    show_codepoints: bool,
+    /// The program and its arguments
+    #[arg(required = true)]
+    command: Vec<String>,
+}
+
+/// The options of compare, for documentation
+pub fn command() -> clap::Command {
+    CompareArgs::command()
+}
+
+/// Run the compare tool: run a program and the activity's reference solution on inputs
+/// from the activity's generator and report the smallest input they disagree on, e.g.
+/// `compare ./prog [--activity name] [--count n] [--seed n]`. `--normalize-eol` accepts
+/// output that differs only in whitespace and `--show-invisible` shows it in the diff,
+/// while `--show-codepoints` shows differing non-ASCII characters as codepoints.
+/// Numbers may differ by the tolerance set in the activity's manifest.
+pub fn run_compare(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let CompareArgs {
+        activity,
+        count,
+        seed,
+        normalize_eol,
+        show_invisible,
+        show_codepoints,
+        command: program_args,
+    } = cli::parse_passthrough_args(&config.class, "compare", args)?;
+    let count = count.map(NonZeroU64::get);
+
+    let activity = activity
+        .or_else(|| {
+            FetchRecord::list(Path::new("."))
+                .pop()
+                .map(|record| record.activity)
+        })
+        .ok_or_else(|| "no activity was fetched here; use --activity".to_string())?;
+    let activity_dir = fetch_activity::locate_activity(config, &activity)?;
+    let manifest = ActivityManifest::load(&activity_dir).unwrap_or_default();
+    let generator = manifest.get("generator");
+    let reference = manifest.get("reference");
+    let (Some(generator), Some(reference)) = (generator, reference) else {
+        return Err(format!(
+            "{} has no generated tests (its manifest sets no generator and reference)",
+            activity
+        )
+        .into());
+    };
+    let count = count
+        .or_else(|| manifest.get("generated_tests")?.parse().ok())
+        .unwrap_or(DEFAULT_COUNT);
+
+    let program = shell_quote::join(&program_args);
+    let tolerance = Tolerance::from_manifest(&manifest, None);
+    let passes = |outcome: Match| match outcome {
+        Match::Exact | Match::WithinTolerance => true,
+        Match::IgnoringWhitespace => normalize_eol,
+        Match::Different => false,
+    };
+    let mut whitespace_passes = 0;
+    let mut tolerance_passes = Vec::new();
+    for seed in seed..seed + count {
+        let input = generate(generator, &activity_dir, seed)?;
+        let expected = run(reference, &activity_dir, input.as_bytes(), RUN_TIMEOUT);
+        let actual = run(&program, Path::new("."), input.as_bytes(), RUN_TIMEOUT);
+        let outcome = actual.compare(&expected, tolerance);
+        if passes(outcome) {
+            match outcome {
+                Match::IgnoringWhitespace => whitespace_passes += 1,
+                Match::WithinTolerance => tolerance_passes.push(seed.to_string()),
+                _ => {}
+            }
+            continue;
+        }
+
+        println!(
+            "{} seed {} gives different output from the reference; shrinking the input...",
+            "Mismatch:".red().bold(),
+            seed
+        );
+        let input = shrink(&input, |input| {
+            let actual = run(&program, Path::new("."), input.as_bytes(), RUN_TIMEOUT);
+            let expected = run(reference, &activity_dir, input.as_bytes(), RUN_TIMEOUT);
+            !passes(actual.compare(&expected, tolerance))
+        });
+        let expected = run(reference, &activity_dir, input.as_bytes(), RUN_TIMEOUT);
+        let actual = run(&program, Path::new("."), input.as_bytes(), RUN_TIMEOUT);
+        let saved = save_counterexample(&activity, seed, &input, &expected, &actual)?;
+
+        println!("Smallest input found ({} lines):", input.lines().count());
+        print!("{}", input);
+        if expected.status != actual.status {
+            println!(
+                "Exit status: expected {}, got {}",
+                expected.status, actual.status
+            );
+        }
+        // Whitespace-only differences look identical unless they are shown
+        let show_invisible =
+            show_invisible || actual.compare(&expected, tolerance) == Match::IgnoringWhitespace;
+        if let Some(diff) = diff::unified_diff_with(
+            "expected",
+            "your output",
+            &expected.stdout,
+            &actual.stdout,
+            DiffStyle {
+                show_invisible,
+                show_codepoints,
+            },
+        ) {
+            diff::print_diff(&diff);
+        }
+        if show_invisible {
+            println!("{}", diff::INVISIBLE_LEGEND.dimmed());
+        }
+        if !show_codepoints && (!expected.stdout.is_ascii() || !actual.stdout.is_ascii()) {
+            println!("{}", diff::CODEPOINTS_HINT.dimmed());
+        }
+        println!("Saved to {}", saved.display());
+        exit(exit_codes::FAILURE);
+    }
+
+    println!(
+        "{} your program matched the reference on {} generated inputs",
+        "Passed:".green().bold(),
+        count
+    );
+    if whitespace_passes > 0 {
+        println!(
+            "{} of them pass only ignoring whitespace (line endings or trailing spaces)",
+            whitespace_passes
+        );
+    }
+    if !tolerance_passes.is_empty() {
+        println!(
+            "{} of them pass only within the numeric tolerance of {} (seeds {})",
+            tolerance_passes.len(),
+            tolerance,
+            tolerance_passes.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Ask the generator for the input for one seed, passed as its last argument
+fn generate(generator: &str, activity_dir: &Path, seed: u64) -> Result<String, String> {
+    let command = format!("{} {}", generator, seed);
+    let outcome = run(&command, activity_dir, b"", RUN_TIMEOUT);
+    if outcome.status != "0" {
+        return Err(format!(
+            "the activity's generator failed for seed {} ({})",
+            seed, outcome.status
+        ));
+    }
+    Ok(outcome.stdout)
+}
+
+/// Run a shell command in `dir` with `input` on stdin, giving up after a timeout. The
+/// shell is replaced by the command, so a signal or timeout reaches the command itself.
+pub fn run(command_line: &str, dir: &Path, input: &[u8], timeout: Duration) -> Outcome {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("exec {}", command_line))
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    trace::command(&command);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            return Outcome {
+                stdout: String::new(),
+                stderr: String::new(),
+                status: format!("could not start: {}", e),
+            }
+        }
+    };
+
+    let mut stdin = child.stdin.take();
+    let input = input.to_vec();
+    let writer = thread::spawn(move || {
+        if let Some(stdin) = &mut stdin {
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    let status = match status {
+        Some(status) => match status.code() {
+            Some(code) => code.to_string(),
+            None => format!("killed by signal {}", status.signal().unwrap_or(0)),
+        },
+        None => "timed out".to_string(),
+    };
+    Outcome {
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        status,
+    }
+}
+
+/// Read a child's output stream to the end on another thread
+fn read_in_background(stream: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut stream) = stream {
+            let _ = stream.read_to_end(&mut output);
+        }
+        output
+    })
+}
+
+/// Remove lines from a failing input while it keeps failing: first large chunks, then
+/// smaller ones down to single lines
+pub fn shrink(input: &str, fails: impl Fn(&str) -> bool) -> String {
+    let mut lines: Vec<&str> = input.lines().collect();
+    let join = |lines: &[&str]| {
+        let mut text = lines.join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    };
+
+    let mut chunk = lines.len().div_ceil(2);
+    while chunk > 0 {
+        let mut start = 0;
+        while start < lines.len() {
+            let end = (start + chunk).min(lines.len());
+            let candidate: Vec<&str> = [&lines[..start], &lines[end..]].concat();
+            if fails(&join(&candidate)) {
+                lines = candidate;
+            } else {
+                start = end;
+            }
+        }
+        chunk /= 2;
+    }
+    join(&lines)
+}
+
+/// Keep a failing input and both outputs in the activity's metadata directory
+fn save_counterexample(
+    activity: &str,
+    seed: u64,
+    input: &str,
+    expected: &Outcome,
+    actual: &Outcome,
+) -> Result<PathBuf, String> {
+    let dir = Path::new(METADATA_DIR)
+        .join(activity)
+        .join("counterexamples")
+        .join(format!("seed-{}", seed));
+    let write = |name: &str, contents: &str| {
+        fs::write(dir.join(name), contents)
+            .map_err(|e| format!("Failed to save {}: {}", dir.join(name).display(), e))
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    write("input.txt", input)?;
+    write("expected.txt", &expected.stdout)?;
+    write("actual.txt", &actual.stdout)?;
+    Ok(dir)
+}
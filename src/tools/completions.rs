@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::{BUILT_IN_COMMANDS, STAFF_COMMANDS};
+use crate::config::{self, ClassConfig};
+use crate::error::QuicktoolError;
+use crate::plugins;
+use crate::user_config::UserConfig;
+
+/// Commands of quicktool itself, completed in place of a class code
+const QUICKTOOL_COMMANDS: &[&str] = &[
+    "healthcheck",
+    "doctor",
+    "--bug-report",
+    "list-classes",
+    "install-symlinks",
+    "history",
+    "--classes",
+    "hook",
+    "version",
+    "completions",
+];
+
+/// Options given between the class code and the command
+const GLOBAL_OPTIONS: &[&str] = &[
+    "--strict",
+    "--staff",
+    "--dry-run",
+    "--json",
+    "--verbose",
+    "--quiet",
+    "--color",
+    "--no-color",
+    "--nice",
+    "--chdir",
+    "--help",
+];
+
+/// Built-in commands whose first argument is an activity
+const ACTIVITY_COMMANDS: &[&str] = &[
+    "autotest",
+    "autotest-stage",
+    "fetch-activity",
+    "edit",
+    "help-request",
+    "bench",
+];
+
+/// Compilers autotest accepts before the activity
+const COMPILERS: &[&str] = &["dcc", "gcc", "clang"];
+
+/// Run the completions tool: print a completion script for a shell, e.g.
+/// `quicktool completions bash >> ~/.bashrc`
+pub fn run_completions(args: &[String]) -> Result<i32, QuicktoolError> {
+    let [shell] = args else {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool completions bash|zsh|fish".to_string(),
+        ));
+    };
+    // Class codes are also installed as commands, e.g. `1511 fetch-activity`
+    let mut programs = vec!["quicktool".to_string()];
+    programs.extend(config::installed_classes());
+    let programs = programs.join(" ");
+
+    match shell.as_str() {
+        "bash" => print!(
+            r#"_quicktool() {{
+    local IFS=$'\n'
+    COMPREPLY=($(quicktool __complete "${{COMP_WORDS[@]:0:COMP_CWORD+1}}" 2>/dev/null))
+}}
+complete -o default -F _quicktool {programs}
+"#
+        ),
+        "zsh" => print!(
+            r#"_quicktool() {{
+    local -a candidates
+    candidates=(${{(f)"$(quicktool __complete "${{(@)words[1,CURRENT]}}" 2>/dev/null)"}})
+    if (( ${{#candidates}} )); then
+        compadd -a candidates
+    else
+        _files
+    fi
+}}
+compdef _quicktool {programs}
+"#
+        ),
+        "fish" => {
+            println!("function __quicktool_complete");
+            println!("    quicktool __complete (commandline -opc) (commandline -ct) 2>/dev/null");
+            println!("end");
+            for program in programs.split(' ') {
+                println!("complete -c {} -a '(__quicktool_complete)'", program);
+            }
+        }
+        other => {
+            return Err(QuicktoolError::Usage(format!(
+                "unknown shell {:?} (try bash, zsh or fish)",
+                other
+            )))
+        }
+    }
+    Ok(0)
+}
+
+/// Print the completions for a partly typed command line, one per line. The words are
+/// the program name, the words before the cursor and the word being typed.
+pub fn run_complete(words: &[String]) -> Result<i32, QuicktoolError> {
+    for candidate in complete(words) {
+        println!("{}", candidate);
+    }
+    Ok(0)
+}
+
+fn complete(words: &[String]) -> Vec<String> {
+    let Some((program, words)) = words.split_first() else {
+        return Vec::new();
+    };
+    let Some((current, before)) = words.split_last() else {
+        return Vec::new();
+    };
+
+    let program = Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // Classes and quicktool's commands, when they can come first alongside the default
+    // class's commands
+    let mut first_words = Vec::new();
+    let (class, before) = if program == "quicktool" {
+        match before.split_first() {
+            Some((class, rest)) if config::parse_class_code(class).is_some() => {
+                (class.clone(), rest)
+            }
+            Some((command, _)) if QUICKTOOL_COMMANDS.contains(&command.as_str()) => {
+                return Vec::new()
+            }
+            // Without a class, a command is for the user's default class
+            Some(_) => match UserConfig::load().default_class() {
+                Some(class) => (class, before),
+                None => return Vec::new(),
+            },
+            None => {
+                let mut candidates = config::installed_classes();
+                candidates.extend(QUICKTOOL_COMMANDS.iter().map(|c| c.to_string()));
+                match UserConfig::load().default_class() {
+                    Some(class) => {
+                        first_words = candidates;
+                        (class, before)
+                    }
+                    None => return matching(candidates, current),
+                }
+            }
+        }
+    } else {
+        (program, before)
+    };
+    let Some(mut config) = ClassConfig::new(&class) else {
+        return Vec::new();
+    };
+    let _ = config.load_class_config();
+
+    // The directory after --chdir is left to the shell's own file completion
+    if before.last().is_some_and(|word| word == "--chdir") {
+        return Vec::new();
+    }
+    let mut before = before.iter();
+    let mut command = None;
+    while let Some(word) = before.next() {
+        if word == "--chdir" {
+            before.next();
+        } else if !word.starts_with('-') {
+            command = Some(word);
+            break;
+        }
+    }
+    let mut candidates = match command {
+        None if current.starts_with('-') => GLOBAL_OPTIONS.iter().map(|o| o.to_string()).collect(),
+        None => {
+            let is_staff = config.is_staff();
+            let mut commands = vec!["help".to_string()];
+            commands.extend(
+                BUILT_IN_COMMANDS
+                    .iter()
+                    .map(|(command, _)| *command)
+                    .filter(|command| config.tool_enabled(command))
+                    .filter(|command| is_staff || !STAFF_COMMANDS.contains(command))
+                    .map(str::to_string),
+            );
+            commands.extend(plugins::list(&config));
+            commands.extend(plugins::class_commands(&config));
+            commands
+        }
+        Some(command) if ACTIVITY_COMMANDS.contains(&command.as_str()) => {
+            // Only the first argument is an activity, after any compiler
+            if before.any(|word| !word.starts_with('-') && !COMPILERS.contains(&word.as_str())) {
+                return Vec::new();
+            }
+            activities(&config)
+        }
+        Some(_) => return Vec::new(),
+    };
+    candidates.extend(first_words);
+    matching(candidates, current)
+}
+
+/// Names of the class's activities
+fn activities(config: &ClassConfig) -> Vec<String> {
+    let Ok(dir) = config.paths().activities_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.'))
+        .collect()
+}
+
+/// The candidates starting with the word being typed, sorted
+fn matching(mut candidates: Vec<String>, current: &str) -> Vec<String> {
+    candidates.retain(|candidate| candidate.starts_with(current));
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,138 @@
+use colored::*;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+
+/// Helper programs whose resolved location is shown
+const TOOLS: [&str; 3] = ["autotest", "c_check", "ccache"];
+
+/// Run the config tool: `config show [--json]` prints the configuration quicktool works
+/// out for the class, from its code, its account and config.sh
+pub fn run_config(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let json_output = match args {
+        [show] if show == "show" => config.json,
+        [show, flag] if show == "show" && flag == "--json" => true,
+        _ => {
+            return Err(QuicktoolError::Usage(format!(
+                "usage: {} config show [--json]",
+                config.class
+            )))
+        }
+    };
+
+    let config_sh = ["autotest", "fetch-activity"]
+        .iter()
+        .find_map(|tool| config.tool_config_sh(tool));
+    let load_error = config.load_class_config().err().map(|e| e.to_string());
+    let toml_files: Vec<PathBuf> = config
+        .toml_config_files()
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    let newclassrc = config.newclassrc_path.as_ref().map(|path| {
+        let exists = Path::new(path).exists();
+        (path.clone(), exists)
+    });
+    let paths = config.paths();
+    let locations: Vec<(&str, Result<PathBuf, String>)> = vec![
+        ("session directory", paths.session_dir()),
+        ("activities directory", paths.activities_dir()),
+    ];
+    let tools: Vec<(&str, Option<PathBuf>)> = TOOLS
+        .iter()
+        .map(|tool| (*tool, paths.tool(tool).ok()))
+        .collect();
+    let settings: BTreeMap<&String, &String> = config.custom_config.iter().collect();
+
+    if json_output {
+        let mut resolved = Map::new();
+        for (name, location) in &locations {
+            resolved.insert(
+                name.replace(' ', "_"),
+                match location {
+                    Ok(path) => json!(path),
+                    Err(e) => json!({ "error": e }),
+                },
+            );
+        }
+        for (tool, path) in &tools {
+            resolved.insert(tool.to_string(), json!(path));
+        }
+        let report = json!({
+            "class": config.class,
+            "account": config.account_name,
+            "home_dir": config.home_dir,
+            "bin_path": config.bin_path,
+            "man_path": config.man_path,
+            "newclassrc": newclassrc.as_ref().map(|(path, exists)| {
+                json!({ "path": path, "exists": exists })
+            }),
+            "config_sh": config_sh,
+            "config_sh_error": load_error,
+            "quicktool_toml": toml_files,
+            "settings": settings,
+            "resolved": Value::Object(resolved),
+        });
+        println!("{}", report);
+        return Ok(());
+    }
+
+    let row = |name: &str, value: String| println!("  {:<22} {}", name, value);
+    let missing = |what: &str| what.dimmed().to_string();
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| missing("(none)"));
+
+    println!("{}", "Class".bold());
+    row("class", config.class.clone());
+    row("account", optional(&config.account_name));
+    row("home", optional(&config.home_dir));
+    row("bin", optional(&config.bin_path));
+    row("man", optional(&config.man_path));
+    row(
+        "newclassrc",
+        match &newclassrc {
+            Some((path, true)) => path.clone(),
+            Some((path, false)) => format!("{} {}", path, missing("(missing)")),
+            None => missing("(none)"),
+        },
+    );
+
+    println!("{}", "config.sh".bold());
+    row(
+        "file",
+        match (&config_sh, &load_error) {
+            (Some(path), None) => path.display().to_string(),
+            (_, Some(e)) => format!("{} {}", missing("not loaded:"), e),
+            (None, None) => missing("(none)"),
+        },
+    );
+    for path in &toml_files {
+        row("merged", path.display().to_string());
+    }
+    for (key, value) in &settings {
+        row(key, value.to_string());
+    }
+
+    println!("{}", "Resolved".bold());
+    for (name, location) in &locations {
+        row(
+            name,
+            match location {
+                Ok(path) => path.display().to_string(),
+                Err(e) => missing(e),
+            },
+        );
+    }
+    for (tool, path) in &tools {
+        row(
+            tool,
+            match path {
+                Some(path) => path.display().to_string(),
+                None => missing("(not found)"),
+            },
+        );
+    }
+    Ok(())
+}
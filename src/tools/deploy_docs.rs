@@ -0,0 +1,165 @@
+use colored::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cli::BUILT_IN_COMMANDS;
+use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::tools::{autotest, fetch_activity};
+
+/// Run the deploy-docs tool: write man pages for quicktool's autotest, autotest-stage
+/// and fetch-activity, generated from their options, into the class's man directory,
+/// so `man autotest` in the class shell describes what these tools actually do.
+/// `--dir DIR` writes them to `DIR/man1` instead.
+pub fn run_deploy_docs(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let man_dir = match args {
+        [] => config
+            .man_path
+            .clone()
+            .map(PathBuf::from)
+            .ok_or("the class has no man directory; give one with --dir")?,
+        [flag, dir] if flag == "--dir" => PathBuf::from(dir),
+        _ => {
+            return Err(QuicktoolError::Usage(format!(
+                "usage: {} deploy-docs [--dir DIR]",
+                config.class
+            )))
+        }
+    };
+    let section_dir = man_dir.join("man1");
+
+    let mut pages = autotest::commands().to_vec();
+    pages.push(("fetch-activity", fetch_activity::command()));
+    if !config.dry_run {
+        fs::create_dir_all(&section_dir)
+            .map_err(|e| format!("Failed to create {}: {}", section_dir.display(), e))?;
+    }
+    for (tool, command) in pages {
+        let path = section_dir.join(format!("{}.1", tool));
+        let page = man_page(config, tool, command);
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == page) {
+            println!("{} {}", "Unchanged".dimmed(), path.display());
+        } else if config.dry_run {
+            dry_run::report(&format!("would write {}", path.display()));
+        } else {
+            fs::write(&path, page)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            println!("{} {}", "Wrote".green().bold(), path.display());
+        }
+    }
+    Ok(())
+}
+
+/// A man page in roff for one tool, branded with the class
+fn man_page(config: &ClassConfig, tool: &'static str, command: clap::Command) -> String {
+    let mut command = command.name(tool).bin_name(tool);
+    let usage = command.render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage: ");
+    let summary = BUILT_IN_COMMANDS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map(|(_, help)| help.lines().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+
+    let mut page = format!(
+        ".TH {} 1 \"\" \"quicktool {}\" \"{} tools\"\n",
+        escape(&tool.to_uppercase()),
+        env!("CARGO_PKG_VERSION"),
+        escape(&config.class)
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n",
+        escape(tool),
+        escape(
+            summary
+                .split([',', '('])
+                .next()
+                .unwrap_or_default()
+                .trim_end()
+                .to_lowercase()
+                .as_str()
+        )
+    ));
+    page.push_str(&format!(".SH SYNOPSIS\n{}\n", escape(usage)));
+    page.push_str(&format!(
+        ".SH DESCRIPTION\n{}\n.PP\nOutside the class shell, run it as \\fB{} {}\\fR.\n",
+        escape(&summary),
+        escape(&config.class),
+        escape(tool)
+    ));
+
+    page.push_str(".SH OPTIONS\n");
+    for arg in command.get_arguments() {
+        if arg.is_hide_set() || arg.get_id() == "help" {
+            continue;
+        }
+        let value = arg
+            .get_value_names()
+            .map(|names| names.join(" "))
+            .unwrap_or_else(|| arg.get_id().to_string().to_uppercase());
+        let name = match arg.get_long() {
+            Some(long) if arg.get_action().takes_values() => {
+                format!("\\fB\\-\\-{}\\fR \\fI{}\\fR", escape(long), escape(&value))
+            }
+            Some(long) => format!("\\fB\\-\\-{}\\fR", escape(long)),
+            None => format!("\\fI{}\\fR", escape(&value)),
+        };
+        let help = arg
+            .get_help()
+            .map(|help| help.to_string())
+            .unwrap_or_default();
+        page.push_str(&format!(".TP\n{}\n{}\n", name, escape(&help)));
+        for possible in arg.get_possible_values() {
+            let help = possible.get_help().map(|help| help.to_string());
+            page.push_str(&format!(
+                ".br\n\\fB{}\\fR{}\n",
+                escape(possible.get_name()),
+                help.map(|help| format!(": {}", escape(&help)))
+                    .unwrap_or_default()
+            ));
+        }
+        let defaults: Vec<String> = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy().to_string())
+            .collect();
+        // Flags default to off, which goes without saying
+        if !defaults.is_empty() && arg.get_action().takes_values() {
+            page.push_str(&format!(".br\nDefault: {}\n", escape(&defaults.join(" "))));
+        }
+    }
+    if let Some(after) = command.get_after_help() {
+        page.push_str(&format!(".PP\n{}\n", escape(&after.to_string())));
+    }
+
+    page.push_str(".SH EXIT STATUS\n");
+    for (code, meaning) in exit_codes::DOCUMENTED {
+        page.push_str(&format!(".TP\n{}\n{}\n", code, escape(meaning)));
+    }
+    page.push_str(".PP\nWhen a program it runs fails, its own exit status is passed on.\n");
+
+    page.push_str(&format!(
+        ".SH SEE ALSO\n\\fB{} help\\fR lists the other class commands.\n",
+        escape(&config.class)
+    ));
+    page
+}
+
+/// Text made safe for roff: backslashes and hyphens escaped, and no line starting
+/// with a control character
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
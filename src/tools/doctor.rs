@@ -0,0 +1,303 @@
+use colored::*;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use crate::config::{self, ClassConfig};
+use crate::error::QuicktoolError;
+use crate::retry;
+use crate::tools::healthcheck;
+
+/// Class tools a working class account links from its bin directory
+const CLASS_TOOLS: [&str; 2] = ["autotest", "fetch-activity"];
+
+/// Most problem entries named in one line
+const MAX_LISTED: usize = 5;
+
+/// One link in the chain from class code to runnable tests
+struct Finding {
+    name: &'static str,
+    detail: String,
+    /// What to do about a failure; None if the check passed
+    fix: Option<String>,
+}
+
+impl Finding {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Finding {
+            name,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            name,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn print(&self) {
+        let label = match self.fix {
+            None => "PASS".green().bold(),
+            Some(_) => "FAIL".red().bold(),
+        };
+        println!("{} {:<20} {}", label, self.name, self.detail);
+        if let Some(fix) = &self.fix {
+            println!("     {:<20} {}", "", format!("fix: {}", fix).yellow());
+        }
+    }
+}
+
+/// Run the doctor tool: `quicktool doctor <class>` checks everything between the class
+/// code and a runnable autotest, printing a fix for each problem. Returns the exit code.
+pub fn run_doctor(args: &[String]) -> Result<i32, QuicktoolError> {
+    let [class_code] = args else {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool doctor <class>".to_string(),
+        ));
+    };
+
+    let Some(mut config) = ClassConfig::new(class_code) else {
+        Finding::fail(
+            "class code",
+            format!("'{}' is not a class code", class_code),
+            "give a class code such as 1511, COMP1511 or cs1511",
+        )
+        .print();
+        return Ok(1);
+    };
+    let findings = diagnose(&mut config);
+    let failed = findings
+        .iter()
+        .filter(|finding| finding.fix.is_some())
+        .count();
+    for finding in &findings {
+        finding.print();
+    }
+
+    println!();
+    if failed == 0 {
+        println!(
+            "{} all {} checks passed",
+            "Healthy:".green().bold(),
+            findings.len()
+        );
+        Ok(0)
+    } else {
+        println!(
+            "{} {} of {} checks failed; fix the first one first, as later checks depend on it",
+            "Problems:".red().bold(),
+            failed,
+            findings.len()
+        );
+        Ok(1)
+    }
+}
+
+/// Check each link in the chain in the order quicktool relies on them
+fn diagnose(config: &mut ClassConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let account = config.account_name.clone().unwrap_or_default();
+    findings.push(Finding::pass(
+        "class code",
+        format!("{} (account {})", config.class, account),
+    ));
+
+    // Class account home
+    let home = config.home_dir.clone().unwrap_or_default();
+    findings.push(if retry::exists(Path::new(&home)) {
+        Finding::pass("account home", &home)
+    } else {
+        Finding::fail(
+            "account home",
+            format!("{} does not exist", home),
+            format!(
+                "check that the {} account exists and {} is mounted \
+                 (QUICKTOOL_CLASS_ROOT changes where accounts are looked for)",
+                account,
+                config::class_root().display()
+            ),
+        )
+    });
+
+    // Links in the class bin directory
+    let bin_path = config.bin_path.clone().unwrap_or_default();
+    for tool in CLASS_TOOLS {
+        let link = Path::new(&bin_path).join(tool);
+        findings.push(if fs::symlink_metadata(&link).is_err() {
+            Finding::fail(
+                tool,
+                format!("{} is missing", link.display()),
+                format!("ask course staff to link {} into {}", tool, bin_path),
+            )
+        } else {
+            match retry::retry_io(format!("resolving {}", link.display()), || {
+                fs::canonicalize(&link)
+            }) {
+                Ok(target) => Finding::pass(tool, target.display().to_string()),
+                Err(e) => Finding::fail(
+                    tool,
+                    format!("{} does not resolve: {}", link.display(), e),
+                    format!("ask course staff to point {} at an existing file", tool),
+                ),
+            }
+        });
+    }
+    let dangling = dangling_links(Path::new(&bin_path));
+    findings.push(if dangling.is_empty() {
+        Finding::pass("bin symlinks", "all resolve")
+    } else {
+        Finding::fail(
+            "bin symlinks",
+            format!("dangling: {}", listed(&dangling)),
+            format!(
+                "ask course staff to fix or remove these links in {}",
+                bin_path
+            ),
+        )
+    });
+
+    // Class config.sh
+    findings.push(match config.load_class_config() {
+        Ok(()) => Finding::pass(
+            "config.sh",
+            format!("{} settings", config.custom_config.len()),
+        ),
+        Err(e) => Finding::fail(
+            "config.sh",
+            e.to_string(),
+            "config.sh is read next to the autotest or fetch-activity link; \
+             check it exists and runs in bash without errors",
+        ),
+    });
+
+    // autotest.py and the interpreter it runs in
+    findings.push(match config.paths().tool("autotest") {
+        Ok(script) => Finding::pass("autotest.py", script.display().to_string()),
+        Err(_) => Finding::fail(
+            "autotest.py",
+            "not found",
+            "install autotest at /usr/local/share/autotest/autotest.py \
+             or set autotest_path in config.sh",
+        ),
+    });
+    env::set_var(
+        "PATH",
+        config.get_path(&env::var("PATH").unwrap_or_default()),
+    );
+    findings.push(match healthcheck::probe_version("python3") {
+        Some(version) => Finding::pass("python3", version),
+        None => Finding::fail(
+            "python3",
+            "not found on PATH",
+            "install python3 or add its directory to PATH",
+        ),
+    });
+
+    findings.push(activities_finding(config));
+    findings
+}
+
+/// Whether students can list the activities directory and read every activity in it
+fn activities_finding(config: &ClassConfig) -> Finding {
+    let dir = match config.paths().activities_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            return Finding::fail(
+                "activities",
+                e,
+                "set activities_directory or course_account in config.sh",
+            )
+        }
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            return Finding::fail(
+                "activities",
+                format!("cannot read {}: {}", dir.display(), e),
+                format!(
+                    "check {} exists, then run chmod o+rx on it and its parents",
+                    dir.display()
+                ),
+            )
+        }
+    };
+
+    let mode = fs::metadata(&dir)
+        .map(|metadata| metadata.permissions().mode())
+        .unwrap_or(0);
+    if mode & 0o005 != 0o005 {
+        return Finding::fail(
+            "activities",
+            format!(
+                "{} has mode {:o}, so students outside the group cannot read it",
+                dir.display(),
+                mode & 0o777
+            ),
+            format!("run chmod o+rx {}", dir.display()),
+        );
+    }
+
+    let mut count = 0;
+    let mut unreadable = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        count += 1;
+        let mode = fs::metadata(&path)
+            .map(|metadata| metadata.permissions().mode())
+            .unwrap_or(0);
+        if fs::read_dir(&path).is_err() || mode & 0o005 != 0o005 {
+            unreadable.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    unreadable.sort();
+    if unreadable.is_empty() {
+        Finding::pass(
+            "activities",
+            format!(
+                "{} ({} activities, mode {:o})",
+                dir.display(),
+                count,
+                mode & 0o777
+            ),
+        )
+    } else {
+        Finding::fail(
+            "activities",
+            format!("students cannot read {}", listed(&unreadable)),
+            format!("run chmod -R o+rX on those activities in {}", dir.display()),
+        )
+    }
+}
+
+/// Names of the symlinks in a directory that do not resolve
+fn dangling_links(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dangling: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_symlink()))
+        .filter(|entry| fs::metadata(entry.path()).is_err())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    dangling.sort();
+    dangling
+}
+
+/// A few names for one line, e.g. `a, b, c and 4 more`
+fn listed(names: &[String]) -> String {
+    let shown = names[..names.len().min(MAX_LISTED)].join(", ");
+    match names.len().saturating_sub(MAX_LISTED) {
+        0 => shown,
+        more => format!("{} and {} more", shown, more),
+    }
+}
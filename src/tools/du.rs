@@ -0,0 +1,288 @@
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::artifacts::{self, Artifact};
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::trace;
+
+/// How many of the largest entries to list
+const LARGEST_SHOWN: usize = 10;
+
+/// `struct statvfs` on 64-bit Linux
+#[repr(C)]
+#[derive(Default)]
+struct Statvfs {
+    f_bsize: u64,
+    f_frsize: u64,
+    f_blocks: u64,
+    f_bfree: u64,
+    f_bavail: u64,
+    f_files: u64,
+    f_ffree: u64,
+    f_favail: u64,
+    f_fsid: u64,
+    f_flag: u64,
+    f_namemax: u64,
+    f_spare: [c_int; 6],
+}
+
+extern "C" {
+    fn statvfs(path: *const c_char, buf: *mut Statvfs) -> c_int;
+}
+
+/// Disk quota of one filesystem as reported by `quota -v`
+struct Quota {
+    filesystem: String,
+    used_kb: u64,
+    limit_kb: u64,
+}
+
+/// Options of the du tool
+#[derive(Parser)]
+struct DuArgs {
+    /// Remove the generated files found, such as compiled programs
+    #[arg(long)]
+    clean: bool,
+    /// Directory to summarise
+    #[arg(default_value = ".")]
+    dir: PathBuf,
+}
+
+/// The options of du, for documentation
+pub fn command() -> clap::Command {
+    DuArgs::command()
+}
+
+/// Run the du tool: summarise disk usage of a course directory and the home quota,
+/// e.g. `du [dir] [--clean]`
+pub fn run_du(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let DuArgs { clean, dir } = cli::parse_tool_args(&config.class, "du", args)?;
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()).into());
+    }
+
+    let mut files = Vec::new();
+    walk(&dir, &mut files);
+    let total: u64 = files.iter().map(|(_, size)| size).sum();
+    println!(
+        "{}: {} in {} files",
+        dir.display(),
+        format_size(total).bold(),
+        files.len()
+    );
+
+    // Usage of each top-level entry, like du -d1
+    let mut entries: Vec<(PathBuf, u64)> = Vec::new();
+    for (path, size) in &files {
+        let Ok(relative) = path.strip_prefix(&dir) else {
+            continue;
+        };
+        let Some(top) = relative.components().next() else {
+            continue;
+        };
+        let top = dir.join(top);
+        match entries.iter_mut().find(|(entry, _)| *entry == top) {
+            Some((_, total)) => *total += size,
+            None => entries.push((top, *size)),
+        }
+    }
+    entries.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.cmp(b)));
+    if !entries.is_empty() {
+        println!();
+        println!("Largest entries:");
+        for (path, size) in entries.iter().take(LARGEST_SHOWN) {
+            println!("  {:>10}  {}", format_size(*size), path.display());
+        }
+    }
+
+    // Generated files that are taking up space
+    let mut offenders: Vec<(&PathBuf, u64, Artifact)> = files
+        .iter()
+        .filter_map(|(path, size)| Some((path, *size, artifacts::classify(path)?)))
+        .collect();
+    offenders.sort_by(|(a, a_size, _), (b, b_size, _)| b_size.cmp(a_size).then(a.cmp(b)));
+    if !offenders.is_empty() {
+        println!();
+        println!("Generated files:");
+        for (path, size, artifact) in &offenders {
+            let marker = if artifact.safe_to_delete() {
+                "*".yellow().bold()
+            } else {
+                " ".normal()
+            };
+            println!(
+                "{} {:>10}  {} ({})",
+                marker,
+                format_size(*size),
+                path.display(),
+                artifact.description()
+            );
+        }
+        let removable: Vec<_> = offenders
+            .iter()
+            .filter(|(_, _, artifact)| artifact.safe_to_delete())
+            .collect();
+        let removable_size: u64 = removable.iter().map(|(_, size, _)| size).sum();
+
+        if clean && config.dry_run {
+            for (path, size, _) in removable {
+                dry_run::report(&format!(
+                    "would remove {} ({})",
+                    path.display(),
+                    format_size(*size)
+                ));
+            }
+        } else if clean {
+            let mut freed = 0;
+            for (path, size, _) in removable {
+                match fs::remove_file(path) {
+                    Ok(()) => freed += size,
+                    Err(e) => eprintln!("Failed to remove {}: {}", path.display(), e),
+                }
+            }
+            println!("Removed generated files, freeing {}.", format_size(freed));
+        } else if removable_size > 0 {
+            println!(
+                "Files marked * can be regenerated; remove them ({}) with: quicktool {} du --clean",
+                format_size(removable_size),
+                config.class
+            );
+        }
+    }
+
+    println!();
+    show_quota();
+    Ok(())
+}
+
+/// Collect every regular file below a directory with its disk usage, without following
+/// symlinks
+fn walk(dir: &Path, files: &mut Vec<(PathBuf, u64)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            walk(&path, files);
+        } else if metadata.is_file() {
+            files.push((path, metadata.blocks() * 512));
+        }
+    }
+}
+
+/// Print the home directory quota, or the free space on its filesystem when there is
+/// no quota
+fn show_quota() {
+    let quotas = quota_usage();
+    if !quotas.is_empty() {
+        for quota in quotas {
+            let percent = quota.used_kb * 100 / quota.limit_kb.max(1);
+            let line = format!(
+                "quota on {}: {} used of {} ({}%)",
+                quota.filesystem,
+                format_size(quota.used_kb * 1024),
+                format_size(quota.limit_kb * 1024),
+                percent
+            );
+            if percent >= 90 {
+                println!("{}", line.red().bold());
+            } else {
+                println!("{}", line);
+            }
+        }
+        return;
+    }
+
+    let home = env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    let Ok(path) = CString::new(home.clone()) else {
+        return;
+    };
+    let mut stats = Statvfs::default();
+    // SAFETY: path is NUL-terminated and stats is valid for writes
+    if unsafe { statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return;
+    }
+    let block = stats.f_frsize;
+    println!(
+        "filesystem of {}: {} free of {}",
+        home,
+        format_size(stats.f_bavail * block),
+        format_size(stats.f_blocks * block)
+    );
+}
+
+/// Quotas reported by `quota -v`, skipping filesystems without a limit
+fn quota_usage() -> Vec<Quota> {
+    let mut command = Command::new("quota");
+    command.arg("-v");
+    trace::command(&command);
+    match command.output() {
+        Ok(output) => parse_quota(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parse the output of `quota -v`, where long filesystem names sit on a line of their own
+fn parse_quota(text: &str) -> Vec<Quota> {
+    let mut quotas = Vec::new();
+    let mut filesystem: Option<String> = None;
+    for line in text
+        .lines()
+        .skip_while(|line| !line.contains("Filesystem"))
+        .skip(1)
+    {
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() == 1 {
+            filesystem = Some(fields[0].to_string());
+            continue;
+        }
+        let name = match filesystem.take() {
+            Some(name) => name,
+            None if !fields.is_empty() => fields.remove(0).to_string(),
+            None => continue,
+        };
+        let number =
+            |index: usize| -> Option<u64> { fields.get(index)?.trim_end_matches('*').parse().ok() };
+        let (Some(used_kb), Some(soft), Some(hard)) = (number(0), number(1), number(2)) else {
+            continue;
+        };
+        let limit_kb = if soft > 0 { soft } else { hard };
+        if limit_kb > 0 {
+            quotas.push(Quota {
+                filesystem: name,
+                used_kb,
+                limit_kb,
+            });
+        }
+    }
+    quotas
+}
+
+/// Format a byte count for people
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
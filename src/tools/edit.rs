@@ -0,0 +1,108 @@
+use std::env;
+use std::path::Path;
+use std::process::{exit, Command};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::metadata::FetchRecord;
+use crate::tools::fetch_activity;
+use crate::trace;
+
+/// Extensions of files students are expected to edit
+const SOURCE_EXTENSIONS: [&str; 5] = ["c", "h", "py", "sh", "s"];
+
+/// Run the edit tool: open an exercise's files in the user's editor, optionally
+/// fetching the exercise first
+pub fn run_edit(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let fetch = args.iter().any(|arg| arg == "--fetch");
+    let activity = args.iter().find(|arg| !arg.starts_with("--"));
+
+    if fetch {
+        let activity = activity.ok_or_else(|| {
+            QuicktoolError::Usage(format!("usage: {} edit --fetch activity", config.class))
+        })?;
+        fetch_activity::run_fetch_activity(config, std::slice::from_ref(activity))?;
+    }
+
+    // Without an activity, edit whatever was fetched here most recently
+    let record = match activity {
+        Some(activity) => FetchRecord::load(Path::new("."), activity),
+        None => FetchRecord::list(Path::new(".")).pop(),
+    };
+    let activity = match (activity, &record) {
+        (Some(activity), _) => activity.clone(),
+        (None, Some(record)) => record.activity.clone(),
+        (None, None) => {
+            return Err(QuicktoolError::Usage(format!(
+                "no activity has been fetched here; usage: {} edit [--fetch] activity",
+                config.class
+            )))
+        }
+    };
+
+    let files = primary_files(&activity, record.as_ref());
+    if files.is_empty() {
+        return Err(format!(
+            "no files for '{}' in this directory; try {} edit --fetch {}",
+            activity, config.class, activity
+        )
+        .into());
+    }
+
+    // The class may suggest an editor in its config.sh
+    if config.get_custom_config("quicktool_editor").is_none() {
+        let _ = config.load_class_config();
+    }
+    let editor = editor_command(config);
+    // Run through the shell so editor settings such as "code --wait" work
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg("editor")
+        .args(&files);
+    trace::command(&command);
+
+    match command.status() {
+        Ok(status) => {
+            if !status.success() {
+                exit(exit_codes::of_child(status));
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to start editor '{}': {}", editor, e).into()),
+    }
+}
+
+/// Source files of an exercise present in the working directory
+pub fn primary_files(activity: &str, record: Option<&FetchRecord>) -> Vec<String> {
+    let mut files: Vec<String> = record
+        .map(|record| record.files.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|file| {
+            Path::new(file)
+                .extension()
+                .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()))
+        })
+        .collect();
+
+    let main_file = format!("{}.c", activity);
+    if !files.contains(&main_file) {
+        files.insert(0, main_file);
+    }
+
+    files.retain(|file| Path::new(file).exists());
+    files
+}
+
+/// Editor to run: $VISUAL, then $EDITOR, then the class's `quicktool_editor`, then vi
+fn editor_command(config: &ClassConfig) -> String {
+    env::var("VISUAL")
+        .ok()
+        .or_else(|| env::var("EDITOR").ok())
+        .or_else(|| config.get_custom_config("quicktool_editor").cloned())
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
@@ -1,30 +1,91 @@
+use clap::{CommandFactory, Parser};
 use colored::Colorize;
 use log::info;
+use serde_json::json;
 use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::cli;
 use crate::config::ClassConfig;
+use crate::diff;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{AutotestHistory, FetchRecord};
+use crate::notices;
+use crate::retry;
 
-/// Run the fetch-activity tool to copy or link activity starter files
-pub fn run_fetch_activity(config: &mut ClassConfig, args: &[String]) -> Result<(), String> {
-    // Ensure we have at least one argument (the activity name)
-    if args.is_empty() {
-        let course_number = config.class.clone();
-        println!("usage: {} fetch-activity activity", course_number);
-        return Err(format!("usage: {} fetch-activity activity", course_number));
+/// Options of the fetch-activity tool
+#[derive(Parser)]
+struct FetchActivityArgs {
+    /// Refresh the provided files of an activity fetched before, showing upstream changes
+    #[arg(long)]
+    update: bool,
+    /// Fetch from a local activities tree instead of the class account
+    #[arg(long, value_name = "DIR")]
+    source: Option<PathBuf>,
+    /// Run the class's own fetch-activity script instead
+    #[arg(long)]
+    legacy: bool,
+    /// Activity to fetch, e.g. lab01
+    activity: String,
+}
+
+/// The options of fetch-activity, for documentation
+pub fn command() -> clap::Command {
+    FetchActivityArgs::command()
+}
+
+/// Files a fetch or update dealt with, reported as JSON with `--json`
+#[derive(Default)]
+struct Fetched {
+    copied: Vec<String>,
+    linked: Vec<String>,
+    /// Files left alone because the student already has their own copy
+    kept: Vec<String>,
+}
+
+impl Fetched {
+    fn print_json(&self, config: &ClassConfig, activity: &str) {
+        println!(
+            "{}",
+            json!({
+                "activity": activity,
+                "copied": self.copied,
+                "linked": self.linked,
+                "kept": self.kept,
+                "dry_run": config.dry_run,
+            })
+        );
     }
+}
 
-    let activity_name = &args[0];
+/// Run the fetch-activity tool to copy or link activity starter files
+pub fn run_fetch_activity(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let args: FetchActivityArgs = cli::parse_tool_args(&config.class, "fetch-activity", args)?;
+    let update = args.update;
+    let mut source = match &args.source {
+        Some(dir) => Some(
+            fs::canonicalize(dir)
+                .map_err(|e| format!("Activities tree {} not found: {}", dir.display(), e))?,
+        ),
+        None => None,
+    };
+    let activity_name = &args.activity;
 
     // Path to the "fetch-activity" symlink, which we'll use to find config.sh
     let bin_path = config.bin_path.as_deref().unwrap_or("");
     let original_fetch_activity_softlink = Path::new(bin_path).join("fetch-activity");
 
     // Ensure fetch-activity exists
-    let fetch_activity_path = fs::canonicalize(&original_fetch_activity_softlink)
-        .map_err(|e| format!("Failed to canonicalize fetch-activity path: {}", e))?;
+    let fetch_activity_path = retry::retry_io("resolving fetch-activity", || {
+        fs::canonicalize(&original_fetch_activity_softlink)
+    })
+    .map_err(|e| {
+        QuicktoolError::ToolNotFound(format!("Failed to canonicalize fetch-activity path: {}", e))
+    })?;
 
     // DEBUG: Print the resolved path
     info!("Resolved fetch-activity path: {:?}", fetch_activity_path);
@@ -38,26 +99,33 @@ pub fn run_fetch_activity(config: &mut ClassConfig, args: &[String]) -> Result<(
 
     config
         .load_bash_config(config_sh.to_string_lossy().as_ref())
-        .map_err(|e| format!("Could not load bash config: {}", e))?;
+        .map_err(|e| QuicktoolError::ConfigMissing(format!("Could not load bash config: {}", e)))?;
 
-    // Build path to the activity directory
-    let course_account = config
-        .get_custom_config("course_account")
-        .ok_or_else(|| "course_account not found in config".to_string())?;
+    notices::show_notices(config, Some(activity_name));
+
+    // An update refreshes from wherever the activity was originally fetched
+    if update && source.is_none() {
+        source = FetchRecord::load(Path::new("."), activity_name)
+            .and_then(|record| record.source)
+            .map(PathBuf::from);
+    }
 
-    let activities_path = Path::new("/web")
-        .join(course_account)
-        .join("current")
-        .join("activities")
-        .join(activity_name);
+    // Build path to the activity directory, either from a local checkout or the class account
+    if let Some(dir) = &source {
+        info!("Fetching from local activities tree {}", dir.display());
+    }
+    let activities_path = activities_root(config, source.as_deref())?.join(activity_name);
 
     // Ensure activity directory exists
-    if !activities_path.exists() {
+    if !retry::exists(&activities_path) {
+        if config.json {
+            return Err(format!("Exercise '{}' does not exist", activity_name).into());
+        }
         println!(
             "Exercise '{}' does not exist. Make sure you spelt it correctly!",
             activity_name
         );
-        return Err(format!("Exercise '{}' does not exist", activity_name));
+        return Err(format!("Exercise '{}' does not exist", activity_name).into());
     }
 
     // Check for files directories
@@ -65,39 +133,88 @@ pub fn run_fetch_activity(config: &mut ClassConfig, args: &[String]) -> Result<(
     let files_ln_dir = activities_path.join("files.ln");
     let files_cp_dir = activities_path.join("files.cp");
 
-    if files_dir.exists() || files_ln_dir.exists() || files_cp_dir.exists() {
+    if update {
+        return Ok(update_activity(config, activity_name, &activities_path)?);
+    }
+
+    warn_missing_prerequisites(activity_name, &activities_path);
+
+    let mut record = FetchRecord::load(Path::new("."), activity_name)
+        .unwrap_or_else(|| FetchRecord::new(activity_name, &config.class));
+    record.source = source.map(|dir| dir.to_string_lossy().to_string());
+
+    let mut fetched = Fetched::default();
+    if retry::exists(&files_dir) || retry::exists(&files_ln_dir) || retry::exists(&files_cp_dir) {
         // Copy files from files/ and files.cp/ directories
-        copy_files_from_dirs(&[&files_dir, &files_cp_dir])?;
+        copy_files_from_dirs(
+            config,
+            &[&files_dir, &files_cp_dir],
+            &mut record,
+            &mut fetched,
+        )?;
 
         // Link files from files.ln/ directory
-        link_files_from_dir(&files_ln_dir)?;
+        link_files_from_dir(config, &files_ln_dir, &mut fetched)?;
     } else {
         // Check for main activity file
         let main_file = activities_path.join(format!("{}.c", activity_name));
 
-        if !main_file.exists() {
-            println!(
-                "Exercise '{}' does not have any starter code.",
-                activity_name
-            );
-            return Err(format!("No starter code for '{}'", activity_name));
+        if !retry::exists(&main_file) {
+            if !config.json {
+                println!(
+                    "Exercise '{}' does not have any starter code.",
+                    activity_name
+                );
+            }
+            return Err(format!("No starter code for '{}'", activity_name).into());
         }
 
         let target_file_name = activity_name.to_string() + ".c";
         let target_file = Path::new(&target_file_name);
         if target_file.exists() {
-            println!(
-                "The file '{}.c' already exists in this directory!",
-                activity_name
-            );
-            return Err(format!("File '{}.c' already exists", activity_name));
+            if !config.json {
+                println!(
+                    "The file '{}.c' already exists in this directory!",
+                    activity_name
+                );
+            }
+            return Err(format!("File '{}.c' already exists", activity_name).into());
         }
 
         // Copy the main file
-        fs::copy(&main_file, target_file)
-            .map_err(|e| format!("Failed to copy file {}.c: {}", activity_name, e))?;
+        fetched.copied.push(target_file_name.clone());
+        if config.dry_run {
+            if config.json {
+                fetched.print_json(config, activity_name);
+            } else {
+                dry_run::report(&format!(
+                    "would copy {} to {}",
+                    main_file.display(),
+                    target_file_name
+                ));
+            }
+            return Ok(());
+        }
+        retry::retry_io(format!("copying {}", main_file.display()), || {
+            fs::copy(&main_file, target_file)
+        })
+        .map_err(|e| format!("Failed to copy file {}.c: {}", activity_name, e))?;
+        remember_original(&mut record, &target_file_name, &main_file)?;
+    }
+
+    if config.json {
+        fetched.print_json(config, activity_name);
+    }
+    if config.dry_run {
+        return Ok(());
     }
+    record
+        .save(Path::new("."))
+        .map_err(|e| format!("Failed to save fetch metadata: {}", e))?;
 
+    if config.json {
+        return Ok(());
+    }
     println!(
         "Copied '{}' starter code successfully!",
         activity_name.green().bold()
@@ -105,34 +222,252 @@ pub fn run_fetch_activity(config: &mut ClassConfig, args: &[String]) -> Result<(
     Ok(())
 }
 
+/// Warn when the activity's manifest lists prerequisites that autotest has not passed
+/// for in this directory
+fn warn_missing_prerequisites(activity: &str, activity_dir: &Path) {
+    let Some(manifest) = ActivityManifest::load(activity_dir) else {
+        return;
+    };
+    let Some(prerequisites) = manifest.get("prerequisites") else {
+        return;
+    };
+    for prerequisite in prerequisites.split_whitespace() {
+        if !AutotestHistory::load(Path::new("."), prerequisite).passed() {
+            eprintln!(
+                "{} {} builds on {}, which has not passed autotest in this directory yet",
+                "Warning:".yellow().bold(),
+                activity,
+                prerequisite
+            );
+        }
+    }
+}
+
+/// Directory holding the class's activities: a local tree if given, otherwise the class account
+pub fn activities_root(config: &ClassConfig, source: Option<&Path>) -> Result<PathBuf, String> {
+    match source {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => config.paths().activities_dir(),
+    }
+}
+
+/// Locate an activity's directory for tools other than fetch-activity, using the tree it
+/// was fetched from into the working directory if it was
+pub fn locate_activity(
+    config: &mut ClassConfig,
+    activity: &str,
+) -> Result<PathBuf, QuicktoolError> {
+    let source = FetchRecord::load(Path::new("."), activity)
+        .and_then(|record| record.source)
+        .map(PathBuf::from);
+    if source.is_none() && config.paths().activities_dir().is_err() {
+        config.require_class_config()?;
+    }
+    let root = activities_root(config, source.as_deref()).map_err(QuicktoolError::ConfigMissing)?;
+    Ok(root.join(activity))
+}
+
+/// Refresh provided files of an already fetched activity, showing what changed upstream
+fn update_activity(
+    config: &ClassConfig,
+    activity_name: &str,
+    activities_path: &Path,
+) -> Result<(), String> {
+    let mut record = FetchRecord::load(Path::new("."), activity_name).ok_or_else(|| {
+        format!(
+            "'{}' was not fetched into this directory; fetch it without --update first",
+            activity_name
+        )
+    })?;
+
+    let provided = provided_files(config, activity_name, activities_path)?;
+    if provided.is_empty() {
+        if !config.json {
+            println!(
+                "Exercise '{}' does not have any starter code.",
+                activity_name
+            );
+        }
+        return Err(format!("No starter code for '{}'", activity_name));
+    }
+
+    let mut changed = 0;
+    let mut fetched = Fetched::default();
+    for (file_name, upstream_path) in provided {
+        let upstream = retry::retry_io(format!("reading {}", upstream_path.display()), || {
+            fs::read(&upstream_path)
+        })
+        .map_err(|e| format!("Failed to read {}: {}", upstream_path.display(), e))?;
+        let original = fs::read(record.original_path(Path::new("."), &file_name)).ok();
+
+        if original.as_ref() == Some(&upstream) {
+            continue;
+        }
+        changed += 1;
+
+        let local = fs::read(&file_name).ok();
+        let replace = local.is_none() || local == original;
+        if config.json {
+            if replace {
+                fetched.copied.push(file_name.clone());
+            } else {
+                fetched.kept.push(file_name.clone());
+            }
+        }
+
+        match &original {
+            _ if config.json => {}
+            Some(original) => {
+                let diff = diff::unified_diff(
+                    &format!("{} (as fetched)", file_name),
+                    &format!("{} (current)", file_name),
+                    &String::from_utf8_lossy(original),
+                    &String::from_utf8_lossy(&upstream),
+                );
+                println!("Course staff changed {}:", file_name.yellow().bold());
+                if let Some(diff) = diff {
+                    diff::print_diff(&diff);
+                }
+            }
+            None => println!("Course staff added {}", file_name.yellow().bold()),
+        }
+
+        // Only replace the local copy if the student has not modified it
+        if config.dry_run {
+            if replace && !config.json {
+                dry_run::report(&format!(
+                    "would copy {} to {}",
+                    upstream_path.display(),
+                    file_name
+                ));
+            }
+            continue;
+        }
+        if replace {
+            retry::retry_io(format!("copying {}", upstream_path.display()), || {
+                fs::copy(&upstream_path, &file_name)
+            })
+            .map_err(|e| format!("Failed to copy file {}: {}", file_name, e))?;
+            if !config.json {
+                println!("Updated {}", file_name.green().bold());
+            }
+        } else if !config.json {
+            println!(
+                "You have modified {}; your copy was left unchanged.",
+                file_name.red().bold()
+            );
+        }
+
+        remember_original(&mut record, &file_name, &upstream_path)?;
+    }
+
+    if !config.dry_run {
+        record
+            .save(Path::new("."))
+            .map_err(|e| format!("Failed to save fetch metadata: {}", e))?;
+    }
+
+    if config.json {
+        fetched.print_json(config, activity_name);
+    } else if changed == 0 {
+        println!("'{}' is already up to date.", activity_name.green().bold());
+    }
+    Ok(())
+}
+
+/// List the provided (copied) files of an activity as (file name, upstream path) pairs
+fn provided_files(
+    config: &ClassConfig,
+    activity_name: &str,
+    activities_path: &Path,
+) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut files = Vec::new();
+
+    for dir in [
+        activities_path.join("files"),
+        activities_path.join("files.cp"),
+    ] {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in walk_dir(config, &dir)?
+            .into_iter()
+            .filter(|e| e.file_type().is_file())
+        {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            files.push((file_name, entry.path().to_path_buf()));
+        }
+    }
+
+    if files.is_empty() && !activities_path.join("files.ln").is_dir() {
+        let main_file = activities_path.join(format!("{}.c", activity_name));
+        if main_file.exists() {
+            files.push((format!("{}.c", activity_name), main_file));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Store the fetched copy of a provided file in the activity's metadata
+fn remember_original(
+    record: &mut FetchRecord,
+    file_name: &str,
+    source: &Path,
+) -> Result<(), String> {
+    record
+        .store_original(Path::new("."), file_name, source)
+        .map_err(|e| format!("Failed to save fetch metadata for {}: {}", file_name, e))
+}
+
 /// Copy files from multiple directories if they exist
-fn copy_files_from_dirs(dirs: &[&Path]) -> Result<(), String> {
+fn copy_files_from_dirs(
+    config: &ClassConfig,
+    dirs: &[&Path],
+    record: &mut FetchRecord,
+    fetched: &mut Fetched,
+) -> Result<(), String> {
     for dir in dirs {
         if !dir.is_dir() {
             continue;
         }
 
-        for entry in WalkDir::new(dir)
-            .follow_links(true)
-            .min_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
+        for entry in walk_dir(config, dir)? {
             if entry.file_type().is_file() {
                 let file_path = entry.path();
                 let file_name = file_path.file_name().unwrap_or_else(|| OsStr::new(""));
                 let target_path = Path::new(file_name);
 
+                let name = file_name.to_string_lossy().to_string();
                 if target_path.exists() {
-                    println!(
-                        "The file {} already exists in this directory",
-                        file_name.to_string_lossy().red().bold()
-                    );
+                    fetched.kept.push(name);
+                    if !config.json {
+                        println!(
+                            "The file {} already exists in this directory",
+                            file_name.to_string_lossy().red().bold()
+                        );
+                    }
+                } else if config.dry_run {
+                    fetched.copied.push(name);
+                    if !config.json {
+                        dry_run::report(&format!(
+                            "would copy {} to {}",
+                            file_path.display(),
+                            file_name.to_string_lossy()
+                        ));
+                    }
                 } else {
-                    println!("Copying {}", file_name.to_string_lossy().red().bold());
-                    fs::copy(file_path, target_path).map_err(|e| {
+                    if !config.json {
+                        println!("Copying {}", file_name.to_string_lossy().red().bold());
+                    }
+                    retry::retry_io(format!("copying {}", file_path.display()), || {
+                        fs::copy(file_path, target_path)
+                    })
+                    .map_err(|e| {
                         format!("Failed to copy file {}: {}", file_name.to_string_lossy(), e)
                     })?;
+                    remember_original(record, &name, file_path)?;
+                    fetched.copied.push(name);
                 }
             }
         }
@@ -141,35 +476,68 @@ fn copy_files_from_dirs(dirs: &[&Path]) -> Result<(), String> {
 }
 
 /// Create symlinks to files in the source directory
-fn link_files_from_dir(dir: &Path) -> Result<(), String> {
+fn link_files_from_dir(
+    config: &ClassConfig,
+    dir: &Path,
+    fetched: &mut Fetched,
+) -> Result<(), String> {
     if !dir.is_dir() {
         return Ok(());
     }
 
-    for entry in WalkDir::new(dir)
-        .follow_links(true)
-        .min_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
+    for entry in walk_dir(config, dir)? {
         if entry.file_type().is_file() {
             let file_path = entry.path();
             let file_name = file_path.file_name().unwrap_or_else(|| OsStr::new(""));
             let target_path = Path::new(file_name);
 
+            let name = file_name.to_string_lossy().to_string();
             if target_path.exists() {
-                println!(
-                    "The file {} already exists in this directory",
-                    file_name.to_string_lossy().red().bold()
-                );
+                fetched.kept.push(name);
+                if !config.json {
+                    println!(
+                        "The file {} already exists in this directory",
+                        file_name.to_string_lossy().red().bold()
+                    );
+                }
+            } else if config.dry_run {
+                fetched.linked.push(name);
+                if !config.json {
+                    dry_run::report(&format!(
+                        "would link {} to {}",
+                        file_name.to_string_lossy(),
+                        file_path.display()
+                    ));
+                }
             } else {
-                println!("Linking {}", file_name.to_string_lossy().red().bold());
+                if !config.json {
+                    println!("Linking {}", file_name.to_string_lossy().red().bold());
+                }
 
                 std::os::unix::fs::symlink(file_path, target_path).map_err(|e| {
                     format!("Failed to link file {}: {}", file_name.to_string_lossy(), e)
                 })?;
+                fetched.linked.push(name);
             }
         }
     }
     Ok(())
 }
+
+/// Walk the entries below a directory, following symlinks, in order of name. Entries that
+/// cannot be read, such as dangling symlinks, are reported as configuration warnings and
+/// skipped.
+fn walk_dir(config: &ClassConfig, dir: &Path) -> Result<Vec<walkdir::DirEntry>, String> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(dir)
+        .follow_links(true)
+        .min_depth(1)
+        .sort_by_file_name()
+    {
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(e) => config.config_warning(&format!("skipping unreadable entry: {}", e))?,
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::features;
+use crate::tools::healthcheck::probe_version;
+
+/// Programs whose versions decide how submissions build and run
+const PROGRAMS: [&str; 4] = ["dcc", "gcc", "clang", "python3"];
+
+/// What a result was produced with, so staff can tell a student's environment from the
+/// marking environment at a glance
+#[derive(Debug)]
+pub struct Fingerprint {
+    pub quicktool: String,
+    pub host: String,
+    pub class: String,
+    /// Hash of the class's loaded config.sh settings
+    pub config_hash: String,
+    /// Version line of each program, None where it is not installed
+    pub programs: Vec<(String, Option<String>)>,
+}
+
+impl Fingerprint {
+    /// Take the fingerprint of this machine for a class
+    pub fn collect(config: &ClassConfig) -> Self {
+        let mut settings: Vec<_> = config.custom_config.iter().collect();
+        settings.sort();
+        let settings: String = settings
+            .iter()
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect();
+
+        Fingerprint {
+            quicktool: features::version().to_string(),
+            host: hostname(),
+            class: config.class.clone(),
+            config_hash: hash(&settings),
+            programs: PROGRAMS
+                .iter()
+                .map(|program| (program.to_string(), probe_version(program)))
+                .collect(),
+        }
+    }
+
+    /// Short identifier that differs whenever anything in the fingerprint does
+    pub fn id(&self) -> String {
+        hash(&self.lines().join("\n"))[..8].to_string()
+    }
+
+    /// One `name: value` line per item
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("quicktool: {}", self.quicktool),
+            format!("host: {}", self.host),
+            format!("class config: {} {}", self.class, self.config_hash),
+        ];
+        for (program, version) in &self.programs {
+            let version = version.as_deref().unwrap_or("not installed");
+            lines.push(format!("{}: {}", program, version));
+        }
+        lines
+    }
+
+    /// The identifier and every line, as shown by the fingerprint tool
+    pub fn to_text(&self) -> String {
+        format!("fingerprint: {}\n{}\n", self.id(), self.lines().join("\n"))
+    }
+
+    pub fn to_json(&self) -> Value {
+        let programs: serde_json::Map<String, Value> = self
+            .programs
+            .iter()
+            .map(|(program, version)| (program.clone(), json!(version)))
+            .collect();
+        json!({
+            "id": self.id(),
+            "quicktool": self.quicktool,
+            "host": self.host,
+            "class": self.class,
+            "config_hash": self.config_hash,
+            "programs": programs,
+        })
+    }
+}
+
+/// Run the fingerprint tool: print this machine's fingerprint, as JSON with `--json`
+pub fn run_fingerprint(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let json_output = match args {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => {
+            return Err(QuicktoolError::Usage(format!(
+                "usage: {} fingerprint [--json]",
+                config.class
+            )))
+        }
+    };
+    let _ = config.load_class_config();
+
+    let fingerprint = Fingerprint::collect(config);
+    if json_output {
+        println!("{}", fingerprint.to_json());
+    } else {
+        print!("{}", fingerprint.to_text());
+    }
+    Ok(())
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .or_else(|| env::var("HOSTNAME").ok())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 64-bit FNV-1a hash in hex, stable across builds unlike `DefaultHasher`
+fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
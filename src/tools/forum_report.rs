@@ -0,0 +1,85 @@
+use regex::Regex;
+
+use crate::features;
+use crate::tools::bug_report::scrub;
+use crate::tools::fingerprint::Fingerprint;
+use crate::tools::healthcheck::probe_version;
+
+/// Most lines of output kept for each failing test
+const MAX_LINES_PER_TEST: usize = 20;
+
+/// Most failing tests described in one report
+const MAX_TESTS: usize = 3;
+
+/// Build a markdown post for the course forum from the output of an autotest run
+pub fn forum_report(
+    exercise: &str,
+    compiler: &str,
+    output: &str,
+    fingerprint: &Fingerprint,
+) -> String {
+    let failure = Regex::new(r"^Test (\S+) .*- failed").expect("valid failure pattern");
+    let test_start = Regex::new(r"^Test \S+ ").expect("valid test pattern");
+
+    // Output of each failing test, up to the start of the next test
+    let mut failures: Vec<(String, Vec<&str>)> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in output.lines() {
+        if let Some(caps) = failure.captures(line) {
+            failures.push((caps[1].to_string(), vec![line]));
+            current = Some(failures.len() - 1);
+        } else if test_start.is_match(line) {
+            current = None;
+        } else if let Some(index) = current {
+            failures[index].1.push(line);
+        }
+    }
+    let summary = output
+        .lines()
+        .rev()
+        .find(|line| line.contains("tests passed") || line.contains("tests failed"))
+        .unwrap_or("");
+
+    let mut report = format!("### {}: autotest failing\n\n", exercise);
+    if failures.is_empty() {
+        report.push_str("No failing tests were found in the autotest output.\n");
+    } else {
+        let names: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        report.push_str(&format!("**Failing tests:** {}\n\n", names.join(", ")));
+    }
+    if !summary.is_empty() {
+        report.push_str(&format!("**Summary:** {}\n\n", summary.trim()));
+    }
+
+    for (name, lines) in failures.iter().take(MAX_TESTS) {
+        report.push_str(&format!(
+            "<details><summary>Test {}</summary>\n\n```\n",
+            name
+        ));
+        for line in lines.iter().take(MAX_LINES_PER_TEST) {
+            report.push_str(line);
+            report.push('\n');
+        }
+        if lines.len() > MAX_LINES_PER_TEST {
+            report.push_str(&format!(
+                "... ({} more lines)\n",
+                lines.len() - MAX_LINES_PER_TEST
+            ));
+        }
+        report.push_str("```\n\n</details>\n\n");
+    }
+    if failures.len() > MAX_TESTS {
+        report.push_str(&format!(
+            "({} more failing tests not shown)\n\n",
+            failures.len() - MAX_TESTS
+        ));
+    }
+
+    report.push_str(&format!(
+        "**Compiler:** {}  \n**quicktool:** {}  \n**Environment fingerprint:** `{}`\n",
+        probe_version(compiler).unwrap_or_else(|| compiler.to_string()),
+        features::long_version(),
+        fingerprint.id()
+    ));
+    scrub(&report)
+}
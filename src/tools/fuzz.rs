@@ -0,0 +1,375 @@
+use colored::Colorize;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{FetchRecord, METADATA_DIR};
+use crate::shell_quote;
+use crate::temp::TempDir;
+use crate::tools::compare::{self, Outcome};
+use crate::tools::edit::primary_files;
+use crate::trace;
+
+/// How long one run of the program may take before it counts as hanging
+const RUN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most seed inputs read from an activity, and the largest size of each
+const MAX_SEEDS: usize = 100;
+const MAX_SEED_BYTES: u64 = 64 * 1024;
+
+/// Most distinct crashes saved in one fuzzing session
+const MAX_CRASHES: usize = 10;
+
+/// Values that often break input handling, swapped in for numbers
+const INTERESTING_NUMBERS: &[&str] = &[
+    "0",
+    "-1",
+    "1",
+    "127",
+    "128",
+    "255",
+    "256",
+    "2147483647",
+    "-2147483648",
+    "4294967296",
+    "99999999999999999999",
+];
+
+/// Fuzz a program for `time`: mutate the activity's test inputs and run the program on
+/// them under sanitizers, saving every input that crashes or hangs it. `target` is a
+/// program, or an exercise whose source is compiled with `compiler`.
+pub fn run_fuzz(
+    config: &ClassConfig,
+    target: &str,
+    time: Duration,
+    compiler: &str,
+    activities_dir: &Path,
+) -> Result<(), QuicktoolError> {
+    if config.dry_run {
+        dry_run::report(&format!(
+            "would fuzz {} for {} seconds",
+            target,
+            time.as_secs()
+        ));
+        return Ok(());
+    }
+
+    let build_dir = TempDir::new("fuzz")?;
+    let (program, activity) = if Path::new(target).is_file() {
+        let activity = FetchRecord::list(Path::new("."))
+            .pop()
+            .map(|record| record.activity)
+            .unwrap_or_else(|| file_stem(target));
+        (PathBuf::from(target), activity)
+    } else {
+        (
+            build(target, compiler, build_dir.path())?,
+            target.to_string(),
+        )
+    };
+    let program = shell_quote::quote(&program.canonicalize().unwrap_or(program).to_string_lossy());
+
+    let activity_dir = activities_dir.join(&activity);
+    let seeds = seed_inputs(&activity_dir);
+    println!(
+        "Fuzzing {} for {} seconds from {} seed inputs of {}...",
+        target,
+        time.as_secs(),
+        seeds.len(),
+        activity
+    );
+
+    // Leaks are not what fuzzing looks for, and most student programs have some
+    env::set_var("ASAN_OPTIONS", "detect_leaks=0:abort_on_error=0");
+    let mut random = Random::new();
+    let mut signatures = Vec::new();
+    let mut runs = 0u64;
+    let deadline = Instant::now() + time;
+    while Instant::now() < deadline && signatures.len() < MAX_CRASHES {
+        let mut input = seeds[random.below(seeds.len())].clone();
+        for _ in 0..=random.below(4) {
+            mutate(&mut input, &mut random);
+        }
+        runs += 1;
+        let outcome = compare::run(&program, Path::new("."), &input, RUN_TIMEOUT);
+        let Some(report) = crash_report(&outcome) else {
+            continue;
+        };
+        let signature = signature(&report);
+        if signatures.contains(&signature) {
+            continue;
+        }
+
+        let input = minimise(&program, input, &signature);
+        let saved = save_crash(&activity, signatures.len() + 1, &input, &outcome)?;
+        println!(
+            "{} {} (saved to {})",
+            "Crash:".red().bold(),
+            report,
+            saved.display()
+        );
+        signatures.push(signature);
+    }
+    drop(build_dir);
+
+    if signatures.is_empty() {
+        println!(
+            "{} no crashes found in {} runs",
+            "Done:".green().bold(),
+            runs
+        );
+        return Ok(());
+    }
+    println!(
+        "{} {} distinct crashes found in {} runs; rerun one with e.g. {} < {}",
+        "Done:".red().bold(),
+        signatures.len(),
+        runs,
+        target,
+        Path::new(METADATA_DIR)
+            .join(&activity)
+            .join("crashes/crash-1/input")
+            .display()
+    );
+    exit(exit_codes::FAILURE);
+}
+
+/// Compile an exercise's source in the working directory with sanitizers
+fn build(exercise: &str, compiler: &str, build_dir: &Path) -> Result<PathBuf, String> {
+    let record = FetchRecord::load(Path::new("."), exercise);
+    let sources: Vec<String> = primary_files(exercise, record.as_ref())
+        .into_iter()
+        .filter(|file| file.ends_with(".c"))
+        .collect();
+    if sources.is_empty() {
+        return Err(format!(
+            "{} is neither a program nor an exercise with source code here",
+            exercise
+        ));
+    }
+
+    fs::create_dir_all(build_dir)
+        .map_err(|e| format!("Failed to create {}: {}", build_dir.display(), e))?;
+    let binary = build_dir.join(exercise);
+    let mut command = Command::new(compiler);
+    // dcc already builds with sanitizers and explains their errors
+    if compiler != "dcc" {
+        command.args([
+            "-g",
+            "-fsanitize=address,undefined",
+            "-fno-omit-frame-pointer",
+        ]);
+    }
+    command.args(&sources).arg("-o").arg(&binary);
+    trace::command(&command);
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", compiler, e))?;
+    if !status.success() {
+        return Err(format!("{} could not compile {}", compiler, exercise));
+    }
+    Ok(binary)
+}
+
+/// Inputs to start from: the activity's test inputs (files named like `*stdin*` or
+/// `*.in`) and the files in its `fuzz_seeds` directory if the manifest names one
+fn seed_inputs(activity_dir: &Path) -> Vec<Vec<u8>> {
+    let mut dirs = vec![activity_dir.to_path_buf()];
+    if let Some(dir) = ActivityManifest::load(activity_dir)
+        .and_then(|manifest| manifest.get("fuzz_seeds").map(str::to_string))
+    {
+        dirs.push(activity_dir.join(dir));
+    }
+
+    let mut seeds: Vec<Vec<u8>> = Vec::new();
+    for dir in dirs {
+        // In order of name, so the same seeds are chosen on every machine
+        let walker = WalkDir::new(dir).follow_links(true).sort_by_file_name();
+        for entry in walker.into_iter().flatten() {
+            let name = entry.file_name().to_string_lossy();
+            let is_input = name.contains("stdin") || name.ends_with(".in");
+            let small = entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() <= MAX_SEED_BYTES);
+            if entry.file_type().is_file() && is_input && small && seeds.len() < MAX_SEEDS {
+                if let Ok(contents) = fs::read(entry.path()) {
+                    seeds.push(contents);
+                }
+            }
+        }
+    }
+    if seeds.is_empty() {
+        seeds.extend([b"".to_vec(), b"1\n".to_vec(), b"hello world\n".to_vec()]);
+    }
+    seeds
+}
+
+/// Change an input in one small way
+fn mutate(input: &mut Vec<u8>, random: &mut Random) {
+    let lines = |input: &[u8]| -> Vec<Vec<u8>> {
+        input
+            .split_inclusive(|&byte| byte == b'\n')
+            .map(<[u8]>::to_vec)
+            .collect()
+    };
+    match random.below(8) {
+        // Flip a bit
+        0 if !input.is_empty() => {
+            let at = random.below(input.len());
+            input[at] ^= 1 << random.below(8);
+        }
+        // Insert a byte
+        1 => {
+            let at = random.below(input.len() + 1);
+            let byte = *b" -09az\n\0\xff".get(random.below(10)).unwrap_or(&b'x');
+            input.insert(at, byte);
+        }
+        // Delete some bytes
+        2 if !input.is_empty() => {
+            let start = random.below(input.len());
+            let end = (start + 1 + random.below(8)).min(input.len());
+            input.drain(start..end);
+        }
+        // Repeat a line
+        3 if !input.is_empty() => {
+            let mut lines = lines(input);
+            let line = lines[random.below(lines.len())].clone();
+            let at = random.below(lines.len() + 1);
+            lines.insert(at, line);
+            *input = lines.concat();
+        }
+        // Remove a line
+        4 if !input.is_empty() => {
+            let mut lines = lines(input);
+            lines.remove(random.below(lines.len()));
+            *input = lines.concat();
+        }
+        // Swap a number for one that often breaks input handling
+        5 => {
+            let text = String::from_utf8_lossy(input).to_string();
+            let numbers: Vec<_> = Regex::new(r"-?\d+")
+                .expect("valid number pattern")
+                .find_iter(&text)
+                .collect();
+            let replacement = INTERESTING_NUMBERS[random.below(INTERESTING_NUMBERS.len())];
+            if numbers.is_empty() {
+                input.extend_from_slice(format!("{}\n", replacement).as_bytes());
+            } else {
+                let number = numbers[random.below(numbers.len())];
+                let mut text = text.clone();
+                text.replace_range(number.range(), replacement);
+                *input = text.into_bytes();
+            }
+        }
+        // Make the input much longer
+        6 => {
+            let copy = input.clone();
+            input.extend_from_slice(&copy);
+            if input.is_empty() {
+                input.extend(std::iter::repeat_n(b'a', 4096));
+            }
+        }
+        // Cut the input short
+        _ => {
+            let at = random.below(input.len() + 1);
+            input.truncate(at);
+        }
+    }
+}
+
+/// What went wrong in a run, if it crashed, hung or tripped a sanitizer
+fn crash_report(outcome: &Outcome) -> Option<String> {
+    let sanitizer = outcome.stderr.lines().find(|line| {
+        line.contains("ERROR: AddressSanitizer")
+            || line.contains("runtime error")
+            || line.contains("Runtime error")
+    });
+    match sanitizer {
+        Some(line) => Some(line.trim().to_string()),
+        None if outcome.status.starts_with("killed by signal") => Some(outcome.status.clone()),
+        None if outcome.status == "timed out" => Some(format!(
+            "took longer than {} seconds",
+            RUN_TIMEOUT.as_secs()
+        )),
+        None => None,
+    }
+}
+
+/// A crash report with its addresses and values dropped, so one bug found with
+/// different inputs is reported once
+fn signature(report: &str) -> String {
+    let numbers = Regex::new(r"0x[0-9a-fA-F]+|-?\d+").expect("valid number pattern");
+    numbers.replace_all(report, "N").to_string()
+}
+
+/// Shrink a crashing text input line by line while it crashes the same way
+fn minimise(program: &str, input: Vec<u8>, signature: &str) -> Vec<u8> {
+    let Ok(text) = String::from_utf8(input.clone()) else {
+        return input;
+    };
+    compare::shrink(&text, |candidate| {
+        let outcome = compare::run(program, Path::new("."), candidate.as_bytes(), RUN_TIMEOUT);
+        crash_report(&outcome).is_some_and(|report| self::signature(&report) == signature)
+    })
+    .into_bytes()
+}
+
+/// Keep a crashing input and what the program reported in the activity's metadata
+fn save_crash(
+    activity: &str,
+    number: usize,
+    input: &[u8],
+    outcome: &Outcome,
+) -> Result<PathBuf, String> {
+    let dir = Path::new(METADATA_DIR)
+        .join(activity)
+        .join("crashes")
+        .join(format!("crash-{}", number));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    fs::write(dir.join("input"), input)
+        .and_then(|_| {
+            fs::write(
+                dir.join("report.txt"),
+                format!("status: {}\n\n{}", outcome.status, outcome.stderr),
+            )
+        })
+        .map_err(|e| format!("Failed to save the crash in {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Small xorshift generator; fuzzing needs variety, not statistical quality
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Random(seed | 1)
+    }
+
+    /// A number below `n`, which must not be 0
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
@@ -0,0 +1,406 @@
+use clap::{Parser, ValueEnum};
+use colored::Colorize;
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::features;
+use crate::retry;
+use crate::toolchain;
+
+/// How long a probed program may take to report its version
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Class tools expected to be linked from the class bin directory
+const CLASS_TOOLS: [&str; 2] = ["autotest", "fetch-activity"];
+
+/// Outcome of a single check, ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+
+    /// Exit code following the usual monitoring convention (0 ok, 1 warning, 2 critical)
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Result of one non-destructive check of the class environment
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    /// How long the check took, e.g. for a compiler to report its version
+    pub duration: Duration,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+/// Ways of reporting the checks
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    /// Prometheus text exposition format, as node_exporter's textfile collector reads
+    Prometheus,
+}
+
+/// Options of the healthcheck tool
+#[derive(Parser)]
+struct HealthcheckArgs {
+    /// Class to check, e.g. 1511
+    #[arg(long, value_name = "CODE")]
+    class: String,
+    /// How to report the checks
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Count warnings as failures
+    #[arg(long)]
+    strict: bool,
+    /// Also write the results as Prometheus metrics to PATH
+    #[arg(long, value_name = "PATH")]
+    textfile: Option<PathBuf>,
+}
+
+/// Run the healthcheck tool: `quicktool healthcheck --class CODE
+/// [--format text|json|prometheus] [--strict] [--textfile PATH]`. `--textfile` also writes
+/// the results as Prometheus metrics to PATH, for a node_exporter textfile collector.
+/// Returns the process exit code.
+pub fn run_healthcheck(args: &[String]) -> Result<i32, QuicktoolError> {
+    let HealthcheckArgs {
+        class: class_code,
+        format,
+        strict,
+        textfile,
+    } = cli::parse_tool_args("quicktool", "healthcheck", args)?;
+    let class_code = class_code.as_str();
+    let mut config = ClassConfig::new(class_code)
+        .ok_or_else(|| format!("{} is not a valid class", class_code))?;
+    config.strict = strict;
+
+    // Compilers such as dcc are often provided by the class bin directory
+    env::set_var(
+        "PATH",
+        config.get_path(&env::var("PATH").unwrap_or_default()),
+    );
+
+    let started = Instant::now();
+    let mut checks = run_checks(&mut config);
+    let elapsed = started.elapsed();
+    if strict {
+        for check in checks
+            .iter_mut()
+            .filter(|check| check.status == Status::Warn)
+        {
+            check.status = Status::Fail;
+        }
+    }
+    let status = checks
+        .iter()
+        .map(|check| check.status)
+        .max()
+        .unwrap_or(Status::Ok);
+
+    if let Some(path) = textfile {
+        write_textfile(&path, &metrics(&config.class, &checks, elapsed))?;
+    }
+
+    if format == Format::Prometheus {
+        print!("{}", metrics(&config.class, &checks, elapsed));
+    } else if format == Format::Json {
+        let checks: Vec<_> = checks
+            .iter()
+            .map(|check| {
+                json!({
+                    "name": check.name,
+                    "status": check.status.as_str(),
+                    "detail": check.detail,
+                })
+            })
+            .collect();
+        let report = json!({
+            "class": config.class,
+            "status": status.as_str(),
+            "checks": checks,
+        });
+        println!("{}", report);
+    } else {
+        for check in &checks {
+            let label = match check.status {
+                Status::Ok => "OK  ".green().bold(),
+                Status::Warn => "WARN".yellow().bold(),
+                Status::Fail => "FAIL".red().bold(),
+            };
+            println!("{} {:<22} {}", label, check.name, check.detail);
+        }
+    }
+
+    Ok(status.exit_code())
+}
+
+/// The checks as Prometheus metrics, labelled with the class so that one collector can
+/// read the files of every class on a machine
+fn metrics(class: &str, checks: &[Check], elapsed: Duration) -> String {
+    let class = escape_label(class);
+    let mut text = String::new();
+    let mut metric = |name: &str, help: &str, samples: Vec<(String, String)>| {
+        text.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} gauge\n",
+            name, help, name
+        ));
+        for (labels, value) in samples {
+            text.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+        }
+    };
+    let per_check = |value: &dyn Fn(&Check) -> String| {
+        checks
+            .iter()
+            .map(|check| {
+                (
+                    format!(
+                        "class=\"{}\",check=\"{}\"",
+                        class,
+                        escape_label(&check.name)
+                    ),
+                    value(check),
+                )
+            })
+            .collect()
+    };
+    let count = |status| {
+        vec![(
+            format!("class=\"{}\"", class),
+            checks
+                .iter()
+                .filter(|check| check.status == status)
+                .count()
+                .to_string(),
+        )]
+    };
+
+    metric(
+        "quicktool_healthcheck_status",
+        "Outcome of each healthcheck check: 0 ok, 1 warning, 2 failure.",
+        per_check(&|check| check.status.exit_code().to_string()),
+    );
+    metric(
+        "quicktool_healthcheck_check_duration_seconds",
+        "How long each healthcheck check took.",
+        per_check(&|check| format!("{:.6}", check.duration.as_secs_f64())),
+    );
+    metric(
+        "quicktool_healthcheck_failures",
+        "Number of healthcheck checks that failed.",
+        count(Status::Fail),
+    );
+    metric(
+        "quicktool_healthcheck_warnings",
+        "Number of healthcheck checks that warned.",
+        count(Status::Warn),
+    );
+    metric(
+        "quicktool_healthcheck_duration_seconds",
+        "How long the whole healthcheck took.",
+        vec![(
+            format!("class=\"{}\"", class),
+            format!("{:.6}", elapsed.as_secs_f64()),
+        )],
+    );
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    metric(
+        "quicktool_healthcheck_last_run_timestamp_seconds",
+        "When the healthcheck last ran, as a Unix time.",
+        vec![(format!("class=\"{}\"", class), now.to_string())],
+    );
+    metric(
+        "quicktool_build_info",
+        "The quicktool version that ran the healthcheck.",
+        vec![(
+            format!(
+                "class=\"{}\",version=\"{}\",commit=\"{}\"",
+                class,
+                escape_label(features::version()),
+                escape_label(features::git_commit())
+            ),
+            "1".to_string(),
+        )],
+    );
+    text
+}
+
+/// A label value with backslashes, quotes and newlines escaped
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Replace a textfile collector file all at once, so the collector never reads half of it
+fn write_textfile(path: &Path, text: &str) -> Result<(), String> {
+    let temporary = path.with_file_name(format!(
+        ".{}.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        process::id()
+    ));
+    fs::write(&temporary, text)
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            format!("Failed to write {}: {}", path.display(), e)
+        })
+}
+
+/// Run every non-destructive check of a class environment, timing each one
+pub fn run_checks(config: &mut ClassConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    // Class account mount
+    checks.push(timed(|| match &config.home_dir {
+        Some(home) if retry::exists(Path::new(home)) => {
+            Check::new("class home", Status::Ok, home.as_str())
+        }
+        Some(home) => Check::new(
+            "class home",
+            Status::Fail,
+            format!("{} is not mounted", home),
+        ),
+        None => Check::new(
+            "class home",
+            Status::Fail,
+            format!("no class account known for {}", config.class),
+        ),
+    }));
+
+    // Tool symlinks in the class bin directory
+    let bin_path = config.bin_path.clone().unwrap_or_default();
+    for tool in CLASS_TOOLS {
+        let link = Path::new(&bin_path).join(tool);
+        checks.push(timed(|| {
+            if fs::symlink_metadata(&link).is_err() {
+                Check::new(tool, Status::Fail, format!("{} is missing", link.display()))
+            } else {
+                match retry::retry_io(format!("resolving {}", link.display()), || {
+                    fs::canonicalize(&link)
+                }) {
+                    Ok(target) => Check::new(tool, Status::Ok, target.display().to_string()),
+                    Err(e) => Check::new(
+                        tool,
+                        Status::Fail,
+                        format!("{} is a dangling symlink: {}", link.display(), e),
+                    ),
+                }
+            }
+        }));
+    }
+
+    // Class config.sh
+    checks.push(timed(|| match config.load_class_config() {
+        Ok(()) => Check::new(
+            "config.sh",
+            Status::Ok,
+            format!("{} settings", config.custom_config.len()),
+        ),
+        Err(e) => Check::new("config.sh", Status::Fail, e.to_string()),
+    }));
+
+    // Requirements the class declares for quicktool itself
+    checks.push(timed(|| match features::check_compatibility(config) {
+        Ok(()) => Check::new("quicktool version", Status::Ok, features::version()),
+        Err(problem) => Check::new("quicktool version", Status::Fail, problem),
+    }));
+
+    // Interpreter and compilers
+    checks.push(timed(|| match probe_version("python3") {
+        Some(version) => Check::new("python3", Status::Ok, version),
+        None => Check::new("python3", Status::Fail, "python3 not found on PATH"),
+    }));
+    for compiler in ["dcc", "gcc", "clang"] {
+        checks.push(timed(|| match probe_version(compiler) {
+            Some(version) => match toolchain::mismatch(config, compiler) {
+                Ok(None) => Check::new(compiler, Status::Ok, version),
+                Ok(Some(problem)) | Err(problem) => Check::new(compiler, Status::Warn, problem),
+            },
+            None => Check::new(compiler, Status::Warn, "not found on PATH"),
+        }));
+    }
+
+    checks
+}
+
+/// Make a check, recording how long it took
+fn timed(check: impl FnOnce() -> Check) -> Check {
+    let started = Instant::now();
+    let mut check = check();
+    check.duration = started.elapsed();
+    check
+}
+
+/// Run `program --version` and return the first line it prints, giving up after a timeout
+pub fn probe_version(program: &str) -> Option<String> {
+    let mut child = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    loop {
+        match child.try_wait().ok()? {
+            Some(_) => break,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+
+    let output = child.wait_with_output().ok()?;
+    // Some tools print their version on stderr
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,113 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::metadata::FetchRecord;
+use crate::output::{self, Plumbing};
+use crate::temp::TempDir;
+use crate::tools::bug_report::{write_archive, write_file};
+use crate::tools::edit::primary_files;
+use crate::tools::fingerprint::Fingerprint;
+use crate::tools::run;
+
+/// Largest help-request archive, in KB, unless the class sets `help_request_max_kb`
+const DEFAULT_MAX_KB: u64 = 512;
+
+/// Run the help-request tool: bundle an exercise's code, its autotest output and
+/// environment details into a scrubbed archive for the course forum, e.g.
+/// `help-request activity`
+pub fn run_help_request(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let [activity] = args else {
+        return Err(QuicktoolError::Usage(format!(
+            "usage: {} help-request activity",
+            config.class
+        )));
+    };
+
+    let record = FetchRecord::load(Path::new("."), activity);
+    let files = primary_files(activity, record.as_ref());
+    if files.is_empty() {
+        return Err(format!("no files for '{}' in this directory", activity).into());
+    }
+
+    let staging = TempDir::new("help-request")?;
+    let archive = bundle(config, activity, &files, staging.path())?;
+
+    println!(
+        "Help request written to {}. Attach it to your forum post.",
+        archive.display()
+    );
+    Ok(())
+}
+
+/// Gather everything into `staging` and pack it, returning the archive path
+fn bundle(
+    config: &ClassConfig,
+    activity: &str,
+    files: &[String],
+    staging: &Path,
+) -> Result<PathBuf, String> {
+    let code_dir = staging.join("code");
+    fs::create_dir_all(&code_dir)
+        .map_err(|e| format!("Failed to create {}: {}", code_dir.display(), e))?;
+    for file in files {
+        let contents =
+            fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+        write_file(&code_dir.join(file), &contents)?;
+    }
+
+    // Run autotest again so the output matches the code being sent
+    println!("Running autotest for {}...", activity);
+    let exe = env::current_exe().map_err(|e| format!("Cannot find quicktool itself: {}", e))?;
+    let autotest_log = staging.join("autotest.txt");
+    let autotest = output::run(
+        Command::new(&exe).args([&config.class, "autotest", activity]),
+        &Plumbing {
+            log: Some(&autotest_log),
+            ..Plumbing::default()
+        },
+    )
+    .map_err(|e| format!("Failed to run autotest: {}", e))?;
+    let log = fs::read_to_string(&autotest_log).unwrap_or_default();
+    write_file(&autotest_log, &log)?;
+
+    let mut environment = format!(
+        "exercise: {}\nclass: {}\nautotest result: {}\n",
+        activity, config.class, autotest.status
+    );
+    environment.push_str(&Fingerprint::collect(config).to_text());
+    write_file(&staging.join("environment.txt"), &environment)?;
+
+    // A transcript recorded with `run --transcript` shows what the student saw
+    if let Some(transcript) = run::latest_transcript(activity) {
+        if let Ok(contents) = fs::read_to_string(&transcript) {
+            write_file(&staging.join("transcript.txt"), &contents)?;
+        }
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let archive = PathBuf::from(format!("help-request-{}-{}.tar.gz", activity, timestamp));
+    write_archive(staging, &archive)?;
+
+    let max_kb = config
+        .get_custom_config("help_request_max_kb")
+        .and_then(|kb| kb.parse().ok())
+        .unwrap_or(DEFAULT_MAX_KB);
+    let size_kb = fs::metadata(&archive).map(|m| m.len() / 1024).unwrap_or(0);
+    if size_kb > max_kb {
+        let _ = fs::remove_file(&archive);
+        return Err(format!(
+            "the help request would be {} KB, more than the {} KB the forum accepts; \
+             remove large files from this exercise and try again",
+            size_kb, max_kb
+        ));
+    }
+    Ok(archive)
+}
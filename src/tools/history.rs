@@ -0,0 +1,191 @@
+use clap::Parser;
+use colored::Colorize;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::raw::{c_int, c_long};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli;
+use crate::config;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::shell_quote;
+use crate::signals::{self, Work};
+use crate::state;
+use crate::user_config::UserConfig;
+
+/// File in the state directory that invocations are appended to
+const HISTORY_FILE: &str = "history.log";
+
+/// Set in the environment of the quicktool process whose exit is being recorded
+const RECORDING_VAR: &str = "QUICKTOOL_RECORDING_HISTORY";
+
+/// Entries `quicktool history` shows unless told otherwise
+const DEFAULT_COUNT: usize = 20;
+
+/// `struct tm`; the zone name pointer is kept as an address, as it is never read
+#[repr(C)]
+#[derive(Default)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: usize,
+}
+
+extern "C" {
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+}
+
+/// Whether the user keeps a history of what they run for the class, set with
+/// `history = true` in their config, or e.g. `COMP1511_history = true` for one class
+fn enabled(class: &str) -> bool {
+    UserConfig::load()
+        .class_setting(class, "history")
+        .is_some_and(|value| matches!(value, "1" | "yes" | "true"))
+}
+
+/// Record this invocation in the user's history, if they keep one: run quicktool again
+/// with the same arguments, append a line once it exits and return its exit code.
+/// Returns None in the process being recorded, or when the history is off, so the
+/// command then runs as usual.
+pub fn record_invocation(
+    class: &str,
+    program: &str,
+    class_code: &str,
+    args: &[String],
+) -> Option<i32> {
+    if env::var_os(RECORDING_VAR).is_some() {
+        // Commands this run starts, e.g. a class shell, record their own quicktool runs
+        env::remove_var(RECORDING_VAR);
+        return None;
+    }
+    if !enabled(class) {
+        return None;
+    }
+
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let mut command = Command::new(env::current_exe().ok()?);
+    if program == "quicktool" {
+        // The class may have been picked from a menu, so name it rather than pick again
+        command.arg0(program).arg(class_code);
+    } else {
+        command.arg0(program);
+    }
+    command.args(args).env(RECORDING_VAR, "1");
+    let status = signals::status(&mut command, Work::Interactive).ok()?;
+    let code = exit_codes::of_child(status);
+
+    // Failures are ignored, so the history never gets in the way of the command itself
+    let path = state::state_dir().join(HISTORY_FILE);
+    let _ = fs::create_dir_all(state::state_dir());
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(
+            file,
+            "{}\t{}\t{}\t{}",
+            local_time(started),
+            class,
+            code,
+            shell_quote::join(args)
+        );
+    }
+    Some(code)
+}
+
+/// Options of the history tool
+#[derive(Parser)]
+struct HistoryArgs {
+    /// Only show commands run for this class, e.g. 1511
+    #[arg(long, value_name = "CLASS")]
+    class: Option<String>,
+    /// How many of the most recent commands to show
+    #[arg(short = 'n', long, value_name = "COUNT", default_value_t = DEFAULT_COUNT)]
+    count: usize,
+}
+
+/// Run the history tool: `quicktool history [--class CLASS] [-n COUNT]` shows the most
+/// recent invocations, oldest first, with their exit codes. Returns the exit code.
+pub fn run_history(args: &[String]) -> Result<i32, QuicktoolError> {
+    let HistoryArgs { class, count } = cli::parse_tool_args("quicktool", "history", args)?;
+    let class =
+        match class {
+            Some(code) => Some(config::parse_class_code(&code).ok_or_else(|| {
+                format!("'{}' is not a class code; give a code such as 1511", code)
+            })?),
+            None => None,
+        };
+
+    let path = state::state_dir().join(HISTORY_FILE);
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let entries: Vec<Vec<&str>> = contents
+        .lines()
+        .map(|line| line.splitn(4, '\t').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 4)
+        .filter(|fields| class.as_deref().is_none_or(|class| fields[1] == class))
+        .collect();
+    if entries.is_empty() {
+        match &class {
+            Some(class) if !contents.is_empty() => {
+                println!("No commands recorded for {}", class)
+            }
+            _ => {
+                println!("No commands recorded in {}", path.display());
+                println!(
+                    "To record what you run, add {} under [user] in {}",
+                    "history = true".bold(),
+                    UserConfig::load().path.display()
+                );
+            }
+        }
+        return Ok(0);
+    }
+
+    for fields in &entries[entries.len().saturating_sub(count)..] {
+        let [time, class, code, command] = fields[..] else {
+            continue;
+        };
+        let code = if code == "0" {
+            format!("{:>3}", code).green()
+        } else {
+            format!("{:>3}", code).red()
+        };
+        let command = if command.is_empty() {
+            "(shell)"
+        } else {
+            command
+        };
+        println!("{}  {:<8}  {}  {}", time.dimmed(), class, code, command);
+    }
+    Ok(0)
+}
+
+/// A Unix time as local date and time, e.g. `2024-03-05 14:07:33`
+pub fn local_time(secs: i64) -> String {
+    let mut tm = Tm::default();
+    // SAFETY: both pointers are valid for the call and localtime_r is thread-safe
+    if unsafe { localtime_r(&secs, &mut tm) }.is_null() {
+        return secs.to_string();
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
@@ -0,0 +1,196 @@
+use std::env;
+
+use crate::cli::setup_environment;
+use crate::config::{self, ClassConfig};
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::shell_quote;
+use crate::tools::session_env;
+
+/// Variables the class environment sets, which activating a class exports into the
+/// user's shell and deactivating restores or removes
+const CLASS_VARIABLES: &[&str] = &[
+    "PATH",
+    "MANPATH",
+    "noclass_PATH",
+    "noclass_MANPATH",
+    "GIVECLASS",
+];
+
+/// Run the hook tool: print shell functions that make `quicktool <class>` switch the
+/// current shell into the class environment, as a subshell would but without one, and
+/// `quicktool deactivate` switch it back, e.g.
+/// `eval "$(quicktool hook bash)"` in ~/.bashrc
+pub fn run_hook(args: &[String]) -> Result<i32, QuicktoolError> {
+    let [shell] = args else {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool hook bash|zsh|fish".to_string(),
+        ));
+    };
+    match shell.as_str() {
+        "bash" | "zsh" => print!(
+            r#"quicktool() {{
+    if [ "$#" -eq 1 ] && [ "$1" = deactivate ]; then
+        quicktool_deactivate
+        return
+    fi
+    if [ "$#" -eq 1 ]; then
+        local quicktool_env
+        quicktool_env=$(PS1="$PS1" command quicktool __env {shell} "$1")
+        case $? in
+            0)
+                quicktool_deactivate
+                eval "$quicktool_env"
+                return
+                ;;
+            {usage}) ;;
+            *) return 1 ;;
+        esac
+    fi
+    command quicktool "$@"
+}}
+quicktool_deactivate() {{
+    [ -n "$QUICKTOOL_ACTIVE_CLASS" ] || return 0
+    PATH=$noclass_PATH
+    if [ -n "$noclass_MANPATH" ]; then
+        export MANPATH="$noclass_MANPATH"
+    else
+        unset MANPATH
+    fi
+    PS1=$noclass_PS1
+    local name
+    for name in {session_vars}; do
+        unset "$name"
+    done
+    unset noclass_PATH noclass_MANPATH noclass_PS1 GIVECLASS QUICKTOOL_ACTIVE_CLASS \
+        QUICKTOOL_SESSION_VARS
+}}
+"#,
+            shell = shell,
+            usage = exit_codes::USAGE,
+            // zsh does not split a variable into words unless told to
+            session_vars = if shell == "zsh" {
+                "${=QUICKTOOL_SESSION_VARS}"
+            } else {
+                "$QUICKTOOL_SESSION_VARS"
+            }
+        ),
+        "fish" => print!(
+            r#"function quicktool --wraps quicktool
+    if test (count $argv) -eq 1; and test "$argv[1]" = deactivate
+        quicktool_deactivate
+        return
+    end
+    if test (count $argv) -eq 1
+        set -l quicktool_env (command quicktool __env fish $argv[1])
+        switch $status
+            case 0
+                quicktool_deactivate
+                printf '%s\n' $quicktool_env | source
+                return
+            case {usage}
+            case '*'
+                return 1
+        end
+    end
+    command quicktool $argv
+end
+function quicktool_deactivate
+    set -q QUICKTOOL_ACTIVE_CLASS; or return 0
+    set -gx PATH $noclass_PATH
+    if test -n "$noclass_MANPATH"
+        set -gx MANPATH $noclass_MANPATH
+    else
+        set -e MANPATH
+    end
+    for name in (string split ' ' -- $QUICKTOOL_SESSION_VARS)
+        set -e $name
+    end
+    set -e noclass_PATH noclass_MANPATH GIVECLASS QUICKTOOL_ACTIVE_CLASS QUICKTOOL_SESSION_VARS
+end
+"#,
+            usage = exit_codes::USAGE
+        ),
+        other => {
+            return Err(QuicktoolError::Usage(format!(
+                "unknown shell {:?} (try bash, zsh or fish)",
+                other
+            )))
+        }
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Print the commands that put a shell into a class's environment, for the hook:
+/// `quicktool __env bash|zsh|fish CLASS`. Exits quietly with the usage code when CLASS
+/// is not a class code, so the hook runs quicktool as usual.
+pub fn run_env(args: &[String]) -> Result<i32, QuicktoolError> {
+    let [shell, class_code] = args else {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool __env bash|zsh|fish class".to_string(),
+        ));
+    };
+    if config::parse_class_code(class_code).is_none() {
+        return Ok(exit_codes::USAGE);
+    }
+    let Some(mut class_config) = ClassConfig::new(class_code) else {
+        return Ok(exit_codes::USAGE);
+    };
+    let _ = class_config.load_class_config();
+    setup_environment(&class_config);
+
+    let fish = match shell.as_str() {
+        "bash" | "zsh" => false,
+        "fish" => true,
+        other => {
+            return Err(QuicktoolError::Usage(format!(
+                "unknown shell {:?} (try bash, zsh or fish)",
+                other
+            )))
+        }
+    };
+    let set = |name: &str, value: &str| {
+        if fish && name.ends_with("PATH") {
+            // fish keeps paths as lists
+            println!("set -gx {} (string split : -- {})", name, fish_quote(value));
+        } else if fish {
+            println!("set -gx {} {}", name, fish_quote(value));
+        } else {
+            println!("export {}={}", name, shell_quote::quote(value));
+        }
+    };
+    for name in CLASS_VARIABLES {
+        if let Ok(value) = env::var(name) {
+            set(name, &value);
+        }
+    }
+    let session = session_env::variables(&class_config.class);
+    for (name, value) in &session {
+        set(name, value);
+    }
+    let names: Vec<&str> = session.iter().map(|(name, _)| name.as_str()).collect();
+    set("QUICKTOOL_SESSION_VARS", &names.join(" "));
+    set("QUICKTOOL_ACTIVE_CLASS", &class_config.class);
+
+    // fish has no PS1 and cannot source the class's bash .newclassrc
+    if !fish {
+        set("noclass_PS1", &env::var("noclass_PS1").unwrap_or_default());
+        println!(
+            "PS1={}",
+            shell_quote::quote(&env::var("PS1").unwrap_or_default())
+        );
+        if let Some(newclassrc) = class_config
+            .newclassrc_path
+            .as_ref()
+            .filter(|_| class_config.has_newclassrc())
+        {
+            println!(". {}", shell_quote::quote(newclassrc));
+        }
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Quote a value for fish, where only `\` and `'` are special inside single quotes
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
@@ -0,0 +1,109 @@
+use colored::*;
+use std::env;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::error::QuicktoolError;
+
+/// Run the install-symlinks tool: `quicktool install-symlinks <dir> [--force] [class...]`
+/// links each class code, e.g. `1511` or `cs1521`, to this quicktool binary in `dir`, so
+/// `1511 autotest lab01` works with `dir` on PATH. Without class codes, every class with
+/// an account on this machine is linked. Returns the exit code.
+pub fn run_install_symlinks(args: &[String]) -> Result<i32, QuicktoolError> {
+    let usage = || {
+        QuicktoolError::Usage(
+            "usage: quicktool install-symlinks <dir> [--force] [class...]".to_string(),
+        )
+    };
+    let force = args.iter().any(|arg| arg == "--force");
+    let mut args = args.iter().filter(|arg| *arg != "--force");
+    let dir = PathBuf::from(args.next().ok_or_else(usage)?);
+    let mut codes: Vec<String> = args.cloned().collect();
+    if codes.is_empty() {
+        codes = config::installed_classes();
+        if codes.is_empty() {
+            return Err(format!(
+                "no class accounts found in {}; name the classes to link",
+                config::class_root().display()
+            )
+            .into());
+        }
+    }
+    if let Some(code) = codes
+        .iter()
+        .find(|code| config::parse_class_code(code).is_none())
+    {
+        return Err(format!(
+            "'{}' is not a class code; give codes such as 1511, cs1521 or SENG2011",
+            code
+        )
+        .into());
+    }
+
+    let binary = env::current_exe()
+        .and_then(fs::canonicalize)
+        .map_err(|e| format!("Cannot find the quicktool binary: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut failed = 0;
+    for code in &codes {
+        let link = dir.join(code);
+        match install(&link, &binary, force) {
+            Ok(Installed::Created) => println!("{} {}", "Linked".green().bold(), link.display()),
+            Ok(Installed::Already) => println!("{} {}", "Already linked".dimmed(), link.display()),
+            Err(e) => {
+                println!("{} {}: {}", "Skipped".red().bold(), link.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    let on_path = env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|entry| same_dir(&entry, &dir)));
+    if !on_path {
+        println!(
+            "{} is not on your PATH; add it to run the classes by name, e.g. {}",
+            dir.display(),
+            format!("export PATH=\"{}:$PATH\"", dir.display()).bold()
+        );
+    }
+    Ok(if failed == 0 { 0 } else { 1 })
+}
+
+/// What installing one link did
+enum Installed {
+    Created,
+    Already,
+}
+
+/// Link `link` to `binary`, replacing an existing symlink elsewhere only with `force`.
+/// Files that are not symlinks are never replaced.
+fn install(link: &Path, binary: &Path, force: bool) -> Result<Installed, String> {
+    if let Ok(metadata) = fs::symlink_metadata(link) {
+        if !metadata.file_type().is_symlink() {
+            return Err("a file that is not a symlink is in the way".to_string());
+        }
+        if fs::canonicalize(link).is_ok_and(|target| target == binary) {
+            return Ok(Installed::Already);
+        }
+        if !force {
+            let target = fs::read_link(link)
+                .map(|target| target.display().to_string())
+                .unwrap_or_default();
+            return Err(format!("it links to {} (--force replaces it)", target));
+        }
+        fs::remove_file(link).map_err(|e| format!("cannot remove it: {}", e))?;
+    }
+    symlink(binary, link).map_err(|e| e.to_string())?;
+    Ok(Installed::Created)
+}
+
+/// Whether two paths name the same directory
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
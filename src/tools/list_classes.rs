@@ -0,0 +1,55 @@
+use colored::*;
+use std::path::Path;
+
+use crate::config::{self, ClassConfig};
+use crate::error::QuicktoolError;
+
+/// Class tools quicktool builds on, as linked from a class's bin directory
+const QUICKTOOL_TOOLS: [&str; 3] = ["autotest", "autotest-stage", "fetch-activity"];
+
+/// Run the list-classes tool: print every class with an account on this machine and the
+/// quicktool-compatible tools in its bin directory
+pub fn run_list_classes(args: &[String]) -> Result<i32, QuicktoolError> {
+    if !args.is_empty() {
+        return Err(QuicktoolError::Usage(
+            "usage: quicktool list-classes".to_string(),
+        ));
+    }
+
+    let classes = config::installed_classes();
+    if classes.is_empty() {
+        println!(
+            "No class accounts found in {}",
+            config::class_root().display()
+        );
+        return Ok(1);
+    }
+
+    for code in classes {
+        let Some(config) = ClassConfig::new(&code) else {
+            continue;
+        };
+        let bin_path = config.bin_path.clone().unwrap_or_default();
+        let tools: Vec<&str> = QUICKTOOL_TOOLS
+            .into_iter()
+            .filter(|tool| Path::new(&bin_path).join(tool).exists())
+            .collect();
+        let account = config.account_name.as_deref().unwrap_or("");
+        if tools.is_empty() {
+            println!(
+                "{:<10} {:<10} {}",
+                code,
+                account,
+                "no quicktool tools".dimmed()
+            );
+        } else {
+            println!(
+                "{:<10} {:<10} {}",
+                code.green().bold(),
+                account,
+                tools.join(", ")
+            );
+        }
+    }
+    Ok(0)
+}
@@ -1,2 +1,35 @@
 pub mod autotest;
-pub mod fetch_activity;
\ No newline at end of file
+pub mod autotest_backend;
+pub mod autotest_parameters;
+pub mod bench;
+pub mod bug_report;
+pub mod clean;
+pub mod command_help;
+pub mod compare;
+pub mod completions;
+pub mod config_show;
+pub mod deploy_docs;
+pub mod doctor;
+pub mod du;
+pub mod edit;
+pub mod fetch_activity;
+pub mod fingerprint;
+pub mod forum_report;
+pub mod fuzz;
+pub mod healthcheck;
+pub mod help_request;
+pub mod history;
+pub mod hook;
+pub mod install_symlinks;
+pub mod list_classes;
+pub mod multi_class;
+pub mod new_file;
+pub mod progress;
+pub mod run;
+pub mod session_env;
+pub mod stats;
+pub mod student;
+pub mod test_all;
+pub mod unit_tests;
+pub mod version;
+pub mod which;
\ No newline at end of file
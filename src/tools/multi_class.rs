@@ -0,0 +1,77 @@
+use colored::*;
+use std::env;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::Command;
+
+use crate::config;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::signals::{self, Work};
+
+/// Run the same command for several classes, one after another, e.g.
+/// `quicktool --classes 1511,1521 -- gcc --version`. Each class runs as
+/// `quicktool <class> ...` would, in its own environment, under a header naming it.
+/// Returns 0 if the command succeeded for every class, or 1.
+pub fn run_multi_class(args: &[String]) -> Result<i32, QuicktoolError> {
+    let usage = || {
+        QuicktoolError::Usage(
+            "usage: quicktool --classes class,class... [options] [command]".to_string(),
+        )
+    };
+    let (list, rest) = args.split_first().ok_or_else(usage)?;
+    let classes: Vec<&str> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .collect();
+    if classes.is_empty() || rest.is_empty() {
+        return Err(usage());
+    }
+    if let Some(code) = classes
+        .iter()
+        .find(|code| config::parse_class_code(code).is_none())
+    {
+        return Err(format!("'{}' is not a class code; give codes such as 1511", code).into());
+    }
+
+    let program = env::current_exe().map_err(|e| format!("cannot find quicktool: {}", e))?;
+    let mut failed = Vec::new();
+    for (i, code) in classes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", format!("== {} ==", code).bold());
+        let mut command = Command::new(&program);
+        command.arg0("quicktool").arg(code).args(rest);
+        let status = signals::status(&mut command, Work::Interactive)
+            .map_err(|e| format!("cannot run quicktool for {}: {}", code, e))?;
+        if status.signal() == Some(signals::SIGINT) {
+            // Ctrl-C stops the whole run, not just this class
+            return Ok(exit_codes::of_child(status));
+        }
+        if !status.success() {
+            failed.push(format!("{} ({})", code, exit_codes::of_child(status)));
+        }
+    }
+
+    println!();
+    if failed.is_empty() {
+        println!(
+            "{}",
+            format!("Succeeded for all {} classes", classes.len()).green()
+        );
+        Ok(exit_codes::SUCCESS)
+    } else {
+        println!(
+            "{}",
+            format!(
+                "Failed for {} of {} classes: {}",
+                failed.len(),
+                classes.len(),
+                failed.join(", ")
+            )
+            .red()
+        );
+        Ok(exit_codes::FAILURE)
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{self, ClassConfig};
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::state;
+use crate::tools::history;
+use crate::trace;
+use crate::user_config::UserConfig;
+
+/// Template used for a new C file unless the class or the user provides one
+const C_TEMPLATE: &str = "\
+// {{file}}
+// Written by {{name}} ({{zid}}) on {{date}}
+//
+// TODO: describe what this program does
+
+#include <stdio.h>
+
+int main(void) {
+
+    return 0;
+}
+";
+
+/// Template used for a new C header unless the class or the user provides one
+const H_TEMPLATE: &str = "\
+// {{file}}
+// Written by {{name}} ({{zid}}) on {{date}}
+
+#ifndef {{guard}}
+#define {{guard}}
+
+#endif
+";
+
+/// Run the new tool: create source files from the course templates with the header
+/// filled in, e.g. `new hello.c` or `new list.h list.c`. `--force` replaces files that
+/// already exist.
+pub fn run_new(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let force = args.iter().any(|arg| arg == "--force");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--force").collect();
+    if files.is_empty() {
+        return Err(QuicktoolError::Usage(format!(
+            "usage: {} new file... [--force]",
+            config.class
+        )));
+    }
+
+    let user_config = UserConfig::load();
+    // Left for the student to fill in when they cannot be worked out
+    let name = user_config
+        .class_setting(&config.class, "name")
+        .map(str::to_string)
+        .or_else(full_name)
+        .unwrap_or_else(|| "YOUR NAME".to_string());
+    let zid = user_config
+        .class_setting(&config.class, "zid")
+        .map(str::to_string)
+        .or_else(config::current_user)
+        .unwrap_or_else(|| "YOUR ZID".to_string());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let date = history::local_time(now)[..10].to_string();
+
+    // Check every file first, so nothing is half done
+    if let Some(existing) = files.iter().find(|file| !force && Path::new(file).exists()) {
+        return Err(format!("{} already exists (--force replaces it)", existing).into());
+    }
+
+    for file in files {
+        let path = Path::new(file);
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (template, source) = find_template(config, &extension).ok_or_else(|| {
+            format!(
+                "no template for {} files; add one as template.{} in {}",
+                if extension.is_empty() {
+                    "extensionless".to_string()
+                } else {
+                    format!(".{}", extension)
+                },
+                extension,
+                user_template_dir(config).display()
+            )
+        })?;
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let contents = template
+            .replace("{{file}}", &file_name)
+            .replace("{{name}}", &name)
+            .replace("{{zid}}", &zid)
+            .replace("{{date}}", &date)
+            .replace("{{class}}", &config.class)
+            .replace("{{guard}}", &include_guard(&file_name));
+
+        if config.dry_run {
+            dry_run::report(&format!("would create {} from {}", path.display(), source));
+            continue;
+        }
+        fs::write(path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        println!("Created {} from {}", path.display(), source);
+    }
+    Ok(())
+}
+
+/// The template for files with an extension and where it came from: the user's own for
+/// the class or for every class, then the class's, then quicktool's built-in one
+fn find_template(config: &ClassConfig, extension: &str) -> Option<(String, String)> {
+    let file_name = format!("template.{}", extension);
+    let mut dirs = vec![
+        user_template_dir(config),
+        state::config_dir().join("templates"),
+    ];
+    dirs.extend(class_template_dir(config));
+    for dir in dirs {
+        let path = dir.join(&file_name);
+        if let Ok(template) = fs::read_to_string(&path) {
+            return Some((template, path.display().to_string()));
+        }
+    }
+    let built_in = match extension {
+        "c" => C_TEMPLATE,
+        "h" => H_TEMPLATE,
+        _ => return None,
+    };
+    Some((built_in.to_string(), "the built-in template".to_string()))
+}
+
+/// Where the user keeps their own templates for the class
+fn user_template_dir(config: &ClassConfig) -> PathBuf {
+    state::config_dir().join("templates").join(&config.class)
+}
+
+/// Where the class keeps its templates: `templates_directory` in config.sh, or else
+/// `templates` in the class account
+fn class_template_dir(config: &ClassConfig) -> Option<PathBuf> {
+    match config.get_custom_config("templates_directory") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => Some(Path::new(config.home_dir.as_deref()?).join("templates")),
+    }
+}
+
+/// The user's full name from the password database, as `finger` shows it
+fn full_name() -> Option<String> {
+    let user = config::current_user()?;
+    let mut command = Command::new("getent");
+    command.arg("passwd").arg(&user);
+    trace::command(&command);
+    let output = command.output().ok()?;
+    let entry = String::from_utf8_lossy(&output.stdout).to_string();
+    let gecos = entry.trim_end().split(':').nth(4)?;
+    let name = gecos.split(',').next()?.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The macro guarding a header against being included twice, e.g. `LIST_H` for list.h
+fn include_guard(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,120 @@
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::metadata::{AutotestHistory, FetchRecord, METADATA_DIR};
+
+/// Progress on one exercise in the working directory
+struct Progress {
+    activity: String,
+    fetched_at: Option<u64>,
+    history: AutotestHistory,
+}
+
+impl Progress {
+    /// Fraction of autotest runs that passed, if autotest was run at all
+    fn pass_rate(&self) -> Option<f64> {
+        (self.history.runs > 0).then(|| self.history.passes as f64 / self.history.runs as f64)
+    }
+}
+
+/// Run the progress tool: export which exercises were fetched and how autotest went on
+/// them in the working directory, e.g. `progress [--format text|json|csv]`
+pub fn run_progress(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let format = match args {
+        [] => "text",
+        [flag, format] if flag == "--format" => format.as_str(),
+        _ => {
+            return Err(QuicktoolError::Usage(format!(
+                "usage: {} progress [--format text|json|csv]",
+                config.class
+            )))
+        }
+    };
+
+    let progress = collect_progress(Path::new("."));
+    match format {
+        "text" => {
+            if progress.is_empty() {
+                println!("No exercises fetched or tested in this directory.");
+            }
+            for exercise in &progress {
+                let result = if exercise.history.passed() {
+                    "passed".to_string()
+                } else if exercise.history.runs > 0 {
+                    format!("not passed yet ({} runs)", exercise.history.runs)
+                } else {
+                    "not tested".to_string()
+                };
+                println!("  {:<24} {}", exercise.activity, result);
+            }
+        }
+        "json" => {
+            let exercises: Vec<_> = progress
+                .iter()
+                .map(|exercise| {
+                    json!({
+                        "activity": exercise.activity,
+                        "fetched_at": exercise.fetched_at,
+                        "autotest_runs": exercise.history.runs,
+                        "autotest_passes": exercise.history.passes,
+                        "pass_rate": exercise.pass_rate(),
+                        "last_passed_at": exercise.history.last_passed_at,
+                    })
+                })
+                .collect();
+            let report = json!({
+                "class": config.class,
+                "exercises": exercises,
+            });
+            println!("{}", report);
+        }
+        "csv" => {
+            println!("activity,fetched_at,autotest_runs,autotest_passes,pass_rate,last_passed_at");
+            let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+            for exercise in &progress {
+                println!(
+                    "{},{},{},{},{},{}",
+                    exercise.activity,
+                    optional(exercise.fetched_at),
+                    exercise.history.runs,
+                    exercise.history.passes,
+                    exercise
+                        .pass_rate()
+                        .map(|rate| format!("{:.2}", rate))
+                        .unwrap_or_default(),
+                    optional(exercise.history.last_passed_at)
+                );
+            }
+        }
+        other => return Err(QuicktoolError::Usage(format!("unknown format {:?}", other))),
+    }
+    Ok(())
+}
+
+/// Every exercise that was fetched or autotested in `dir`, by name
+fn collect_progress(dir: &Path) -> Vec<Progress> {
+    let Ok(entries) = fs::read_dir(dir.join(METADATA_DIR)) else {
+        return Vec::new();
+    };
+    let mut progress: Vec<Progress> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let activity = entry.file_name().to_string_lossy().to_string();
+            let record = FetchRecord::load(dir, &activity);
+            let history = AutotestHistory::load(dir, &activity);
+            if record.is_none() && history.runs == 0 {
+                return None;
+            }
+            Some(Progress {
+                fetched_at: record.map(|record| record.fetched_at),
+                activity,
+                history,
+            })
+        })
+        .collect();
+    progress.sort_by(|a, b| a.activity.cmp(&b.activity));
+    progress
+}
@@ -0,0 +1,124 @@
+// Assertion-style unit tests run by quicktool's autotest.
+//
+// Staff write test_*.c files in an activity's unit_tests directory:
+//
+//     #include "quicktool_test.h"
+//     #include "list.h"
+//
+//     TEST(insert_into_empty_list) {
+//         struct node *head = insert(NULL, 3);
+//         CHECK(head != NULL);
+//         CHECK_INT_EQ(head->data, 3);
+//     }
+//
+// quicktool compiles each file with the student's code, renaming the student's main,
+// and runs every test in its own process so a crash fails only that test.
+
+#ifndef QUICKTOOL_TEST_H
+#define QUICKTOOL_TEST_H
+
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define QUICKTOOL_MAX_TESTS 256
+#define QUICKTOOL_TIMEOUT_SECONDS 10
+
+static struct {
+    const char *name;
+    void (*run)(void);
+} quicktool_tests[QUICKTOOL_MAX_TESTS];
+static int quicktool_test_count;
+
+static void quicktool_register(const char *name, void (*run)(void)) {
+    if (quicktool_test_count < QUICKTOOL_MAX_TESTS) {
+        quicktool_tests[quicktool_test_count].name = name;
+        quicktool_tests[quicktool_test_count].run = run;
+        quicktool_test_count++;
+    }
+}
+
+// Report why the running test failed and end it
+static void quicktool_fail(const char *file, int line, const char *message) {
+    printf("\nquicktool-detail %s:%d: %s\n", file, line, message);
+    fflush(stdout);
+    exit(1);
+}
+
+#define TEST(name)                                                          \
+    static void name(void);                                                 \
+    __attribute__((constructor)) static void quicktool_register_##name(void) { \
+        quicktool_register(#name, name);                                    \
+    }                                                                       \
+    static void name(void)
+
+#define CHECK(condition)                                                    \
+    do {                                                                    \
+        if (!(condition)) {                                                 \
+            quicktool_fail(__FILE__, __LINE__, "CHECK(" #condition ") failed"); \
+        }                                                                   \
+    } while (0)
+
+#define CHECK_INT_EQ(actual, expected)                                      \
+    do {                                                                    \
+        long long quicktool_actual = (actual);                              \
+        long long quicktool_expected = (expected);                          \
+        if (quicktool_actual != quicktool_expected) {                       \
+            char quicktool_message[256];                                    \
+            snprintf(quicktool_message, sizeof quicktool_message,           \
+                     "%s is %lld, expected %lld", #actual, quicktool_actual, \
+                     quicktool_expected);                                   \
+            quicktool_fail(__FILE__, __LINE__, quicktool_message);          \
+        }                                                                   \
+    } while (0)
+
+#define CHECK_STR_EQ(actual, expected)                                      \
+    do {                                                                    \
+        const char *quicktool_actual = (actual);                            \
+        const char *quicktool_expected = (expected);                        \
+        if (quicktool_actual == NULL ||                                     \
+            strcmp(quicktool_actual, quicktool_expected) != 0) {            \
+            char quicktool_message[512];                                    \
+            snprintf(quicktool_message, sizeof quicktool_message,           \
+                     "%s is \"%.200s\", expected \"%.200s\"", #actual,      \
+                     quicktool_actual ? quicktool_actual : "(null)",        \
+                     quicktool_expected);                                   \
+            quicktool_fail(__FILE__, __LINE__, quicktool_message);          \
+        }                                                                   \
+    } while (0)
+
+// The student's main is renamed on the command line; this one runs the tests
+#undef main
+int main(void) {
+    for (int i = 0; i < quicktool_test_count; i++) {
+        fflush(stdout);
+        pid_t pid = fork();
+        if (pid == 0) {
+            alarm(QUICKTOOL_TIMEOUT_SECONDS);
+            quicktool_tests[i].run();
+            fflush(stdout);
+            exit(0);
+        }
+
+        int status = 0;
+        if (pid < 0 || waitpid(pid, &status, 0) < 0) {
+            printf("\nquicktool-test %s failed could not run the test\n", quicktool_tests[i].name);
+        } else if (WIFEXITED(status) && WEXITSTATUS(status) == 0) {
+            printf("\nquicktool-test %s passed\n", quicktool_tests[i].name);
+        } else if (WIFSIGNALED(status) && WTERMSIG(status) == SIGALRM) {
+            printf("\nquicktool-test %s failed took longer than %d seconds\n",
+                   quicktool_tests[i].name, QUICKTOOL_TIMEOUT_SECONDS);
+        } else if (WIFSIGNALED(status)) {
+            printf("\nquicktool-test %s failed crashed (%s)\n", quicktool_tests[i].name,
+                   strsignal(WTERMSIG(status)));
+        } else {
+            printf("\nquicktool-test %s failed\n", quicktool_tests[i].name);
+        }
+    }
+    return 0;
+}
+
+#endif
@@ -0,0 +1,376 @@
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::crash;
+use crate::diff::{self, DiffStyle, Match, Tolerance};
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{FetchRecord, METADATA_DIR};
+use crate::output::{self, Plumbing, Stream};
+use crate::shell_quote;
+use crate::tools::fetch_activity;
+use crate::trace;
+
+/// Text shown after the options in `run --help` and `compare --help`
+pub const PROGRAM_ARGS_HELP: &str = "Other arguments are passed to the program; put them \
+                                 after -- to pass it options such as --help.";
+
+/// Options of the run tool
+#[derive(Parser)]
+#[command(after_help = PROGRAM_ARGS_HELP)]
+struct RunArgs {
+    /// Give the program this file as its input
+    #[arg(long, value_name = "FILE")]
+    stdin: Option<String>,
+    /// Compare the program's output with this file
+    #[arg(long, value_name = "FILE")]
+    expect: Option<String>,
+    /// Accept output that differs only in line endings or trailing whitespace
+    #[arg(long)]
+    normalize_eol: bool,
+    /// Show whitespace and line endings in the diff
+    #[arg(long)]
+    show_invisible: bool,
+    /// Show differing non-ASCII characters as codepoints
+    #[arg(long)]
+    show_codepoints: bool,
+    /// How far numbers may differ from the expected ones
+    #[arg(long, value_name = "N")]
+    tolerance: Option<f64>,
+    /// How far numbers may differ, as a fraction of the larger one
+    #[arg(long, value_name = "N")]
+    relative_tolerance: Option<f64>,
+    /// Record a transcript of an interactive run instead
+    #[arg(long)]
+    transcript: bool,
+    /// The program and its arguments
+    #[arg(required = true)]
+    command: Vec<String>,
+}
+
+/// The options of run, for documentation
+pub fn command() -> clap::Command {
+    RunArgs::command()
+}
+
+/// Run the run tool: execute a program with input from a file and optionally compare its
+/// output with the expected output, e.g.
+/// `run ./prog --stdin tests/input1.txt --expect tests/output1.txt`, or record a
+/// transcript of an interactive run with `run ./prog --transcript`.
+///
+/// `--normalize-eol` accepts output that differs only in line endings or trailing
+/// whitespace, and `--show-invisible` shows those characters in the diff, while
+/// `--show-codepoints` shows differing non-ASCII characters as codepoints. Numbers may
+/// differ by `--tolerance` or `--relative-tolerance`, or else by the tolerance the
+/// fetched activity's manifest sets for the test named after the expected output file.
+pub fn run_program(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let RunArgs {
+        stdin: stdin_file,
+        expect: expect_file,
+        normalize_eol,
+        show_invisible,
+        show_codepoints,
+        tolerance: absolute_tolerance,
+        relative_tolerance,
+        transcript,
+        command: program_args,
+    } = cli::parse_passthrough_args(&config.class, "run", args)?;
+    let (program, program_args) = program_args
+        .split_first()
+        .expect("clap requires the program");
+    let stdin_file = stdin_file.as_deref();
+    let expect_file = expect_file.as_deref();
+    if transcript {
+        if stdin_file.is_some() || expect_file.is_some() {
+            return Err(QuicktoolError::Usage(
+                "--transcript records what you type, so it cannot be used with \
+                 --stdin or --expect"
+                    .to_string(),
+            ));
+        }
+        return record_transcript(config, program, program_args);
+    }
+
+    let mut command = Command::new(program);
+    command.args(program_args);
+    if let Some(path) = stdin_file {
+        let input = File::open(path).map_err(|e| format!("Cannot open input {}: {}", path, e))?;
+        command.stdin(Stdio::from(input));
+    }
+    trace::command(&command);
+
+    let Some(expect_file) = expect_file else {
+        // Nothing to compare, so let the program write to the terminal directly
+        let output = run_explaining_crash(config, program, &mut command, Stream::Inherit)?;
+        if !output.status.success() {
+            exit(exit_codes::of_child(output.status));
+        }
+        return Ok(());
+    };
+
+    let expected = fs::read_to_string(expect_file)
+        .map_err(|e| format!("Cannot read expected output {}: {}", expect_file, e))?;
+    let output = run_explaining_crash(config, program, &mut command, Stream::Capture)?;
+    let status = output.status;
+    let actual = String::from_utf8_lossy(&output.stdout);
+
+    if !status.success() {
+        println!("{} exited with {}", program, status);
+    }
+
+    let mut tolerance = manifest_tolerance(config, expect_file);
+    tolerance.absolute = absolute_tolerance.unwrap_or(tolerance.absolute);
+    tolerance.relative = relative_tolerance.unwrap_or(tolerance.relative);
+    let outcome = diff::compare_output(&expected, &actual, tolerance);
+    match outcome {
+        Match::Exact => println!("{}", "Output matches the expected output.".green().bold()),
+        Match::WithinTolerance => println!(
+            "{} (its numbers are within {} of the expected ones)",
+            "Output passes within tolerance.".yellow().bold(),
+            tolerance
+        ),
+        Match::IgnoringWhitespace if normalize_eol => println!(
+            "{} (it differs only in line endings or trailing whitespace)",
+            "Output passes ignoring whitespace.".yellow().bold()
+        ),
+        Match::IgnoringWhitespace | Match::Different => {
+            let whitespace_only = outcome == Match::IgnoringWhitespace;
+            if whitespace_only {
+                println!(
+                    "{}",
+                    "Output differs from the expected output only in invisible whitespace:"
+                        .red()
+                        .bold()
+                );
+            } else {
+                println!(
+                    "{}",
+                    "Output differs from the expected output:".red().bold()
+                );
+            }
+            // Whitespace-only differences look identical unless they are shown
+            let show_invisible = show_invisible || whitespace_only;
+            if let Some(difference) = diff::unified_diff_with(
+                &format!("{} (expected)", expect_file),
+                &format!("{} (your output)", program),
+                &expected,
+                &actual,
+                DiffStyle {
+                    show_invisible,
+                    show_codepoints,
+                },
+            ) {
+                diff::print_diff(&difference);
+            }
+            if show_invisible {
+                println!("{}", diff::INVISIBLE_LEGEND.dimmed());
+            }
+            if !show_codepoints && (!expected.is_ascii() || !actual.is_ascii()) {
+                println!("{}", diff::CODEPOINTS_HINT.dimmed());
+            }
+            if whitespace_only {
+                println!("(--normalize-eol accepts this, reported as passing ignoring whitespace)");
+            }
+            exit(exit_codes::FAILURE);
+        }
+    }
+    if !status.success() {
+        exit(exit_codes::of_child(status));
+    }
+    Ok(())
+}
+
+/// The numeric tolerance the most recently fetched activity's manifest sets for a test,
+/// named after its expected output file, e.g. `float_tolerance_output1`
+fn manifest_tolerance(config: &mut ClassConfig, expect_file: &str) -> Tolerance {
+    let Some(record) = FetchRecord::list(Path::new(".")).pop() else {
+        return Tolerance::default();
+    };
+    let Some(manifest) = fetch_activity::locate_activity(config, &record.activity)
+        .ok()
+        .and_then(|dir| ActivityManifest::load(&dir))
+    else {
+        return Tolerance::default();
+    };
+    let test = Path::new(expect_file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string());
+    Tolerance::from_manifest(&manifest, test.as_deref())
+}
+
+/// Run the program, passing its stderr through to the terminal while keeping it to
+/// explain what went wrong if the program crashed
+fn run_explaining_crash(
+    config: &ClassConfig,
+    program: &str,
+    command: &mut Command,
+    stdout: Stream,
+) -> Result<Output, String> {
+    let plumbing = Plumbing {
+        stdout,
+        ..Plumbing::default()
+    };
+    let output =
+        output::run(command, &plumbing).map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        crash::explain(
+            config,
+            output.status,
+            &String::from_utf8_lossy(&output.stderr),
+        )?;
+    }
+    Ok(output)
+}
+
+/// Run the program on a pseudo-terminal through `script`, saving what appeared on the
+/// screen, with what was typed echoed in place, and the typed input on its own
+fn record_transcript(
+    config: &ClassConfig,
+    program: &str,
+    program_args: &[String],
+) -> Result<(), QuicktoolError> {
+    let script = config.paths().tool("script").map_err(|_| {
+        QuicktoolError::ToolNotFound(
+            "recording a transcript needs the script program from util-linux, \
+             which is not installed"
+                .to_string(),
+        )
+    })?;
+    let dir = transcript_dir(program)?;
+    let raw_output = dir.join("output.raw");
+    let raw_input = dir.join("input.raw");
+
+    let mut words = vec![program.to_string()];
+    words.extend(program_args.iter().map(|arg| arg.to_string()));
+    let command_line = shell_quote::join(&words);
+    let mut command = Command::new(script);
+    command
+        .args(["--quiet", "--return", "--echo", "always"])
+        .arg("--log-out")
+        .arg(&raw_output)
+        .arg("--log-in")
+        .arg(&raw_input)
+        .arg("--command")
+        .arg(&command_line);
+    trace::command(&command);
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    let code = status.code().unwrap_or(1);
+
+    let screen = script_log(&raw_output);
+    let typed = script_log(&raw_input);
+    let _ = fs::remove_file(&raw_output);
+    let _ = fs::remove_file(&raw_input);
+    let transcript = format!("$ {}\n{}[exit status {}]\n", command_line, screen, code);
+    let write = |name: &str, contents: &str| {
+        fs::write(dir.join(name), contents)
+            .map_err(|e| format!("Failed to save {}: {}", dir.join(name).display(), e))
+    };
+    write("transcript.txt", &transcript)?;
+    write("input.txt", &typed)?;
+
+    println!(
+        "Transcript saved to {}",
+        dir.join("transcript.txt").display()
+    );
+    println!(
+        "Replay the same input with: {} run {} --stdin {}",
+        config.class,
+        command_line,
+        dir.join("input.txt").display()
+    );
+    if code != 0 {
+        exit(code);
+    }
+    Ok(())
+}
+
+/// A new directory for a transcript, under the most recently fetched activity or else
+/// the program's name, e.g. `.quicktool/lab01/transcripts/1700000000`, or
+/// `1700000000-1` for a second transcript in the same second
+fn transcript_dir(program: &str) -> Result<PathBuf, String> {
+    let activity = FetchRecord::list(Path::new("."))
+        .pop()
+        .map(|record| record.activity)
+        .or_else(|| {
+            Path::new(program)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let transcripts = Path::new(METADATA_DIR).join(activity).join("transcripts");
+    fs::create_dir_all(&transcripts)
+        .map_err(|e| format!("Failed to create {}: {}", transcripts.display(), e))?;
+    for attempt in 0.. {
+        let name = match attempt {
+            0 => timestamp.to_string(),
+            n => format!("{}-{}", timestamp, n),
+        };
+        let dir = transcripts.join(name);
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {}: {}", dir.display(), e)),
+        }
+    }
+    unreachable!("some attempt finds a free name")
+}
+
+/// When a transcript directory was made, from its name, for ordering them
+fn transcript_order(name: &str) -> Option<(u64, u64)> {
+    let (secs, attempt) = name.split_once('-').unwrap_or((name, "0"));
+    Some((secs.parse().ok()?, attempt.parse().ok()?))
+}
+
+/// The text of a `script` log without the lines `script` adds around it and with the
+/// terminal's carriage returns removed
+fn script_log(path: &Path) -> String {
+    let log = fs::read(path).unwrap_or_default();
+    let log = String::from_utf8_lossy(&log).replace("\r\n", "\n");
+    let mut lines: Vec<&str> = log.split_inclusive('\n').collect();
+    if lines
+        .first()
+        .is_some_and(|line| line.starts_with("Script started"))
+    {
+        lines.remove(0);
+    }
+    if lines
+        .last()
+        .is_some_and(|line| line.starts_with("Script done"))
+    {
+        lines.pop();
+        // script separates its closing line with an empty one
+        if lines.last() == Some(&"\n") {
+            lines.pop();
+        }
+    }
+    lines.concat()
+}
+
+/// The most recent transcript recorded for an activity, if any
+pub fn latest_transcript(activity: &str) -> Option<PathBuf> {
+    let dir = Path::new(METADATA_DIR).join(activity).join("transcripts");
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let order = transcript_order(&entry.file_name().to_string_lossy())?;
+            Some((order, entry.path().join("transcript.txt")))
+        })
+        .filter(|(_, path)| path.is_file())
+        .max()
+        .map(|(_, path)| path)
+}
@@ -0,0 +1,253 @@
+use colored::Colorize;
+use serde_json::{json, Map, Value};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ClassConfig;
+use crate::dry_run;
+use crate::error::QuicktoolError;
+use crate::state;
+
+/// File in the state directory holding each class's session variables
+const SESSION_ENV_FILE: &str = "session-env.json";
+
+/// Seconds a variable lasts unless `--for` says otherwise, about a day's work
+const DEFAULT_LIFETIME: u64 = 12 * 60 * 60;
+
+/// Variables quicktool sets up for the class itself, which setenv cannot override
+const RESERVED: &[&str] = &["PATH", "MANPATH", "PS1", "GIVECLASS", "QUICKTOOL_CLASS"];
+
+/// Run the setenv tool: `setenv KEY=VALUE... [--for DURATION]` sets variables for the
+/// class's commands and shells until they expire, 12 hours by default, or are unset;
+/// without arguments it lists them
+pub fn run_setenv(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let usage = || {
+        QuicktoolError::Usage(format!(
+            "usage: {} setenv KEY=VALUE... [--for DURATION, e.g. 30m, 2h or 7d]",
+            config.class
+        ))
+    };
+    let mut lifetime = DEFAULT_LIFETIME;
+    let mut assignments = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--for" {
+            lifetime = iter
+                .next()
+                .and_then(|duration| parse_duration(duration))
+                .ok_or_else(usage)?;
+            continue;
+        }
+        let (key, value) = arg.split_once('=').ok_or_else(usage)?;
+        check_key(key)?;
+        assignments.push((key, value));
+    }
+    if assignments.is_empty() {
+        return list(config);
+    }
+    let expires = now().checked_add(lifetime).ok_or_else(usage)?;
+    if config.dry_run {
+        for (key, value) in &assignments {
+            dry_run::report(&format!("would set {}={} for {}", key, value, config.class));
+        }
+        return Ok(());
+    }
+
+    let mut store = load();
+    let variables = class_variables(&mut store, &config.class);
+    for (key, value) in &assignments {
+        variables.insert(
+            key.to_string(),
+            json!({ "value": value, "expires": expires }),
+        );
+    }
+    save(&store)?;
+    for (key, value) in &assignments {
+        println!(
+            "{}={} is set for {} commands for {}",
+            key.bold(),
+            value,
+            config.class,
+            format_duration(lifetime)
+        );
+    }
+    Ok(())
+}
+
+/// Run the unsetenv tool: `unsetenv KEY...` or `unsetenv --all` removes variables set
+/// with setenv
+pub fn run_unsetenv(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    if args.is_empty() {
+        return Err(QuicktoolError::Usage(format!(
+            "usage: {} unsetenv KEY...|--all",
+            config.class
+        )));
+    }
+    if config.dry_run {
+        dry_run::report(&format!(
+            "would unset {} for {}",
+            args.join(" "),
+            config.class
+        ));
+        return Ok(());
+    }
+
+    let mut store = load();
+    let variables = class_variables(&mut store, &config.class);
+    if args.iter().any(|arg| arg == "--all") {
+        variables.clear();
+    } else {
+        for key in args {
+            if variables.remove(key).is_none() {
+                eprintln!("{} was not set with setenv", key);
+            }
+        }
+    }
+    save(&store)?;
+    Ok(())
+}
+
+/// The variables set with setenv for a class that have not expired, sorted by name
+pub fn variables(class: &str) -> Vec<(String, String)> {
+    let store = load();
+    let Some(variables) = store.get(class).and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    variables
+        .iter()
+        .filter_map(|(key, entry)| Some((key.clone(), entry["value"].as_str()?.to_string())))
+        .collect()
+}
+
+/// Set the class's session variables in quicktool's environment, for every command and
+/// shell it starts
+pub fn apply(class: &str) {
+    for (key, value) in variables(class) {
+        env::set_var(key, value);
+    }
+}
+
+/// Print the class's session variables and how long each has left
+fn list(config: &ClassConfig) -> Result<(), QuicktoolError> {
+    let store = load();
+    let now = now();
+    let variables = store
+        .get(&config.class)
+        .and_then(Value::as_object)
+        .filter(|variables| !variables.is_empty());
+    let Some(variables) = variables else {
+        println!("No variables are set for {}", config.class);
+        println!("Set one with e.g.: {} setenv DCC_OUTPUT=json", config.class);
+        return Ok(());
+    };
+    for (key, entry) in variables {
+        let left = entry["expires"].as_u64().unwrap_or(now).saturating_sub(now);
+        println!(
+            "{}={}  {}",
+            key.bold(),
+            entry["value"].as_str().unwrap_or_default(),
+            format!("({} left)", format_duration(left)).dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Refuse names that are not environment variable names, or that quicktool sets itself
+fn check_key(key: &str) -> Result<(), String> {
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("'{}' is not a variable name", key));
+    }
+    if RESERVED.contains(&key) || key.starts_with("noclass_") {
+        return Err(format!(
+            "{} is set up by quicktool for the class and cannot be changed with setenv",
+            key
+        ));
+    }
+    Ok(())
+}
+
+/// Every class's session variables, dropping any that have expired
+fn load() -> Value {
+    let mut store: Value = state::read_to_string(&state::state_dir().join(SESSION_ENV_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .filter(Value::is_object)
+        .unwrap_or_else(|| json!({}));
+    let now = now();
+    if let Some(classes) = store.as_object_mut() {
+        for variables in classes.values_mut().filter_map(Value::as_object_mut) {
+            variables.retain(|_, entry| entry["expires"].as_u64().is_some_and(|e| e > now));
+        }
+    }
+    store
+}
+
+fn save(store: &Value) -> Result<(), String> {
+    let path = state::state_dir().join(SESSION_ENV_FILE);
+    state::write(&path, store.to_string())
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// The variables of one class in the store, added if it has none
+fn class_variables<'a>(store: &'a mut Value, class: &str) -> &'a mut Map<String, Value> {
+    if !store[class].is_object() {
+        store[class] = json!({});
+    }
+    store[class].as_object_mut().expect("just made an object")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parse a duration such as `90s`, `30m`, `2h` or `7d` into seconds; None if it is not
+/// one, or too long to count in seconds
+fn parse_duration(duration: &str) -> Option<u64> {
+    let unit = match duration.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = duration[..duration.len() - 1].parse().ok()?;
+    count.checked_mul(unit).filter(|&seconds| seconds > 0)
+}
+
+/// Seconds as the largest units that fit, e.g. `2h 5m`
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s >= 24 * 60 * 60 => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+        s if s >= 60 * 60 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("30m"), Some(30 * 60));
+        assert_eq!(parse_duration("2h"), Some(2 * 60 * 60));
+        assert_eq!(parse_duration("7d"), Some(7 * 24 * 60 * 60));
+        for bad in ["", "d", "0h", "-1h", "1.5h", "2w", "3"] {
+            assert_eq!(parse_duration(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn durations_too_long_to_count() {
+        assert_eq!(parse_duration(&format!("{}s", u64::MAX)), Some(u64::MAX));
+        assert_eq!(parse_duration(&format!("{}m", u64::MAX)), None);
+        assert_eq!(parse_duration("99999999999999999999d"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use colored::Colorize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli::BUILT_IN_COMMANDS;
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::plugins;
+use crate::state;
+use crate::tools::{autotest, fetch_activity};
+
+/// Marker file in the state directory recording that the user opted in
+const OPT_IN_FILE: &str = "usage-stats-opt-in";
+
+/// Shared file, inside the class stats directory, that usage lines are appended to
+const USAGE_FILE: &str = "usage.log";
+
+/// Recorded in place of commands that are not built-ins or plugins, whose names, such as
+/// a student's own program, may identify them
+const OTHER_COMMAND: &str = "other";
+
+/// Run the stats tool: opt in or out, or, for staff, summarise the class usage counters
+pub fn run_stats(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    match args.first().map(String::as_str) {
+        Some("opt-in") => {
+            let dir = state::state_dir();
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            fs::write(dir.join(OPT_IN_FILE), "").map_err(|e| format!("Failed to opt in: {}", e))?;
+            println!(
+                "Anonymous usage counting enabled. Only command and exercise names are recorded."
+            );
+            Ok(())
+        }
+        Some("opt-out") => {
+            let marker = state::state_dir().join(OPT_IN_FILE);
+            if marker.exists() {
+                fs::remove_file(&marker).map_err(|e| format!("Failed to opt out: {}", e))?;
+            }
+            println!("Anonymous usage counting disabled.");
+            Ok(())
+        }
+        None => {
+            // The counters are for staff; anyone may choose whether to contribute
+            if config.get_custom_config("usage_stats_dir").is_none() {
+                config.require_class_config()?;
+            }
+            if !config.is_staff() {
+                return Err(config.not_staff_message("the usage report").into());
+            }
+            show_stats(config)
+        }
+        Some(other) => Err(QuicktoolError::Usage(format!(
+            "unknown stats action '{}' (expected opt-in or opt-out)",
+            other
+        ))),
+    }
+}
+
+/// Append an anonymous usage line for a command, if the user has opted in and the
+/// class publishes a stats directory. Failures are ignored so usage counting never
+/// gets in the way of the command itself.
+pub fn record_usage(config: &mut ClassConfig, args: &[String]) {
+    if !state::state_dir().join(OPT_IN_FILE).exists() {
+        return;
+    }
+    if config.get_custom_config("usage_stats_dir").is_none() && config.load_class_config().is_err()
+    {
+        return;
+    }
+    let Some(usage_file) = usage_file(config) else {
+        return;
+    };
+
+    let command = match args.first().map(String::as_str) {
+        Some(command)
+            if command == "help"
+                || BUILT_IN_COMMANDS.iter().any(|(name, _)| *name == command)
+                || plugins::find(config, command).is_some() =>
+        {
+            command
+        }
+        Some(_) => OTHER_COMMAND,
+        None => "shell",
+    };
+    let exercise = exercise_argument(args).unwrap_or("-");
+
+    // Days rather than seconds, so lines cannot be tied to a particular session
+    let day = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or(0);
+
+    if let Ok(mut file) = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(usage_file)
+    {
+        let _ = writeln!(file, "{} {} {}", day, command, exercise);
+    }
+}
+
+/// Print usage counters per command and per exercise
+fn show_stats(config: &ClassConfig) -> Result<(), QuicktoolError> {
+    let usage_file = usage_file(config)
+        .ok_or_else(|| format!("{} does not collect usage statistics", config.class))?;
+
+    let contents = fs::read_to_string(&usage_file).unwrap_or_default();
+    let mut commands: HashMap<&str, usize> = HashMap::new();
+    let mut exercises: HashMap<&str, usize> = HashMap::new();
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace().skip(1);
+        let (Some(command), Some(exercise)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        *commands.entry(command).or_default() += 1;
+        if exercise != "-" {
+            *exercises.entry(exercise).or_default() += 1;
+        }
+    }
+
+    print_counts("Commands", commands);
+    println!();
+    print_counts("Exercises", exercises);
+    Ok(())
+}
+
+/// Print a table of counts, most frequent first
+fn print_counts(heading: &str, counts: HashMap<&str, usize>) {
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    println!("{}", heading.bold());
+    if counts.is_empty() {
+        println!("  (no usage recorded)");
+    }
+    for (name, count) in counts {
+        println!("  {:>6}  {}", count, name);
+    }
+}
+
+/// Path of the shared usage file for the class, if the class collects statistics
+fn usage_file(config: &ClassConfig) -> Option<PathBuf> {
+    config
+        .get_custom_config("usage_stats_dir")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(USAGE_FILE))
+}
+
+/// Find the exercise named on the command line of autotest or fetch-activity
+fn exercise_argument(args: &[String]) -> Option<&str> {
+    let tool = args.first()?.as_str();
+    let command = match tool {
+        "fetch-activity" => fetch_activity::command(),
+        _ => {
+            autotest::commands()
+                .into_iter()
+                .find(|(name, _)| *name == tool)?
+                .1
+        }
+    };
+    // autotest-stage takes a stage prefix before the exercise
+    let skip = usize::from(tool == "autotest-stage");
+    // Options taking a value, whose value is not an exercise name
+    let takes_value: Vec<String> = command
+        .get_arguments()
+        .filter(|option| option.get_action().takes_values())
+        .filter_map(|option| option.get_long())
+        .map(|long| format!("--{}", long))
+        .collect();
+
+    let mut candidates = Vec::new();
+    let mut iter = args[1..].iter().map(String::as_str);
+    while let Some(arg) = iter.next() {
+        match arg {
+            _ if takes_value.iter().any(|option| option == arg) => {
+                iter.next();
+            }
+            _ if arg.starts_with('-') => {}
+            "1091" | "dcc" | "gcc" | "clang" => {}
+            _ => candidates.push(arg),
+        }
+    }
+    candidates.get(skip).copied()
+}
@@ -0,0 +1,70 @@
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::roster::{self, Roster, Student};
+
+/// Run the student tool, for staff (checked before dispatch): look up students in the class roster, e.g.
+/// `student z51234`, `student smith`, `student --tut H13A`, `student --tut mine`.
+///
+/// `student --complete prefix` prints only the matching zIDs, for shell completion.
+pub fn run_student(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let usage = || {
+        QuicktoolError::Usage(format!(
+            "usage: {} student [zid|name] [--tut tutorial[,tutorial...]|mine]",
+            config.class
+        ))
+    };
+
+    let mut query = None;
+    let mut tutorials = None;
+    let mut complete = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--complete" => complete = true,
+            "--tut" => {
+                let value = iter.next().ok_or_else(usage)?;
+                tutorials = Some(roster::expand_tutorials(config, value)?);
+            }
+            _ if query.is_none() && !arg.starts_with("--") => query = Some(arg.as_str()),
+            _ => return Err(usage()),
+        }
+    }
+
+    let roster = Roster::load(config)?;
+    let mut students = match query {
+        Some(query) if !complete => match roster.lookup(query) {
+            Some(student) => vec![student],
+            None => roster.search(query),
+        },
+        Some(query) => roster.search(query),
+        None if complete => roster.search("z"),
+        None if tutorials.is_some() => roster.students.iter().collect(),
+        None => return Err(usage()),
+    };
+    if let Some(tutorials) = &tutorials {
+        students.retain(|student| student.in_tutorials(tutorials));
+    }
+
+    if complete {
+        for student in students {
+            println!("{}", student.zid);
+        }
+        return Ok(());
+    }
+    if students.is_empty() {
+        return Err(match query {
+            Some(query) => format!("no student matches '{}'", query),
+            None => "no students in those tutorials".to_string(),
+        }
+        .into());
+    }
+    print_students(&students);
+    Ok(())
+}
+
+/// Print students one per line
+fn print_students(students: &[&Student]) {
+    for student in students {
+        println!("{}  {:<30} {}", student.zid, student.name, student.tutorial);
+    }
+}
@@ -0,0 +1,254 @@
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use regex::Regex;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use walkdir::WalkDir;
+
+use crate::cli;
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::exit_codes;
+use crate::manifest::ActivityManifest;
+use crate::metadata::{FetchRecord, METADATA_DIR};
+use crate::output::{self, Plumbing, Stream};
+use crate::signals::{self, Work};
+use crate::tools::fetch_activity;
+
+/// How deep below the workspace to look for fetched exercises
+const MAX_DEPTH: usize = 4;
+
+/// Most exercises autotested at once unless `--jobs` says otherwise, so a sweep
+/// doesn't monopolise a shared lab machine
+const DEFAULT_MAX_JOBS: usize = 4;
+
+/// An exercise fetched somewhere in the workspace
+struct Exercise {
+    activity: String,
+    dir: PathBuf,
+}
+
+/// Result of autotesting one exercise
+struct Outcome {
+    passed: usize,
+    total: usize,
+    /// Whether autotest ran to completion without failures
+    success: bool,
+}
+
+/// Options of the test-all tool
+#[derive(Parser)]
+struct TestAllArgs {
+    /// Most exercises to autotest at once
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+    /// Only test exercises from this week, as their manifest.sh sets it
+    week: Option<String>,
+}
+
+/// The options of test-all, for documentation
+pub fn command() -> clap::Command {
+    TestAllArgs::command()
+}
+
+/// Run the test-all tool: autotest every exercise fetched under the working directory
+/// and print how each one did, e.g. `test-all [--jobs N] [week]`.
+///
+/// Exercises are tested concurrently, each by its own autotest process in its own
+/// directory. A week only includes exercises whose manifest.sh sets that `week`.
+pub fn run_test_all(config: &mut ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let options: TestAllArgs = cli::parse_tool_args(&config.class, "test-all", args)?;
+    let week = options.week.as_deref();
+    let jobs = options.jobs.map(NonZeroUsize::get);
+
+    let mut exercises = find_exercises(Path::new("."));
+    if let Some(week) = week {
+        exercises.retain(|exercise| {
+            fetch_activity::locate_activity(config, &exercise.activity)
+                .ok()
+                .and_then(|dir| ActivityManifest::load(&dir))
+                .is_some_and(|manifest| manifest.get("week") == Some(week))
+        });
+    }
+    if exercises.is_empty() {
+        return Err(match week {
+            Some(week) => format!("no exercises from week {} have been fetched here", week),
+            None => "no fetched exercises found below this directory".to_string(),
+        }
+        .into());
+    }
+
+    // The autotests run at the class's priority, and with --nice so do the ones they start
+    config.require_class_config()?;
+    signals::set_priority(config.priority()?);
+    let mut class_args = vec![config.class.clone()];
+    if let Some(nice) = config.nice {
+        class_args.push(format!("--nice={}", nice));
+    }
+
+    let exe = env::current_exe().map_err(|e| format!("Cannot find quicktool itself: {}", e))?;
+    let jobs = jobs
+        .unwrap_or_else(|| {
+            thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                .min(DEFAULT_MAX_JOBS)
+        })
+        .min(exercises.len());
+    let total = exercises.len();
+    let outcomes = test_concurrently(&exe, &class_args, exercises, jobs);
+
+    let progress = io::stderr().is_terminal();
+    if progress {
+        eprint!("[0/{}] exercises tested", total);
+    }
+    let mut results = Vec::new();
+    for (done, result) in outcomes.enumerate() {
+        results.push(result);
+        if progress {
+            eprint!("\r\x1b[K");
+            if done + 1 < total {
+                eprint!("[{}/{}] exercises tested", done + 1, total);
+            }
+        }
+    }
+
+    // Report in workspace order rather than the order the exercises happened to finish,
+    // so runs can be compared
+    results.sort_by(|(a, _), (b, _)| a.dir.cmp(&b.dir).then(a.activity.cmp(&b.activity)));
+    let mut passed = 0;
+    for (exercise, outcome) in &results {
+        match outcome {
+            Ok(outcome) => {
+                passed += usize::from(outcome.success);
+                print_outcome(exercise, outcome);
+            }
+            Err(e) => println!(
+                "{:<24} {:>8}  {}",
+                exercise.activity,
+                "error".red().bold(),
+                e
+            ),
+        }
+    }
+
+    println!("{} of {} exercises passed", passed, total);
+    if passed < total {
+        exit(exit_codes::FAILURE);
+    }
+    Ok(())
+}
+
+/// Autotest `exercises` with at most `jobs` running at once, yielding each exercise
+/// with its outcome as it finishes
+fn test_concurrently(
+    exe: &Path,
+    class_args: &[String],
+    exercises: Vec<Exercise>,
+    jobs: usize,
+) -> mpsc::IntoIter<(Exercise, Result<Outcome, String>)> {
+    let queue = Arc::new(Mutex::new(exercises.into_iter()));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..jobs {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+        let exe = exe.to_path_buf();
+        let class_args = class_args.to_vec();
+        thread::spawn(move || {
+            // Take the lock only long enough to claim the next exercise
+            let next = || queue.lock().ok()?.next();
+            while let Some(exercise) = next() {
+                let outcome = run_exercise(&exe, &class_args, &exercise);
+                if sender.send((exercise, outcome)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    receiver.into_iter()
+}
+
+/// Every exercise recorded in a metadata directory below `root`
+fn find_exercises(root: &Path) -> Vec<Exercise> {
+    let mut exercises = Vec::new();
+    let walker = WalkDir::new(root)
+        .max_depth(MAX_DEPTH)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != METADATA_DIR);
+    for entry in walker.flatten() {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        for record in FetchRecord::list(entry.path()) {
+            exercises.push(Exercise {
+                activity: record.activity,
+                dir: entry.path().to_path_buf(),
+            });
+        }
+    }
+    exercises
+}
+
+/// Autotest one exercise in its own directory, without showing the output
+fn run_exercise(exe: &Path, class_args: &[String], exercise: &Exercise) -> Result<Outcome, String> {
+    let output = output::run(
+        Command::new(exe)
+            .args(class_args)
+            .args(["autotest", &exercise.activity])
+            .current_dir(&exercise.dir),
+        &Plumbing {
+            stdout: Stream::Capture,
+            stderr: Stream::Capture,
+            log: None,
+            work: Work::Heavy,
+        },
+    )
+    .map_err(|e| format!("Failed to run autotest: {}", e))?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let count = |pattern: &str| -> usize {
+        Regex::new(pattern)
+            .expect("valid count pattern")
+            .captures(&text)
+            .and_then(|caps| caps[1].parse().ok())
+            .unwrap_or(0)
+    };
+    let passed = count(r"(\d+) tests? passed");
+    let failed = count(r"(\d+) tests? failed");
+    Ok(Outcome {
+        passed,
+        total: passed + failed,
+        success: output.status.success(),
+    })
+}
+
+/// Print one row of the results table
+fn print_outcome(exercise: &Exercise, outcome: &Outcome) {
+    let ratio = if outcome.total > 0 {
+        format!("{}/{}", outcome.passed, outcome.total)
+    } else if outcome.success {
+        "passed".to_string()
+    } else {
+        "error".to_string()
+    };
+    let ratio = if outcome.success {
+        ratio.green().bold()
+    } else if outcome.passed > 0 {
+        ratio.yellow().bold()
+    } else {
+        ratio.red().bold()
+    };
+    println!(
+        "{:<24} {:>8}  {}",
+        exercise.activity,
+        ratio,
+        exercise.dir.display()
+    );
+}
@@ -0,0 +1,216 @@
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::QuicktoolError;
+use crate::metadata::FetchRecord;
+use crate::temp::TempDir;
+use crate::tools::edit::primary_files;
+use crate::trace;
+
+/// Directory of an activity holding staff unit tests, as `test_*.c` files
+const UNIT_TEST_DIR: &str = "unit_tests";
+
+/// The assert-reporting harness the unit tests include
+const HARNESS: &str = include_str!("quicktool_test.h");
+
+/// Name the student's main is given so the harness's main runs instead
+const RENAMED_MAIN: &str = "quicktool_student_main";
+
+/// Most lines of compiler output shown for a test file that does not compile
+const MAX_COMPILE_LINES: usize = 20;
+
+/// Outcome of one unit test
+#[derive(Debug)]
+pub struct UnitTestResult {
+    pub name: String,
+    /// The `test_*.c` file the test is in
+    pub file: String,
+    pub passed: bool,
+    pub details: Vec<String>,
+}
+
+impl UnitTestResult {
+    /// The result in autotest's style, e.g. `Test insert_head (test_list.c) - failed`
+    fn line(&self) -> String {
+        let outcome = if self.passed { "passed" } else { "failed" };
+        format!("Test {} ({}) - {}", self.name, self.file, outcome)
+    }
+}
+
+/// The `test_*.c` files of an activity, sorted
+pub fn unit_test_files(activity_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(activity_dir.join(UNIT_TEST_DIR)) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("test_") && name.ends_with(".c")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Compile each of the activity's unit test files with the student's code in the working
+/// directory using `compile` (a compiler and any launcher in front of it), and run them
+pub fn run_unit_tests(
+    activity: &str,
+    activity_dir: &Path,
+    compile: &[String],
+) -> Result<Vec<UnitTestResult>, QuicktoolError> {
+    let test_files = unit_test_files(activity_dir);
+    if test_files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let record = FetchRecord::load(Path::new("."), activity);
+    let sources: Vec<String> = primary_files(activity, record.as_ref())
+        .into_iter()
+        .filter(|file| file.ends_with(".c"))
+        .collect();
+
+    let build_dir = TempDir::new("unit-tests")?;
+    fs::write(build_dir.path().join("quicktool_test.h"), HARNESS)
+        .map_err(|e| format!("Failed to write the unit test harness: {}", e))?;
+
+    let mut results = Vec::new();
+    for test_file in &test_files {
+        results.extend(run_test_file(
+            test_file,
+            &sources,
+            compile,
+            build_dir.path(),
+        ));
+    }
+    Ok(results)
+}
+
+/// Build and run one test file, or fail it as a whole if it does not compile
+fn run_test_file(
+    test_file: &Path,
+    sources: &[String],
+    compile: &[String],
+    build_dir: &Path,
+) -> Vec<UnitTestResult> {
+    let file = test_file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let binary = build_dir.join(file.trim_end_matches(".c"));
+    let failed = |details: Vec<String>| {
+        vec![UnitTestResult {
+            name: file.trim_end_matches(".c").to_string(),
+            file: file.clone(),
+            passed: false,
+            details,
+        }]
+    };
+
+    let mut command = Command::new(&compile[0]);
+    command
+        .args(&compile[1..])
+        .arg(format!("-Dmain={}", RENAMED_MAIN))
+        .arg("-I")
+        .arg(build_dir)
+        .arg("-I")
+        .arg(".")
+        .args(sources)
+        .arg(test_file)
+        .arg("-o")
+        .arg(&binary);
+    trace::command(&command);
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => return failed(vec![format!("could not run {}: {}", compile[0], e)]),
+    };
+    if !output.status.success() {
+        let errors = String::from_utf8_lossy(&output.stderr);
+        let mut details: Vec<String> = errors.lines().map(str::to_string).collect();
+        if details.len() > MAX_COMPILE_LINES {
+            details.truncate(MAX_COMPILE_LINES);
+            details.push("...".to_string());
+        }
+        details.insert(0, "did not compile with your code:".to_string());
+        return failed(details);
+    }
+
+    let mut command = Command::new(&binary);
+    trace::command(&command);
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => return failed(vec![format!("could not run the tests: {}", e)]),
+    };
+    parse_results(&file, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read the harness's report lines, ignoring anything the student's code printed
+fn parse_results(file: &str, output: &str) -> Vec<UnitTestResult> {
+    let mut results = Vec::new();
+    let mut details = Vec::new();
+    for line in output.lines() {
+        if let Some(detail) = line.strip_prefix("quicktool-detail ") {
+            details.push(detail.to_string());
+        } else if let Some(result) = line.strip_prefix("quicktool-test ") {
+            let mut words = result.splitn(3, ' ');
+            let name = words.next().unwrap_or_default().to_string();
+            let passed = words.next() == Some("passed");
+            if let Some(reason) = words.next() {
+                details.push(reason.to_string());
+            }
+            results.push(UnitTestResult {
+                name,
+                file: file.to_string(),
+                passed,
+                details: std::mem::take(&mut details),
+            });
+        }
+    }
+    results
+}
+
+/// Print the results after autotest's own
+pub fn print_results(results: &[UnitTestResult]) {
+    for result in results {
+        let line = result.line();
+        if result.passed {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line.red());
+            for detail in &result.details {
+                println!("    {}", detail);
+            }
+        }
+    }
+    println!("{}", summary(results).bold());
+}
+
+/// The results as plain text, for reports built from autotest's output
+pub fn results_text(results: &[UnitTestResult]) -> String {
+    let mut text = String::new();
+    for result in results {
+        text.push_str(&result.line());
+        text.push('\n');
+        if !result.passed {
+            for detail in &result.details {
+                text.push_str(&format!("    {}\n", detail));
+            }
+        }
+    }
+    text.push_str(&summary(results));
+    text.push('\n');
+    text
+}
+
+fn summary(results: &[UnitTestResult]) -> String {
+    let passed = results.iter().filter(|result| result.passed).count();
+    format!(
+        "Unit tests: {} passed, {} failed",
+        passed,
+        results.len() - passed
+    )
+}
@@ -0,0 +1,47 @@
+use colored::Colorize;
+use serde_json::json;
+
+use crate::error::QuicktoolError;
+use crate::features;
+
+/// Run the version tool: `quicktool version [--json]` shows the version, the commit and
+/// date it was built from, the platform and the features this build provides
+pub fn run_version(args: &[String]) -> Result<i32, QuicktoolError> {
+    let json = match args {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => {
+            return Err(QuicktoolError::Usage(
+                "usage: quicktool version [--json]".to_string(),
+            ))
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            json!({
+                "version": features::version(),
+                "commit": features::git_commit(),
+                "build_date": features::build_date(),
+                "target": features::target(),
+                "features": features::FEATURES,
+            })
+        );
+        return Ok(0);
+    }
+
+    println!("quicktool {}", features::long_version().bold());
+    println!("target: {}", features::target());
+    let mut line = String::from("features:");
+    for feature in features::FEATURES {
+        if line.len() + feature.len() + 1 > 78 {
+            println!("{}", line);
+            line = String::from("   ");
+        }
+        line.push(' ');
+        line.push_str(feature);
+    }
+    println!("{}", line);
+    Ok(0)
+}
@@ -0,0 +1,134 @@
+use colored::*;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::cli::BUILT_IN_COMMANDS;
+use crate::config::ClassConfig;
+use crate::error::QuicktoolError;
+use crate::paths::is_executable;
+use crate::plugins;
+use crate::shell_quote;
+
+/// Most symlinks followed from one program, as the kernel allows
+const MAX_LINKS: usize = 40;
+
+/// Run the which tool: show the program a name runs in the class environment, through
+/// the class bin directory and anything .newclassrc adds to PATH, following symlinks,
+/// and the programs of the same name it shadows, e.g. `which gcc`
+pub fn run_which(config: &ClassConfig, args: &[String]) -> Result<(), QuicktoolError> {
+    let [name] = args else {
+        return Err(QuicktoolError::Usage(format!(
+            "usage: {} which tool",
+            config.class
+        )));
+    };
+
+    if BUILT_IN_COMMANDS.iter().any(|(command, _)| command == name) && config.tool_enabled(name) {
+        println!(
+            "{} {} runs quicktool's built-in {}; in the class shell, {} is:",
+            config.class, name, name, name
+        );
+    } else if let Some(plugin) = plugins::find(config, name) {
+        println!(
+            "{} {} runs the plugin {}; in the class shell, {} is:",
+            config.class,
+            name,
+            plugin.display(),
+            name
+        );
+    }
+
+    if name.contains('/') {
+        if !is_executable(Path::new(name)) {
+            return Err(format!("{} is not an executable file", name).into());
+        }
+        print_program(Path::new(name), None, true);
+        return Ok(());
+    }
+
+    let class_path: Vec<PathBuf> =
+        env::split_paths(&env::var_os("PATH").unwrap_or_default()).collect();
+    let path = match newclassrc_path(config) {
+        Some(path) => env::split_paths(&path).collect(),
+        None => class_path.clone(),
+    };
+    let mut found: Vec<(PathBuf, &str)> = Vec::new();
+    for dir in &path {
+        let program = dir.join(name);
+        if !is_executable(&program) || found.iter().any(|(seen, _)| *seen == program) {
+            continue;
+        }
+        let origin = if config.bin_path.as_deref().map(Path::new) == Some(dir.as_path()) {
+            "class bin"
+        } else if !class_path.contains(dir) {
+            ".newclassrc"
+        } else {
+            "PATH"
+        };
+        found.push((program, origin));
+    }
+
+    let Some(((program, origin), shadowed)) = found.split_first() else {
+        return Err(format!("{} is not on the {} PATH", name, config.class).into());
+    };
+    print_program(program, Some(origin), true);
+    if !shadowed.is_empty() {
+        println!("{}", "Shadowed:".bold());
+        for (program, origin) in shadowed {
+            print_program(program, Some(origin), false);
+        }
+    }
+    Ok(())
+}
+
+/// PATH once .newclassrc has been sourced, as commands in the class environment see it,
+/// if the class has one
+fn newclassrc_path(config: &ClassConfig) -> Option<OsString> {
+    let newclassrc = config
+        .newclassrc_path
+        .as_ref()
+        .filter(|_| config.has_newclassrc())?;
+    let shell = env::var("SHELL").unwrap_or_else(|_| String::from("/bin/bash"));
+    let output = Command::new(shell)
+        .arg("-c")
+        .arg(format!(
+            "source {} >/dev/null 2>&1; printf %s \"$PATH\"",
+            shell_quote::quote(newclassrc)
+        ))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    Some(OsString::from_vec(output.stdout)).filter(|path| !path.is_empty())
+}
+
+/// Print a program with where it came from and each symlink it goes through
+fn print_program(program: &Path, origin: Option<&str>, runs: bool) {
+    let origin = origin
+        .map(|origin| format!("  ({})", origin).dimmed().to_string())
+        .unwrap_or_default();
+    let shown = program.display().to_string();
+    if runs {
+        println!("{}{}", shown.green().bold(), origin);
+    } else {
+        println!("  {}{}", shown, origin);
+    }
+
+    let indent = if runs { "  " } else { "    " };
+    let mut current = program.to_path_buf();
+    for _ in 0..MAX_LINKS {
+        let Ok(target) = fs::read_link(&current) else {
+            return;
+        };
+        current = match current.parent() {
+            Some(dir) if target.is_relative() => dir.join(&target),
+            _ => target,
+        };
+        println!("{}-> {}", indent, current.display());
+    }
+    println!("{}-> {}", indent, "(too many symlinks)".red());
+}
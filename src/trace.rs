@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+/// Environment variable naming the file that trace lines are appended to
+pub const TRACE_ENV: &str = "QUICKTOOL_TRACE";
+
+/// Whether this invocation is being traced for a bug report
+pub fn enabled() -> bool {
+    env::var_os(TRACE_ENV).is_some()
+}
+
+/// Record a subprocess command line about to be run
+pub fn command(command: &Command) {
+    write_line(&format!("run: {:?}", command));
+}
+
+/// Take a snapshot of the environment, so changes can be recorded later
+pub fn snapshot_env() -> HashMap<String, String> {
+    if enabled() {
+        env::vars().collect()
+    } else {
+        HashMap::new()
+    }
+}
+
+/// Record the environment variables changed since a snapshot
+pub fn env_changes(before: &HashMap<String, String>) {
+    if !enabled() {
+        return;
+    }
+
+    let mut changes: Vec<String> = env::vars()
+        .filter(|(key, value)| before.get(key) != Some(value))
+        .map(|(key, value)| match before.get(&key) {
+            Some(old) => format!("env: {}={} (was {})", key, value, old),
+            None => format!("env: {}={} (new)", key, value),
+        })
+        .collect();
+    changes.sort();
+    for change in changes {
+        write_line(&change);
+    }
+}
+
+/// Append a line to the trace file, ignoring failures
+fn write_line(line: &str) {
+    let Some(path) = env::var_os(TRACE_ENV) else {
+        return;
+    };
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
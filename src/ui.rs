@@ -0,0 +1,186 @@
+use colored::*;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+/// Most choices listed at once; typing narrows the list
+const MAX_SHOWN: usize = 10;
+
+/// Whether the user can be asked to choose, i.e. stdin and stderr are a terminal
+pub fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// A key the picker understands
+enum Key {
+    Up,
+    Down,
+    Enter,
+    Backspace,
+    Cancel,
+    Char(char),
+    Other,
+}
+
+/// Let the user choose one of `choices` with the arrow keys, typing to filter them by
+/// the letters they contain in order. The list is drawn on stderr.
+///
+/// None if the user cancels with Esc or Ctrl-C, or the terminal cannot be read key by
+/// key.
+pub fn pick(title: &str, choices: &[String]) -> Option<String> {
+    let _raw = RawMode::enable()?;
+    let mut input = io::stdin().lock();
+    let mut filter = String::new();
+    let mut selected = 0;
+    let mut drawn = 0;
+
+    loop {
+        let matches: Vec<&String> = choices
+            .iter()
+            .filter(|choice| fuzzy_match(&filter, choice))
+            .collect();
+        selected = selected.min(matches.len().saturating_sub(1));
+        drawn = draw(title, &filter, &matches, selected, drawn);
+
+        match read_key(&mut input)? {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down if selected + 1 < matches.len() => selected += 1,
+            Key::Enter if !matches.is_empty() => {
+                clear(drawn);
+                return Some(matches[selected].clone());
+            }
+            Key::Backspace => {
+                filter.pop();
+            }
+            Key::Char(c) => {
+                filter.push(c);
+                selected = 0;
+            }
+            Key::Cancel => {
+                clear(drawn);
+                return None;
+            }
+            Key::Down | Key::Enter | Key::Other => {}
+        }
+    }
+}
+
+/// Draw the picker over the lines drawn last time, returning how many lines it used
+fn draw(title: &str, filter: &str, matches: &[&String], selected: usize, drawn: usize) -> usize {
+    let mut screen = String::new();
+    if drawn > 0 {
+        screen.push_str(&format!("\r\x1b[{}A\x1b[J", drawn));
+    }
+    // The terminal is raw, so every line needs its own carriage return
+    screen.push_str(&format!(
+        "{} {}\r\n",
+        title.bold(),
+        "(↑/↓ to move, Enter to choose, Esc to cancel)".dimmed()
+    ));
+    screen.push_str(&format!("> {}\r\n", filter));
+    let first = selected.saturating_sub(MAX_SHOWN - 1);
+    for (index, choice) in matches.iter().enumerate().skip(first).take(MAX_SHOWN) {
+        if index == selected {
+            screen.push_str(&format!("{} {}\r\n", ">".green().bold(), choice.reversed()));
+        } else {
+            screen.push_str(&format!("  {}\r\n", choice));
+        }
+    }
+    if matches.is_empty() {
+        screen.push_str(&format!("  {}\r\n", "no matches".dimmed()));
+    }
+    let mut stderr = io::stderr();
+    let _ = stderr.write_all(screen.as_bytes());
+    let _ = stderr.flush();
+    screen.matches("\r\n").count()
+}
+
+/// Erase the picker from the terminal
+fn clear(drawn: usize) {
+    if drawn > 0 {
+        eprint!("\r\x1b[{}A\x1b[J", drawn);
+    }
+}
+
+/// Read one key press, or None if stdin has closed
+fn read_key(input: &mut impl Read) -> Option<Key> {
+    let byte = loop {
+        // Reads time out so a lone Esc can be told apart from an escape sequence
+        if let Some(byte) = read_byte(input)? {
+            break byte;
+        }
+    };
+    Some(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 => Key::Cancel,
+        0x10 => Key::Up,
+        0x0e => Key::Down,
+        0x1b => match read_byte(input)? {
+            None => Key::Cancel,
+            Some(b'[') | Some(b'O') => match read_byte(input)? {
+                Some(b'A') => Key::Up,
+                Some(b'B') => Key::Down,
+                _ => Key::Other,
+            },
+            Some(_) => Key::Other,
+        },
+        byte if byte.is_ascii_graphic() || byte == b' ' => Key::Char(byte as char),
+        _ => Key::Other,
+    })
+}
+
+/// Read one byte: Some(None) if none arrived in time, None if stdin has closed or failed
+fn read_byte(input: &mut impl Read) -> Option<Option<u8>> {
+    let mut buffer = [0u8; 1];
+    match input.read(&mut buffer) {
+        Ok(0) => Some(None),
+        Ok(_) => Some(Some(buffer[0])),
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => Some(None),
+        Err(_) => None,
+    }
+}
+
+/// Whether every character of `filter` appears in `choice` in order, ignoring case
+fn fuzzy_match(filter: &str, choice: &str) -> bool {
+    let mut rest = choice.chars().flat_map(char::to_lowercase);
+    filter
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| rest.any(|c| c == wanted))
+}
+
+/// The terminal switched to reading key by key without echo, through stty, until dropped
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        // Reads give up after 0.2s, so a lone Esc is noticed
+        stty(&["raw", "-echo", "min", "0", "time", "2"])?;
+        Some(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Run stty on the terminal on stdin, returning what it printed if it succeeded
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
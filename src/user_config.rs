@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use crate::config::parse_class_code;
+use crate::state;
+use crate::toml_config;
+
+/// File in the config directory where users kept their settings as `name=value` lines,
+/// before config.toml
+const OLD_USER_CONFIG_FILE: &str = "config";
+
+static OLD_FILE_NOTICE: Once = Once::new();
+
+/// Settings a user keeps for themselves, in the `[user]` section of their config.toml.
+///
+/// A setting can be made for one class by prefixing the class name, e.g.
+/// `COMP1511_compiler = "dcc"`, or for every class, e.g. `compiler = "gcc"`. See
+/// `toml_config` for how these files fit together.
+#[derive(Debug, Default)]
+pub struct UserConfig {
+    pub path: PathBuf,
+    values: HashMap<String, String>,
+}
+
+impl UserConfig {
+    /// Load the user's settings; a missing or unreadable file sets nothing. Problems
+    /// with the file are reported when a class's settings are merged with it.
+    pub fn load() -> Self {
+        let path = state::config_dir().join(toml_config::USER_FILE_NAME);
+        let values = match fs::read_to_string(&path) {
+            Ok(contents) => toml_config::parse(&path.to_string_lossy(), &contents, true)
+                .user
+                .into_iter()
+                .collect(),
+            Err(_) => {
+                let old = state::config_dir().join(OLD_USER_CONFIG_FILE);
+                if old.is_file() {
+                    OLD_FILE_NOTICE.call_once(|| {
+                        eprintln!(
+                            "quicktool: {} is no longer read; move its settings to the [user] \
+                             section of {}, e.g. compiler = \"dcc\"",
+                            old.display(),
+                            path.display()
+                        )
+                    });
+                }
+                HashMap::new()
+            }
+        };
+        UserConfig { path, values }
+    }
+
+    /// A setting for `class`, falling back to the setting for every class
+    pub fn class_setting(&self, class: &str, name: &str) -> Option<&str> {
+        self.values
+            .get(&format!("{}_{}", class, name))
+            .or_else(|| self.values.get(name))
+            .map(String::as_str)
+    }
+
+    /// The class quicktool uses when none is given, e.g. `quicktool autotest lab03`, set
+    /// with `default_class = "1511"`; a value that is not a class code is ignored with a
+    /// warning
+    pub fn default_class(&self) -> Option<String> {
+        let code = self.values.get("default_class")?;
+        if parse_class_code(code).is_none() {
+            eprintln!(
+                "quicktool: ignoring default_class = \"{}\" in {}: not a class code",
+                code,
+                self.path.display()
+            );
+            return None;
+        }
+        Some(code.clone())
+    }
+}